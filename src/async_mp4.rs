@@ -0,0 +1,371 @@
+//! Async MP4 reader over any byte source implementing
+//! `tokio::io::AsyncRead + AsyncSeek`. Requires the `async` feature.
+//!
+//! Only the `moov` atom is read into memory. It is parsed into the
+//! same model as for the synchronous `Mp4`, meaning track attributes
+//! and the atom parsers in `atom_types` are shared between the two.
+//! Sample data and atoms outside `moov` are read asynchronously.
+//!
+//! ```rs
+//! use mp4iter::AsyncMp4;
+//! use std::path::Path;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), mp4iter::Mp4Error> {
+//!     let mut mp4 = AsyncMp4::open(Path::new("GOPRO_VIDEO.MP4")).await?;
+//!
+//!     // Atom parsers are the same as for `Mp4`
+//!     let ftyp = mp4.find_atom("ftyp", true).await?.ftyp()?;
+//!     println!("{:?}", ftyp.major_brand());
+//!
+//!     // Synchronous methods over the in-memory `moov` atom
+//!     println!("{:?}", mp4.moov().duration(true)?);
+//!
+//!     let mut track = mp4.track("GoPro MET", true)?;
+//!     let mut samples = track.samples();
+//!     while let Some(result) = samples.next().await {
+//!         let sample = result?;
+//!         println!("{:?} {} bytes", sample.relative(), sample.len());
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use std::{
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    ops::Range,
+    path::{Path, PathBuf},
+    slice::Iter,
+};
+
+use time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+use crate::{
+    reader::AtomReadOrigin,
+    support::seek_pos,
+    track::ParsableTrackId,
+    Atom,
+    AtomHeader,
+    FourCC,
+    MoovBuffer,
+    Mp4,
+    Mp4Error,
+    Mp4Reader,
+    Sample,
+    SampleOffset,
+    TargetReader,
+    TrackAttributes,
+};
+
+/// In-memory window over a byte range of an MP4,
+/// with positions corresponding to the full MP4.
+///
+/// Serves as the synchronous reader for `AsyncMp4`,
+/// so that atoms read asynchronously can be parsed
+/// with the same parsers as for `Mp4`.
+/// Reading outside the window raises an error.
+#[derive(Debug, Default, Clone)]
+pub struct Window {
+    /// Absolute position for the first byte in `buf`.
+    offset: u64,
+    /// Size of the full MP4 in bytes.
+    len: u64,
+    /// Current absolute position.
+    pos: u64,
+    buf: Vec<u8>,
+}
+
+impl Window {
+    fn new(offset: u64, buf: Vec<u8>, len: u64) -> Self {
+        Self {
+            offset,
+            len,
+            pos: offset,
+            buf,
+        }
+    }
+
+    /// Absolute byte range that is loaded into memory.
+    pub fn range(&self) -> Range<u64> {
+        self.offset .. self.offset + self.buf.len() as u64
+    }
+}
+
+impl Read for Window {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.len {
+            return Ok(0);
+        }
+        if !self.range().contains(&self.pos) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Position {} is outside loaded range {:?}", self.pos, self.range()),
+            ));
+        }
+        let start = (self.pos - self.offset) as usize;
+        let n = buf.len().min(self.buf.len() - start);
+        buf[..n].copy_from_slice(&self.buf[start .. start + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for Window {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = seek_pos(self.pos, self.len, pos)?;
+        Ok(self.pos)
+    }
+}
+
+/// Async MP4 reader.
+///
+/// Generic over any byte source implementing
+/// `tokio::io::AsyncRead + AsyncSeek`.
+#[derive(Debug)]
+pub struct AsyncMp4<R> {
+    /// Async reader over the full MP4.
+    reader: R,
+    /// Size of the MP4 in bytes.
+    len: u64,
+    /// Current position for atom header search.
+    pos: u64,
+    /// Synchronous model with the `moov` atom in memory,
+    /// shared with `Mp4`.
+    model: Mp4<Window>,
+}
+
+impl AsyncMp4<tokio::fs::File> {
+    /// New `AsyncMp4` from path.
+    pub async fn open(path: &Path) -> Result<Self, Mp4Error> {
+        let file = tokio::fs::File::open(path).await?;
+        let mut mp4 = Self::from_reader(file).await?;
+        mp4.model.path = Some(path.to_owned());
+        Ok(mp4)
+    }
+}
+
+impl <R: AsyncRead + AsyncSeek + Unpin> AsyncMp4<R> {
+    /// New `AsyncMp4` from any reader implementing
+    /// `tokio::io::AsyncRead + AsyncSeek`.
+    ///
+    /// Reads the `moov` atom into memory.
+    pub async fn from_reader(mut reader: R) -> Result<Self, Mp4Error> {
+        let len = reader.seek(SeekFrom::End(0)).await?;
+        let model = Mp4 {
+            path: None,
+            reader: Mp4Reader::without_moov(Window::new(0, Vec::new(), len), None)?,
+        };
+
+        let mut mp4 = Self {
+            reader,
+            len,
+            pos: 0,
+            model,
+        };
+
+        let moov_hdr = mp4.find_header("moov", true).await?
+            .ok_or(Mp4Error::MoovReadError)?;
+        let moov = mp4.read_at(
+            moov_hdr.data_offset(),
+            usize::try_from(moov_hdr.data_size())?
+        ).await?;
+        mp4.model.reader.set_moov(moov_hdr, MoovBuffer::Owned(moov));
+        mp4.pos = 0;
+
+        Ok(mp4)
+    }
+
+    /// Mp4 size in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the MP4 is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Mp4 file path.
+    /// Returns `None` if created from a reader.
+    pub fn path(&self) -> Option<PathBuf> {
+        self.model.path()
+    }
+
+    /// Returns the synchronous model over the in-memory `moov` atom,
+    /// e.g. for `Mp4::mvhd()`, `Mp4::track_list()`, or `Mp4::duration()`.
+    ///
+    /// Note that methods reading outside the `moov` atom,
+    /// such as `Mp4::ftyp()` or `Track::samples()`, will raise an error.
+    /// Use the corresponding `AsyncMp4` methods instead.
+    pub fn moov(&mut self) -> &mut Mp4<Window> {
+        &mut self.model
+    }
+
+    /// Returns the underlying async reader over the entire MP4.
+    pub fn file_reader(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Reads `len` bytes at absolute position `pos`.
+    async fn read_at(&mut self, pos: u64, len: usize) -> Result<Vec<u8>, Mp4Error> {
+        self.reader.seek(SeekFrom::Start(pos)).await?;
+        let mut buf = vec![0_u8; len];
+        self.reader.read_exact(&mut buf).await?;
+        Ok(buf)
+    }
+
+    /// Reads `len` bytes at absolute position `pos`
+    /// into the synchronous model's window.
+    async fn load(&mut self, pos: u64, len: usize) -> Result<(), Mp4Error> {
+        let buf = self.read_at(pos, len).await?;
+        self.model.reader.file_reader = BufReader::new(Window::new(pos, buf, self.len));
+        Ok(())
+    }
+
+    /// Returns atom header at absolute position `pos`.
+    async fn header_at(&mut self, pos: u64) -> Result<AtomHeader, Mp4Error> {
+        // 32 bytes covers 64-bit atom sizes and 'uuid' user types
+        let len = (self.len - pos).min(32);
+        self.load(pos, len as usize).await?;
+        self.model.reader.header(&TargetReader::File, Some(SeekFrom::Start(pos)))
+    }
+
+    /// Finds first atom header with specified FourCC (as string literal).
+    /// Container atoms are descended into.
+    ///
+    /// If found, the position is set to the atom's data payload.
+    ///
+    /// If `reset` = `true`, the search will start from the beginning of the MP4.
+    pub async fn find_header(&mut self, fourcc: &str, reset: bool) -> Result<Option<AtomHeader>, Mp4Error> {
+        if reset {
+            self.pos = 0;
+        }
+
+        let fourcc = FourCC::from_str(fourcc);
+
+        while self.pos < self.len {
+            let header = self.header_at(self.pos).await?;
+
+            // `next` is 0 for containers, i.e. continue search
+            // with the first child atom
+            self.pos = header.data_offset() + header.next;
+
+            if header.name == fourcc {
+                self.pos = header.data_offset();
+                return Ok(Some(header));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns first encountered atom with specified FourCC,
+    /// with the full atom read into memory.
+    ///
+    /// Note that e.g. the `mdat` atom may be many GB in size.
+    pub async fn find_atom(&mut self, fourcc_name: &str, reset: bool) -> Result<Atom<'_, Window>, Mp4Error> {
+        let header = self.find_header(fourcc_name, reset).await?
+            .ok_or_else(|| Mp4Error::NoSuchAtom(fourcc_name.to_owned()))?;
+        self.load(header.offset, usize::try_from(header.atom_size)?).await?;
+        self.model.reader.atom(&TargetReader::File, AtomReadOrigin::Header(header), true)
+    }
+
+    /// Returns the track with specified identifier.
+    /// See `Mp4::track()`.
+    pub fn track(&mut self, identifier: impl ParsableTrackId, reset: bool) -> Result<AsyncTrack<'_, R>, Mp4Error> {
+        let attributes = TrackAttributes::new(&mut self.model, identifier.to_trackid(), reset)?;
+        Ok(AsyncTrack {
+            attributes,
+            reader: &mut self.reader,
+        })
+    }
+
+    /// Returns general info for all track in the MP4 file.
+    pub fn track_list(&mut self, reset: bool) -> Result<Vec<TrackAttributes>, Mp4Error> {
+        self.model.track_list(reset)
+    }
+
+    /// Returns duration of the longest track.
+    /// See `Mp4::duration()`.
+    pub fn duration(&mut self, reset: bool) -> Result<Duration, Mp4Error> {
+        self.model.duration(reset)
+    }
+}
+
+/// Async MP4 track. See `Track`.
+#[derive(Debug)]
+pub struct AsyncTrack<'a, R> {
+    /// Attributes
+    pub attributes: TrackAttributes,
+
+    /// Borrowed async reader over the MP4.
+    reader: &'a mut R,
+}
+
+impl <'a, R: AsyncRead + AsyncSeek + Unpin> AsyncTrack<'a, R> {
+    /// Number of samples in track.
+    pub fn len(&self) -> usize {
+        self.attributes.offsets.len()
+    }
+
+    /// Returns `true` if the track has no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn name(&self) -> &str {
+        &self.attributes.name
+    }
+
+    pub fn id(&self) -> u32 {
+        self.attributes.id
+    }
+
+    pub fn offsets(&self) -> impl Iterator<Item = &SampleOffset> {
+        self.attributes.offsets.iter()
+    }
+
+    /// Duration for track.
+    pub fn duration(&self) -> Duration {
+        self.attributes.duration()
+    }
+
+    /// Returns an async iterator over the track's samples.
+    /// Use `AsyncSamples::next()` to read the next sample.
+    pub fn samples(&mut self) -> AsyncSamples<'_, R> {
+        AsyncSamples {
+            reader: self.reader,
+            offsets: self.attributes.offsets().iter(),
+            time: Duration::ZERO,
+        }
+    }
+}
+
+/// Async iterator over track samples.
+#[derive(Debug)]
+pub struct AsyncSamples<'a, R> {
+    reader: &'a mut R,
+    offsets: Iter<'a, SampleOffset>,
+    /// Relative timestamp for next sample.
+    time: Duration,
+}
+
+impl <'a, R: AsyncRead + AsyncSeek + Unpin> AsyncSamples<'a, R> {
+    /// Reads the next sample. Returns `None` when all samples have been read.
+    pub async fn next(&mut self) -> Option<Result<Sample, Mp4Error>> {
+        let offset = self.offsets.next()?;
+        let rel_t = self.time;
+        self.time += offset.duration;
+
+        Some(self.read(offset).await.map(|s| s.with_time(rel_t)))
+    }
+
+    async fn read(&mut self, offset: &SampleOffset) -> Result<Sample, Mp4Error> {
+        self.reader.seek(SeekFrom::Start(offset.position)).await?;
+        let mut buf = vec![0_u8; offset.size as usize];
+        self.reader.read_exact(&mut buf).await?;
+        Ok(Sample::from_bytes(buf, offset))
+    }
+}
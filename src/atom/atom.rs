@@ -2,29 +2,29 @@
 //!
 //! See: <https://developer.apple.com/documentation/quicktime-file-format/atoms>.
 
-use std::io::{Cursor, SeekFrom, Read};
+use std::{any::Any, fs::File, io::{Cursor, Read, Seek, SeekFrom}};
 
 use binrw::{BinRead, Endian};
 
-use crate::{atom_types::Stsc, errors::Mp4Error, fourcc::FourCC, reader::{Mp4Reader, ReadOption, TargetReader}, Mdhd, Vmhd};
+use crate::{atom_types::Stsc, errors::Mp4Error, fourcc::FourCC, reader::{Mp4Reader, ReadOption, TargetReader}, registry::ParsedAtom, Mdhd, Vmhd};
 
-use crate::{Tkhd, AtomHeader, Co64, Dref, Elst, Ftyp, Hdlr, Mvhd, Sdtp, Smhd, Stco, Stsd, Stss, Stsz, Stts, Tmcd};
+use crate::{Tkhd, Tref, AtomHeader, Co64, Cslg, Ctts, Dref, Elst, Ftyp, Hdlr, Meta, Mvhd, Sdtp, Smhd, Stco, Stsd, Stss, Stsz, Stts, Tmcd};
 
 /// MP4 atom.
 #[derive(Debug)]
-pub struct Atom<'a> {
+pub struct Atom<'a, R = File> {
     /// Header
     pub header: AtomHeader,
     /// Reader over MP4, starting
     /// after atom header.
-    reader: &'a mut Mp4Reader,
+    reader: &'a mut Mp4Reader<R>,
     /// Specifies wether the reader
-    /// is a `BufReader<File>`
+    /// is a `BufReader<R>`
     /// or `Cursor<Vec<u8>>` over the `moov` atom.
     target: TargetReader
 }
 
-impl <'a> Atom<'a> {
+impl <'a, R: Read + Seek> Atom<'a, R> {
     /// Creates a new `Atom`.
     ///
     /// Set `seek` to `true` to ensure the
@@ -33,7 +33,7 @@ impl <'a> Atom<'a> {
     /// this is the case.
     pub(crate) fn new(
         header: &AtomHeader,
-        reader: &'a mut Mp4Reader,
+        reader: &'a mut Mp4Reader<R>,
         target: &TargetReader,
         seek: bool
     ) -> Result<Self, Mp4Error> {
@@ -267,6 +267,24 @@ impl <'a> Atom<'a> {
         Ok(atom)
     }
 
+    /// Parse the atom into `Ctts` (composition offsets)
+    /// if `Atom.name` is `ctts`.
+    pub fn ctts(&mut self) -> Result<Ctts, Mp4Error> {
+        self.verify_fcc(&FourCC::Ctts)?;
+        let atom = self.reader.read_ne::<Ctts>(&self.target)?;
+        self.bounds()?;
+        Ok(atom)
+    }
+
+    /// Parse the atom into `Cslg` (composition shift)
+    /// if `Atom.name` is `cslg`.
+    pub fn cslg(&mut self) -> Result<Cslg, Mp4Error> {
+        self.verify_fcc(&FourCC::Cslg)?;
+        let atom = self.reader.read_ne::<Cslg>(&self.target)?;
+        self.bounds()?;
+        Ok(atom)
+    }
+
     /// Parse the atom into `Hdlr` if `Atom.name` is `hdlr`,
     pub fn hdlr(&mut self) -> Result<Hdlr, Mp4Error> {
         self.verify_fcc(&FourCC::Hdlr)?;
@@ -338,6 +356,7 @@ impl <'a> Atom<'a> {
     pub fn sdtp(&mut self) -> Result<Sdtp, Mp4Error> {
         self.verify_fcc(&FourCC::Sdtp)?;
         let size = self.data_size() as u32;
+        self.reader.load_moov(&self.target, None)?;
         let atom = Sdtp::read_ne_args(
             &mut self.reader.moov_reader,
             binrw::args! {data_size: size}
@@ -438,6 +457,51 @@ impl <'a> Atom<'a> {
         Ok(atom)
     }
 
+    /// Parse the atom into `Tref` if `Atom.name` is `tref`,
+    /// regardless of current position.
+    pub fn tref(&mut self) -> Result<Tref, Mp4Error> {
+        self.verify_fcc(&FourCC::Tref)?;
+        self.reset()?;
+        let data = self.read_data()?;
+        Ok(Tref::read(&mut Cursor::new(data))?)
+    }
+
+    /// Parse the atom into `Meta` if `Atom.name` is `meta`,
+    /// regardless of current position.
+    pub fn meta(&mut self) -> Result<Meta, Mp4Error> {
+        self.verify_fcc(&FourCC::from_str("meta"))?;
+        self.reset()?;
+        let data = self.read_data()?;
+        Meta::new(&data)
+    }
+
+    /// Parse the atom via the parser registered for its FourCC
+    /// (see `AtomRegistry`), regardless of current position.
+    /// Returns `None` if no parser is registered.
+    ///
+    /// Use `downcast()` on the result to get the concrete type,
+    /// or `Atom::parse_as()` if the type is known.
+    pub fn parse(&mut self) -> Result<Option<ParsedAtom>, Mp4Error> {
+        if !self.reader.registry.has_parser(&self.header.name) {
+            return Ok(None)
+        }
+        self.reset()?;
+        let data = self.read_data()?;
+        self.reader.registry.parse(&self.header.name, &data)
+            .transpose()
+    }
+
+    /// Parse the atom via the parser registered for its FourCC
+    /// (see `AtomRegistry`) as `T`.
+    pub fn parse_as<T: Any>(&mut self) -> Result<T, Mp4Error> {
+        let name = self.header.name.to_string();
+        self.parse()?
+            .ok_or_else(|| Mp4Error::NoSuchParser(name.to_owned()))?
+            .downcast::<T>()
+            .map(|t| *t)
+            .map_err(|_| Mp4Error::ParsedTypeMismatch(name))
+    }
+
     /// Bounds check against current position,
     /// to prevent reading outside atom start/end
     /// byte offsets.
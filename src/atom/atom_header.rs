@@ -1,14 +1,15 @@
 use std::ops::Range;
 
-use crate::{FourCC, CONTAINER};
+use crate::{FourCC, UserType};
 
 /// Atom header.
 /// 8 or 16 bytes in MP4, depending on whether
-/// 32 or 64-bit sized.
+/// 32 or 64-bit sized, plus 16 bytes for `uuid` atoms.
 ///
 /// ```ignore
-/// | [X X X X] [Y Y Y Y] [Z Z Z Z Z Z Z Z] |
-///    |         |         |
+/// | [X X X X] [Y Y Y Y] [Z Z Z Z Z Z Z Z] [U U U U U U U U U U U U U U U U] |
+///    |         |         |                 |
+///    |         |         |                 user type (optional, only if FourCC is 'uuid')
 ///    |         |         64bit size (optional, only if 32 bit size == 1)
 ///    |         FourCC
 ///    32bit size
@@ -33,14 +34,26 @@ pub struct AtomHeader {
     /// in 64 bit area regardless
     /// of actual atom size. However this information
     /// can not be derived post-parse.
-    pub(crate) size_64bit: bool
+    pub(crate) size_64bit: bool,
+    /// Set to `true` if the 32-bit size is `0`,
+    /// meaning the atom extends to the end of the file.
+    /// Only valid for top-level atoms, e.g. `mdat`.
+    /// `atom_size` is then set to the remaining file size.
+    pub(crate) size_eof: bool,
+    /// 16-byte user type (UUID) for `uuid` atoms,
+    /// following FourCC and optional 64-bit size.
+    pub(crate) user_type: Option<[u8; 16]>,
+    /// Set to `true` if atom is a container,
+    /// i.e. listed in `CONTAINER` or added to
+    /// the `AtomRegistry` when the header was read.
+    pub(crate) container: bool,
 }
 
 impl AtomHeader {
     /// Convenience method to check whether atom at current offset is
     /// a container or not.
     pub fn is_container(&self) -> bool {
-        CONTAINER.contains(&self.name.to_str())
+        self.container
     }
 
     pub fn start(&self) -> u64 {
@@ -55,6 +68,14 @@ impl AtomHeader {
         self.atom_size
     }
 
+    /// Returns `true` if the atom's 32-bit size is `0`,
+    /// meaning it extends to the end of the file.
+    /// `AtomHeader::atom_size()` then returns the
+    /// remaining file size from the start of the atom.
+    pub fn extends_to_eof(&self) -> bool {
+        self.size_eof
+    }
+
     pub fn name(&self) -> &FourCC {
         &self.name
     }
@@ -63,11 +84,27 @@ impl AtomHeader {
         self.offset
     }
 
+    /// User type (UUID) for `uuid` atoms.
+    /// `None` for all other atoms.
+    pub fn user_type(&self) -> Option<&[u8; 16]> {
+        self.user_type.as_ref()
+    }
+
+    /// Well-known user type for `uuid` atoms,
+    /// e.g. `UserType::Xmp`.
+    /// `None` for all other atoms, or if the user type is unknown.
+    pub fn known_user_type(&self) -> Option<UserType> {
+        self.user_type.as_ref()
+            .and_then(UserType::from_uuid)
+    }
+
     /// Determine header size in bytes in MP4.
-    /// Returns 8 or 16 bytes.
+    /// Returns 8 or 16 bytes, plus 16 bytes for `uuid` atoms.
     /// If a 64-bit atom header, size is increased
     /// 8 bytes to adjust for 64 bit value being read
     /// after FourCC.
+    /// If a `uuid` atom, size is increased 16 bytes
+    /// to adjust for the user type.
     pub fn header_size(&self) -> u8 {
         // Size check will not work for cameras
         // that consistently store atoms with 64-bit size...
@@ -80,9 +117,13 @@ impl AtomHeader {
         // it would otherwise be impossible to
         // determine correct byte offsets without
         // re-reading the MP4.
-        match self.size_64bit {
+        let size = match self.size_64bit {
             true => 16,
             false => 8,
+        };
+        match self.user_type {
+            Some(_) => size + 16,
+            None => size,
         }
     }
 
@@ -92,7 +133,8 @@ impl AtomHeader {
 
     /// Data load absolute offset,
     /// i.e. position after header
-    /// adjusted for optional 64bit size value.
+    /// adjusted for optional 64bit size value
+    /// and `uuid` user type.
     pub fn data_offset(&self) -> u64 {
         self.offset + self.header_size() as u64
     }
@@ -2,6 +2,8 @@
 
 pub mod atom;
 pub mod atom_header;
+pub mod user_type;
 
 pub use atom::Atom;
 pub use atom_header::AtomHeader;
+pub use user_type::UserType;
@@ -0,0 +1,200 @@
+//! Well-known user types for `uuid` atoms.
+//!
+//! A `uuid` atom has a 16-byte user type (a UUID)
+//! directly after the FourCC (and the optional 64-bit size),
+//! which identifies the atom's content.
+//! See `AtomHeader::user_type()`.
+//!
+//! ```rs
+//! use mp4iter::{Mp4, UserType};
+//! use std::path::Path;
+//!
+//! fn main() -> Result<(), mp4iter::Mp4Error> {
+//!     let mut mp4 = Mp4::new(Path::new("VIDEO.MP4"))?;
+//!     for header in mp4.select("//uuid")? {
+//!         if header.known_user_type() == Some(UserType::Xmp) {
+//!             let xmp = mp4.atom_at(&header)?.read_data()?;
+//!             println!("{}", String::from_utf8_lossy(&xmp));
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::fmt;
+
+/// Well-known user types for `uuid` atoms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UserType {
+    /// Garmin VIRB GPS data (`uuid` atom in the main tree).
+    ///
+    /// `9B630F8D-6374-40EC-8204-BC5FF5091728`
+    GarminVirb,
+    /// XMP packet (Adobe XMP specification),
+    /// also written by e.g. Sony cameras.
+    ///
+    /// `BE7ACFCB-97A9-42E8-9C71-999491E3AFAC`
+    Xmp,
+    /// Sony profile (`PROF`), e.g. file, audio,
+    /// and video profiles.
+    ///
+    /// `50524F46-21D2-4FCE-BB88-695CFAC9C740`
+    SonyProf,
+    /// Sony user media metadata (`USMT`).
+    ///
+    /// `55534D54-21D2-4FCE-BB88-695CFAC9C740`
+    SonyUsmt,
+    /// PIFF track fragment decode time (`tfxd`).
+    ///
+    /// `6D1D9B05-42D5-44E6-80E2-141DAFF757B2`
+    PiffTfxd,
+    /// PIFF track fragment reference (`tfrf`).
+    ///
+    /// `D4807EF2-CA39-4695-8E54-26CB9E46A79F`
+    PiffTfrf,
+    /// Spherical video V1 metadata (Google spatial media),
+    /// an XML document.
+    ///
+    /// `FFCC8263-F855-4A93-8814-587A02521FDD`
+    SphericalV1,
+}
+
+impl UserType {
+    /// All well-known user types.
+    pub const ALL: [UserType; 7] = [
+        UserType::GarminVirb,
+        UserType::Xmp,
+        UserType::SonyProf,
+        UserType::SonyUsmt,
+        UserType::PiffTfxd,
+        UserType::PiffTfrf,
+        UserType::SphericalV1,
+    ];
+
+    /// Returns well-known user type for UUID,
+    /// or `None` if unknown.
+    pub fn from_uuid(uuid: &[u8; 16]) -> Option<Self> {
+        Self::ALL.into_iter()
+            .find(|t| &t.uuid() == uuid)
+    }
+
+    /// Returns UUID for user type.
+    pub fn uuid(&self) -> [u8; 16] {
+        match self {
+            UserType::GarminVirb => [
+                0x9b, 0x63, 0x0f, 0x8d, 0x63, 0x74, 0x40, 0xec,
+                0x82, 0x04, 0xbc, 0x5f, 0xf5, 0x09, 0x17, 0x28,
+            ],
+            UserType::Xmp => [
+                0xbe, 0x7a, 0xcf, 0xcb, 0x97, 0xa9, 0x42, 0xe8,
+                0x9c, 0x71, 0x99, 0x94, 0x91, 0xe3, 0xaf, 0xac,
+            ],
+            UserType::SonyProf => [
+                0x50, 0x52, 0x4f, 0x46, 0x21, 0xd2, 0x4f, 0xce,
+                0xbb, 0x88, 0x69, 0x5c, 0xfa, 0xc9, 0xc7, 0x40,
+            ],
+            UserType::SonyUsmt => [
+                0x55, 0x53, 0x4d, 0x54, 0x21, 0xd2, 0x4f, 0xce,
+                0xbb, 0x88, 0x69, 0x5c, 0xfa, 0xc9, 0xc7, 0x40,
+            ],
+            UserType::PiffTfxd => [
+                0x6d, 0x1d, 0x9b, 0x05, 0x42, 0xd5, 0x44, 0xe6,
+                0x80, 0xe2, 0x14, 0x1d, 0xaf, 0xf7, 0x57, 0xb2,
+            ],
+            UserType::PiffTfrf => [
+                0xd4, 0x80, 0x7e, 0xf2, 0xca, 0x39, 0x46, 0x95,
+                0x8e, 0x54, 0x26, 0xcb, 0x9e, 0x46, 0xa7, 0x9f,
+            ],
+            UserType::SphericalV1 => [
+                0xff, 0xcc, 0x82, 0x63, 0xf8, 0x55, 0x4a, 0x93,
+                0x88, 0x14, 0x58, 0x7a, 0x02, 0x52, 0x1f, 0xdd,
+            ],
+        }
+    }
+}
+
+impl fmt::Display for UserType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            UserType::GarminVirb => "Garmin VIRB",
+            UserType::Xmp => "XMP",
+            UserType::SonyProf => "Sony PROF",
+            UserType::SonyUsmt => "Sony USMT",
+            UserType::PiffTfxd => "PIFF tfxd",
+            UserType::PiffTfrf => "PIFF tfrf",
+            UserType::SphericalV1 => "Spherical Video V1",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Formats UUID as hyphenated, upper case hex string,
+/// e.g. `BE7ACFCB-97A9-42E8-9C71-999491E3AFAC`.
+pub fn uuid_to_string(uuid: &[u8; 16]) -> String {
+    uuid.iter()
+        .enumerate()
+        .map(|(i, b)| match i {
+            4 | 6 | 8 | 10 => format!("-{b:02X}"),
+            _ => format!("{b:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{testing::{atom, container, mp4}, Mp4};
+
+    use super::*;
+
+    #[test]
+    fn uuid_strings() {
+        assert_eq!(uuid_to_string(&UserType::Xmp.uuid()), "BE7ACFCB-97A9-42E8-9C71-999491E3AFAC");
+        assert_eq!(uuid_to_string(&UserType::GarminVirb.uuid()), "9B630F8D-6374-40EC-8204-BC5FF5091728");
+        assert_eq!(uuid_to_string(&[0; 16]), "00000000-0000-0000-0000-000000000000");
+    }
+
+    #[test]
+    fn from_uuid() {
+        for user_type in UserType::ALL {
+            assert_eq!(UserType::from_uuid(&user_type.uuid()), Some(user_type));
+        }
+        assert_eq!(UserType::from_uuid(&[0; 16]), None);
+    }
+
+    #[test]
+    fn uuid_header() {
+        let xmp = [UserType::Xmp.uuid().as_slice(), b"<x:xmpmeta/>"].concat();
+        // Unknown user type, in udta
+        let other = [[0xab; 16].as_slice(), b"data"].concat();
+        // 64-bit size
+        let mut prof = [1_u32.to_be_bytes().as_slice(), b"uuid", &0_u64.to_be_bytes()].concat();
+        prof.extend(UserType::SonyProf.uuid());
+        prof.extend(b"PROF");
+        let len = prof.len() as u64;
+        prof[8..16].copy_from_slice(&len.to_be_bytes());
+
+        let bytes = [
+            mp4(&[]),
+            atom(b"uuid", &xmp),
+            container(b"udta", &[atom(b"uuid", &other)]),
+            prof,
+        ].concat();
+        let mut mp4 = Mp4::from_slice(&bytes).unwrap();
+
+        let headers = mp4.select("//uuid").unwrap();
+        assert_eq!(headers.len(), 3);
+        let expected: [(Option<UserType>, u8, &[u8]); 3] = [
+            (Some(UserType::Xmp), 24, b"<x:xmpmeta/>"),
+            (None, 24, b"data"),
+            (Some(UserType::SonyProf), 32, b"PROF"),
+        ];
+        for (header, (user_type, header_size, data)) in headers.iter().zip(expected) {
+            assert_eq!(header.known_user_type(), user_type);
+            assert_eq!(header.header_size(), header_size);
+            assert_eq!(header.data_offset(), header.offset() + header_size as u64);
+            assert_eq!(header.data_size(), data.len() as u64);
+            assert_eq!(mp4.atom_at(header).unwrap().read_data().unwrap(), data);
+        }
+        assert_eq!(headers[1].user_type(), Some(&[0xab; 16]));
+    }
+}
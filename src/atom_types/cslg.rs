@@ -0,0 +1,88 @@
+//! Composition shift least greatest atom (`cslg`).
+//!
+//! Location: `moov/trak[multiple]/mdia/minf/stbl/cslg`
+//!
+//! See: <https://developer.apple.com/documentation/quicktime-file-format/composition_shift_least_greatest_atom>
+
+use binrw::BinRead;
+
+use super::versioned_i64;
+
+/// Composition shift least greatest atom (`cslg`).
+/// Relates composition times to decode times
+/// when composition offsets (`ctts`) are negative.
+///
+/// Location: `moov/trak[multiple]/mdia/minf/stbl/cslg`
+///
+/// See: <https://developer.apple.com/documentation/quicktime-file-format/composition_shift_least_greatest_atom>
+#[derive(Debug, Default, Clone, BinRead)]
+#[br(big)]
+pub struct Cslg {
+    /// Version 1 uses 64-bit values.
+    pub(crate) version: u8,
+    _flags: [u8; 3],
+    /// If added to composition times, composition times
+    /// are greater than or equal to decode times.
+    #[br(parse_with = versioned_i64, args(version))]
+    pub composition_to_dts_shift: i64,
+    /// Smallest composition offset (`ctts`).
+    #[br(parse_with = versioned_i64, args(version))]
+    pub least_decode_to_display_delta: i64,
+    /// Largest composition offset (`ctts`).
+    #[br(parse_with = versioned_i64, args(version))]
+    pub greatest_decode_to_display_delta: i64,
+    /// Smallest composition time.
+    #[br(parse_with = versioned_i64, args(version))]
+    pub composition_start_time: i64,
+    /// Largest composition time, plus the duration of that sample.
+    #[br(parse_with = versioned_i64, args(version))]
+    pub composition_end_time: i64,
+}
+
+impl Cslg {
+    /// Atom version. Version 1 uses 64-bit values.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn composition_to_dts_shift(&self) -> i64 {
+        self.composition_to_dts_shift
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use binrw::BinReaderExt;
+
+    use super::*;
+
+    #[test]
+    fn versions() {
+        let values: [i64; 5] = [1001, -1001, 2002, 0, 30030];
+
+        // 32-bit values
+        let mut data = vec![0, 0, 0, 0];
+        for v in values {
+            data.extend_from_slice(&(v as i32).to_be_bytes());
+        }
+        let cslg = Cursor::new(data).read_be::<Cslg>().unwrap();
+        assert_eq!(cslg.version(), 0);
+        assert_eq!(cslg.composition_to_dts_shift(), 1001);
+        assert_eq!(cslg.least_decode_to_display_delta, -1001);
+        assert_eq!(cslg.composition_end_time, 30030);
+
+        // 64-bit values
+        let mut data = vec![1, 0, 0, 0];
+        for v in values {
+            data.extend_from_slice(&v.to_be_bytes());
+        }
+        let cslg = Cursor::new(data).read_be::<Cslg>().unwrap();
+        assert_eq!(cslg.version(), 1);
+        assert_eq!(cslg.composition_to_dts_shift(), 1001);
+        assert_eq!(cslg.least_decode_to_display_delta, -1001);
+        assert_eq!(cslg.greatest_decode_to_display_delta, 2002);
+        assert_eq!(cslg.composition_end_time, 30030);
+    }
+}
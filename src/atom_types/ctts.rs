@@ -1,16 +1,21 @@
 //! Composition offset atom (`ctts`).
 //!
+//! Location: `moov/trak[multiple]/mdia/minf/stbl/ctts`
+//!
 //! See: <https://developer.apple.com/documentation/quicktime-file-format/composition_offset_atom>
 
 use binrw::BinRead;
 
 /// Composition offset atom (`ctts`).
 ///
+/// Location: `moov/trak[multiple]/mdia/minf/stbl/ctts`
+///
 /// See: <https://developer.apple.com/documentation/quicktime-file-format/composition_offset_atom>
-#[derive(Debug, Default, BinRead)]
+#[derive(Debug, Default, Clone, BinRead)]
 #[br(big)]
 pub struct Ctts {
-    _version: u8,
+    /// Version 1 uses signed composition offsets.
+    pub(crate) version: u8,
     _flags: [u8; 3],
     _entry_count: u32,
     #[br(count = _entry_count)]
@@ -18,15 +23,70 @@ pub struct Ctts {
 }
 
 impl Ctts {
+    /// Atom version. Version 1 uses signed composition offsets.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
     pub fn offset_table(&self) -> &[OffsetTableEntry] {
         &self.offset_table
     }
+
+    /// Returns unscaled composition offsets,
+    /// one for each sample.
+    ///
+    /// Since sample counts are not verified, use `take()`
+    /// to limit this to the track's number of samples (`stsz`).
+    pub fn offsets(&self) -> impl Iterator<Item = i32> + '_ {
+        self.offset_table.iter()
+            .flat_map(|t| (0..t.sample_count).map(|_| t.composition_offset))
+    }
 }
 
 /// Composition offset table
-#[derive(Debug, BinRead)]
+#[derive(Debug, Clone, BinRead)]
 #[br(big)]
 pub struct OffsetTableEntry {
     pub sample_count: u32,
-    pub composition_offset: u32
+    /// Unscaled offset from decode time to composition time.
+    /// Signed for version 1, but since QuickTime also
+    /// writes negative offsets for version 0,
+    /// both versions are read as signed.
+    pub composition_offset: i32
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use binrw::BinReaderExt;
+
+    use super::*;
+
+    /// `ctts` data load with entries as `(SAMPLE_COUNT, OFFSET)`.
+    fn ctts(version: u8, entries: &[(u32, i32)]) -> Ctts {
+        let mut data = vec![version, 0, 0, 0];
+        data.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+        for (count, offset) in entries {
+            data.extend_from_slice(&count.to_be_bytes());
+            data.extend_from_slice(&offset.to_be_bytes());
+        }
+        Cursor::new(data).read_be::<Ctts>().unwrap()
+    }
+
+    #[test]
+    fn signed_offsets() {
+        for version in [0, 1] {
+            let ctts = ctts(version, &[(1, 1001), (2, -1001), (1, 0)]);
+            assert_eq!(ctts.version(), version);
+            assert_eq!(ctts.offsets().collect::<Vec<_>>(), vec![1001, -1001, -1001, 0]);
+        }
+    }
+
+    #[test]
+    fn corrupt_sample_count() {
+        // Not expanded until iterated
+        let ctts = ctts(0, &[(u32::MAX, 1001), (u32::MAX, 2002)]);
+        assert_eq!(ctts.offsets().take(3).collect::<Vec<_>>(), vec![1001; 3]);
+    }
 }
@@ -6,48 +6,73 @@
 
 use binrw::BinRead;
 
+use super::{versioned_i64, versioned_u64};
+
 /// Edit list atom (`elst`).
 ///
 /// Location: `moov/trak[multiple]/edts/elst`
 ///
 /// See: <https://developer.apple.com/documentation/quicktime-file-format/edit_list_atom>
-#[derive(Debug, Default, BinRead)]
+#[derive(Debug, Default, Clone, BinRead)]
+#[br(big)]
 pub struct Elst {
-    _version: u8,
+    /// Version 1 uses 64-bit durations and media times.
+    pub(crate) version: u8,
     _flags: [u8; 3],
     _number_of_entries: u32,
-    #[br(count = _number_of_entries)]
+    #[br(count = _number_of_entries, args {inner: (version,)})]
     pub (crate) edit_list_table: Vec<EditListTable>
 }
 
 impl Elst {
+    /// Atom version. Version 1 uses 64-bit durations and media times.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
     pub fn edit_list_table(&self) -> &[EditListTable] {
         &self.edit_list_table
     }
 }
 
-#[derive(Debug, Default, BinRead)]
+#[derive(Debug, Default, Clone, Copy, BinRead)]
+#[br(big, import(version: u8))]
 pub struct EditListTable {
-    /// Unscaled duration of this edit.
-    pub track_duration: u32,
-    /// Containing the unscaled starting time within the media of this edit segment.
+    /// Unscaled duration of this edit,
+    /// in movie time scale (`mvhd`).
+    #[br(parse_with = versioned_u64, args(version))]
+    pub track_duration: u64,
+    /// Containing the unscaled starting time within the media of this edit segment,
+    /// in media time scale (`mdhd`).
     /// If set to -1 the edit is empty.
-    pub media_time: u32,
+    #[br(parse_with = versioned_i64, args(version))]
+    pub media_time: i64,
     /// Fixed-point number that specifies the relative rate at which to play the media.
-    /// Can not be 0 or negative.
+    /// 0 means the media at `media_time` is shown for the edit's duration (dwell).
     pub media_rate: u32,
 }
 
 impl EditListTable {
-    pub fn track_duration(&self) -> u32 {
+    pub fn track_duration(&self) -> u64 {
         self.track_duration
     }
 
-    pub fn media_time(&self) -> u32 {
+    pub fn media_time(&self) -> i64 {
         self.media_time
     }
 
     pub fn media_rate(&self) -> u32 {
         self.media_rate
     }
+
+    /// Media rate as float, e.g. 1.0 for normal playback.
+    pub fn rate(&self) -> f64 {
+        self.media_rate as i32 as f64 / 65536.
+    }
+
+    /// Returns `true` if this is an empty edit,
+    /// i.e. a delay before the media is presented.
+    pub fn is_empty(&self) -> bool {
+        self.media_time == -1
+    }
 }
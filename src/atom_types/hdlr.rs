@@ -11,7 +11,7 @@ use crate::support::string_from_bytes;
 /// Handler reference atom (`hdlr`)
 ///
 /// See: <https://developer.apple.com/documentation/quicktime-file-format/handler_reference_atom>
-#[derive(Debug, Default, BinRead)]
+#[derive(Debug, Default, Clone, BinRead)]
 pub struct Hdlr {
     _version: u8,
     _flags: [u8; 3],
@@ -12,6 +12,8 @@
 use binrw::BinRead;
 use time::{ext::NumericalDuration, Duration};
 
+use super::versioned_u64;
+
 /// Media header atom ('mdhd'). One per track (`trak`).
 /// Specifies the characteristics of a media (`mdia`), including time scale and duration.
 ///
@@ -21,15 +23,19 @@ use time::{ext::NumericalDuration, Duration};
 #[derive(Debug, Default, BinRead)]
 #[br(big)]
 pub struct Mdhd {
-    _version: u8,
+    /// Version 1 uses 64-bit times and duration.
+    pub(crate) version: u8,
     _flags: [u8; 3],
-    pub(crate) creation_time: u32, // should be UTC
-    pub(crate) modification_time: u32, // should be UTC
+    #[br(parse_with = versioned_u64, args(version))]
+    pub(crate) creation_time: u64, // should be UTC
+    #[br(parse_with = versioned_u64, args(version))]
+    pub(crate) modification_time: u64, // should be UTC
     pub(crate) time_scale: u32,
     /// Unscaled duration. I.e. "ticks"
     /// that require dividing by time scale
     /// to derive a value in seconds.
-    pub(crate) duration: u32,
+    #[br(parse_with = versioned_u64, args(version))]
+    pub(crate) duration: u64,
     /// Specifies the language code for this media.
     /// Stored as three unsigned 5bit integers.
     /// Initially reads value as BE u16.
@@ -41,19 +47,36 @@ pub struct Mdhd {
 }
 
 impl Mdhd {
-    pub fn creation_time(&self) -> u32 {
+    /// Atom version. Version 1 uses 64-bit times and duration.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn creation_time(&self) -> u64 {
         self.creation_time
     }
 
-    pub fn modification_time(&self) -> u32 {
+    pub fn modification_time(&self) -> u64 {
         self.modification_time
     }
 
+    /// Creation datetime for this media.
+    /// `None` if outside supported datetime range.
+    pub fn creation_datetime(&self) -> Option<time::PrimitiveDateTime> {
+        crate::consts::mp4_datetime(self.creation_time)
+    }
+
+    /// Modification datetime for this media.
+    /// `None` if outside supported datetime range.
+    pub fn modification_datetime(&self) -> Option<time::PrimitiveDateTime> {
+        crate::consts::mp4_datetime(self.modification_time)
+    }
+
     pub fn time_scale(&self) -> u32 {
         self.time_scale
     }
 
-    pub fn duration_unscaled(&self) -> u32 {
+    pub fn duration_unscaled(&self) -> u64 {
         self.duration
     }
 
@@ -83,7 +106,7 @@ impl Mdhd {
 /// - 1 `u5` + `0x60`
 /// - 1 `u5` + `0x60`
 /// - 1 `u5` + `0x60`
-fn derive_language_code(data: u16) -> String {
+pub(crate) fn derive_language_code(data: u16) -> String {
     [
         // value between 0-31 + 96 = ascii range so casting to u8 is ok
         (((0b0111_1100_0000_0000 & data) >> 10) as u8 + 0x60) as char,
@@ -1,6 +1,551 @@
+//! Metadata atom (`meta`).
+//!
+//! QuickTime metadata, as written by e.g. iPhones, DJI, and Android phones,
+//! consists of a handler (`hdlr`), a list of keys (`keys`),
+//! e.g. `com.apple.quicktime.make`, and an item list (`ilst`),
+//! where each item refers to a key via its 1-based index
+//! and contains one or more typed values (`data`).
+//!
+//! iTunes-style metadata (`udta/meta`) lacks `keys`,
+//! and items are instead identified by their FourCC, e.g. `©nam`.
+//!
+//! Location:
+//! - `moov/meta` (movie)
+//! - `moov/trak[multiple]/meta` (track)
+//! - `moov/udta/meta` (iTunes-style)
+//!
+//! See: <https://developer.apple.com/documentation/quicktime-file-format/metadata_atom>
+
+use std::io::Cursor;
+
+use binrw::BinReaderExt;
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+
+use crate::{FourCC, Hdlr, Mp4Error};
+
 /// Metadata atom (`meta`).
-/// 
+///
 /// See: <https://developer.apple.com/documentation/quicktime-file-format/metadata_atom>
+#[derive(Debug, Default, Clone)]
 pub struct Meta {
+    /// Metadata handler, `mdta` for QuickTime metadata,
+    /// `mdir` for iTunes-style metadata.
+    pub(crate) hdlr: Option<Hdlr>,
+    /// Keys (`keys`), referred to by items via 1-based index.
+    pub(crate) keys: Vec<MetaKey>,
+    /// Items (`ilst`), one for each value.
+    pub(crate) items: Vec<MetaItem>,
+}
+
+/// Metadata key, e.g. `com.apple.quicktime.make`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetaKey {
+    /// Key namespace, usually `mdta`.
+    pub namespace: FourCC,
+    /// Key, e.g. `com.apple.quicktime.make`.
+    pub key: String,
+}
+
+/// Metadata item with a single value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetaItem {
+    /// Key, e.g. `com.apple.quicktime.make` for QuickTime metadata,
+    /// or FourCC, e.g. `©nam`, for iTunes-style metadata.
+    /// For iTunes freeform items (`----`), `MEAN:NAME`,
+    /// e.g. `com.apple.iTunes:iTunSMPB`.
+    pub key: String,
+    /// Locale, country and language.
+    /// 0 = default.
+    pub locale: u32,
+    /// Value.
+    pub value: MetaValue,
+}
+
+/// Typed metadata value.
+///
+/// See: <https://developer.apple.com/documentation/quicktime-file-format/well-known_types>
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetaValue {
+    /// UTF-8 string (type 1, 4).
+    Utf8(String),
+    /// UTF-16 string (type 2, 5).
+    Utf16(String),
+    /// Date, parsed from an ISO 8601 UTF-8 string,
+    /// e.g. `com.apple.quicktime.creationdate`.
+    /// UTC if no offset is specified.
+    Date(OffsetDateTime),
+    /// Big-endian signed integer (type 21, 65, 66, 67, 74).
+    Int(i64),
+    /// Big-endian unsigned integer (type 22, 75, 76, 77, 78).
+    UInt(u64),
+    /// Big-endian 32-bit float (type 23).
+    Float32(f32),
+    /// Big-endian 64-bit float (type 24).
+    Float64(f64),
+    /// JPEG image (type 13).
+    Jpeg(Vec<u8>),
+    /// PNG image (type 14).
+    Png(Vec<u8>),
+    /// BMP image (type 27).
+    Bmp(Vec<u8>),
+    /// Other or unknown type, e.g. 0 (binary).
+    Binary{type_code: u32, data: Vec<u8>},
+}
+
+impl MetaValue {
+    /// Parses `data` as value with well-known type `type_code`.
+    /// UTF-8 strings are parsed as dates
+    /// if `key` ends with `date` or is `©day`.
+    pub(crate) fn new(key: &str, type_code: u32, data: &[u8]) -> Self {
+        match type_code {
+            1 | 4 => {
+                let s = String::from_utf8_lossy(data)
+                    .trim_end_matches('\0')
+                    .to_owned();
+                let is_date = key.to_lowercase().ends_with("date") || key == "©day";
+                match is_date.then(|| parse_iso8601(&s)).flatten() {
+                    Some(dt) => Self::Date(dt),
+                    None => Self::Utf8(s),
+                }
+            },
+            2 | 5 => {
+                let units: Vec<u16> = data.chunks_exact(2)
+                    .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                    .collect();
+                Self::Utf16(String::from_utf16_lossy(&units).trim_end_matches('\0').to_owned())
+            },
+            13 => Self::Jpeg(data.to_owned()),
+            14 => Self::Png(data.to_owned()),
+            27 => Self::Bmp(data.to_owned()),
+            21 | 65 | 66 | 67 | 74 if matches!(data.len(), 1..=8) => {
+                // Sign extend
+                let shift = 64 - 8 * data.len() as u32;
+                Self::Int((be_u64(data) << shift) as i64 >> shift)
+            },
+            22 | 75 | 76 | 77 | 78 if matches!(data.len(), 1..=8) => Self::UInt(be_u64(data)),
+            23 if data.len() == 4 => Self::Float32(f32::from_bits(be_u64(data) as u32)),
+            24 if data.len() == 8 => Self::Float64(f64::from_bits(be_u64(data))),
+            _ => Self::Binary{type_code, data: data.to_owned()},
+        }
+    }
+
+    /// Returns value as string slice,
+    /// if a UTF-8 or UTF-16 string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Utf8(s) | Self::Utf16(s) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Returns value as signed integer,
+    /// if an integer that fits in `i64`.
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(n) => Some(*n),
+            Self::UInt(n) => i64::try_from(*n).ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns value as float,
+    /// if a float or an integer.
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Self::Float32(n) => Some(*n as f64),
+            Self::Float64(n) => Some(*n),
+            Self::Int(n) => Some(*n as f64),
+            Self::UInt(n) => Some(*n as f64),
+            _ => None,
+        }
+    }
+
+    /// Returns value as date.
+    pub fn as_date(&self) -> Option<OffsetDateTime> {
+        match self {
+            Self::Date(dt) => Some(*dt),
+            _ => None,
+        }
+    }
+
+    /// Returns value as bytes,
+    /// if an image or binary data.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Self::Jpeg(b)
+            | Self::Png(b)
+            | Self::Bmp(b)
+            | Self::Binary{data: b, ..} => Some(b.as_slice()),
+            _ => None,
+        }
+    }
+}
+
+impl Meta {
+    /// Parses the data load of a `meta` atom,
+    /// i.e. excluding the header.
+    ///
+    /// Both QuickTime `meta` and ISO `meta`
+    /// (version and flags preceding child atoms) are supported.
+    pub fn new(data: &[u8]) -> Result<Self, Mp4Error> {
+        let data = match Self::is_iso(data) {
+            true => &data[4..],
+            false => data,
+        };
+
+        let mut meta = Self::default();
+        let mut ilst: Option<&[u8]> = None;
+
+        for (name, load) in child_atoms(data)? {
+            match &name {
+                b"hdlr" => meta.hdlr = Some(hdlr(load)?),
+                b"keys" => meta.keys = keys(load)?,
+                b"ilst" => ilst = Some(load),
+                _ => (),
+            }
+        }
+
+        // Keys precede item list, but parse after
+        // to be independent of atom order
+        if let Some(load) = ilst {
+            meta.items = meta.ilst(load)?;
+        }
+
+        Ok(meta)
+    }
+
+    /// Returns `true` if `data`, the data load of a `meta` atom,
+    /// is ISO `meta`. ISO `meta` is a full atom, i.e. version and flags
+    /// (all zero) precede child atoms, whereas QuickTime `meta` is not.
+    pub(crate) fn is_iso(data: &[u8]) -> bool {
+        matches!(data.get(..4), Some([0, 0, 0, 0]))
+    }
+
+    /// Parses item list (`ilst`).
+    fn ilst(&self, data: &[u8]) -> Result<Vec<MetaItem>, Mp4Error> {
+        let mut items: Vec<MetaItem> = Vec::new();
+        for (name, load) in child_atoms(data)? {
+            // QuickTime items refer to a key via its 1-based index,
+            // iTunes-style items are identified by FourCC
+            let key = match u32::from_be_bytes(name) {
+                i if i > 0 && self.keys.len() >= i as usize => self.keys[i as usize - 1].key.to_owned(),
+                _ => FourCC::from_slice(&name).to_str().to_owned(),
+            };
+
+            let children = child_atoms(load)?;
+
+            // iTunes freeform item '----'
+            let mean = children.iter().find(|(n, _)| n == b"mean");
+            let freeform = children.iter().find(|(n, _)| n == b"name");
+            let key = match (mean, freeform) {
+                (Some((_, m)), Some((_, n))) => format!(
+                    "{}:{}",
+                    String::from_utf8_lossy(m.get(4..).unwrap_or_default()),
+                    String::from_utf8_lossy(n.get(4..).unwrap_or_default()),
+                ),
+                _ => key,
+            };
+
+            for (_, load) in children.iter().filter(|(n, _)| n == b"data") {
+                if load.len() < 8 {
+                    continue;
+                }
+                // First byte in type indicator is reserved
+                let type_code = u32::from_be_bytes([0, load[1], load[2], load[3]]);
+                let locale = u32::from_be_bytes([load[4], load[5], load[6], load[7]]);
+                items.push(MetaItem {
+                    value: MetaValue::new(&key, type_code, &load[8..]),
+                    key: key.to_owned(),
+                    locale,
+                })
+            }
+        }
+        Ok(items)
+    }
+
+    /// Metadata handler.
+    pub fn hdlr(&self) -> Option<&Hdlr> {
+        self.hdlr.as_ref()
+    }
+
+    /// Keys (`keys`). Empty for iTunes-style metadata.
+    pub fn keys(&self) -> &[MetaKey] {
+        &self.keys
+    }
+
+    /// Items (`ilst`), one for each value.
+    pub fn items(&self) -> &[MetaItem] {
+        &self.items
+    }
+
+    /// Returns first value for `key`,
+    /// e.g. `com.apple.quicktime.make`, or `©nam`.
+    pub fn get(&self, key: &str) -> Option<&MetaValue> {
+        self.items.iter()
+            .find(|item| item.key == key)
+            .map(|item| &item.value)
+    }
+
+    /// Iterates over key, value pairs.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &MetaValue)> {
+        self.items.iter()
+            .map(|item| (item.key.as_str(), &item.value))
+    }
+
+    /// Returns `true` if there are no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+/// Metadata for movie and tracks.
+/// See `Mp4::metadata()`.
+#[derive(Debug, Default, Clone)]
+pub struct Metadata {
+    /// Movie metadata (`moov/meta`).
+    pub(crate) movie: Option<Meta>,
+    /// Track metadata (`moov/trak/meta`),
+    /// with track ID.
+    pub(crate) tracks: Vec<(u32, Meta)>,
+}
+
+impl Metadata {
+    /// Movie metadata (`moov/meta`).
+    pub fn movie(&self) -> Option<&Meta> {
+        self.movie.as_ref()
+    }
+
+    /// Track metadata (`moov/trak/meta`),
+    /// as `(TRACK_ID, META)`.
+    pub fn tracks(&self) -> &[(u32, Meta)] {
+        &self.tracks
+    }
+
+    /// Metadata for track with ID `track_id`.
+    pub fn track(&self, track_id: u32) -> Option<&Meta> {
+        self.tracks.iter()
+            .find(|(id, _)| *id == track_id)
+            .map(|(_, meta)| meta)
+    }
+
+    /// Returns first value for `key`,
+    /// in movie metadata, then track metadata.
+    pub fn get(&self, key: &str) -> Option<&MetaValue> {
+        self.movie.iter()
+            .chain(self.tracks.iter().map(|(_, meta)| meta))
+            .find_map(|meta| meta.get(key))
+    }
+}
+
+/// Parses handler (`hdlr`) within `meta`.
+fn hdlr(data: &[u8]) -> Result<Hdlr, Mp4Error> {
+    let mut cursor = Cursor::new(data);
+    let mut hdlr = cursor.read_ne::<Hdlr>()?;
+    let name = data.get(cursor.position() as usize ..).unwrap_or_default();
+    hdlr.component_name = name.iter()
+        .filter(|b| **b != 0)
+        .map(|b| *b as char)
+        .collect();
+    Ok(hdlr)
+}
+
+/// Parses keys (`keys`).
+fn keys(data: &[u8]) -> Result<Vec<MetaKey>, Mp4Error> {
+    // Skip version, flags, and entry count,
+    // remaining entries are structured as atoms
+    let data = data.get(8..).unwrap_or_default();
+    Ok(child_atoms(data)?.into_iter()
+        .map(|(namespace, key)| MetaKey {
+            namespace: FourCC::from_slice(&namespace),
+            key: String::from_utf8_lossy(key).into_owned(),
+        })
+        .collect())
+}
+
+/// Child atom as `(NAME, DATA_LOAD)`.
+type ChildAtom<'a> = ([u8; 4], &'a [u8]);
+
+/// Splits `data` into child atoms, as `(NAME, DATA_LOAD)`.
+/// The name is returned as raw bytes, since `ilst` items
+/// may use a 32-bit key index instead of a FourCC.
+/// Trailing bytes too few for an atom header are ignored.
+fn child_atoms(data: &[u8]) -> Result<Vec<ChildAtom<'_>>, Mp4Error> {
+    let mut atoms: Vec<ChildAtom> = Vec::new();
+    let mut pos = 0;
+    while pos + 8 <= data.len() {
+        let size = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as usize;
+        if size < 8 || pos + size > data.len() {
+            return Err(Mp4Error::UnexpectedAtomSize { len: size as u64, offset: pos as u64 })
+        }
+        let name = [data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]];
+        atoms.push((name, &data[pos + 8 .. pos + size]));
+        pos += size;
+    }
+    Ok(atoms)
+}
+
+/// Big-endian unsigned integer from up to 8 bytes.
+fn be_u64(data: &[u8]) -> u64 {
+    data.iter().fold(0, |n, b| (n << 8) | *b as u64)
+}
+
+/// Parses ISO 8601 date, e.g. `2023-05-01T12:34:56+0200`.
+/// Time and offset are optional.
+fn parse_iso8601(value: &str) -> Option<OffsetDateTime> {
+    let value = value.trim();
+    let (date, rest) = value.split_at_checked(10)?;
+    let mut ymd = date.split('-');
+    let date = Date::from_calendar_date(
+        ymd.next()?.parse().ok()?,
+        Month::try_from(ymd.next()?.parse::<u8>().ok()?).ok()?,
+        ymd.next()?.parse().ok()?,
+    ).ok()?;
+
+    let rest = rest.strip_prefix(['T', ' ']).unwrap_or(rest);
+    // Offset starts at 'Z', '+', or '-' after time
+    let (time, offset) = match rest.find(['Z', '+', '-']) {
+        Some(i) => rest.split_at(i),
+        None => (rest, ""),
+    };
+
+    let time = match time.is_empty() {
+        true => Time::MIDNIGHT,
+        false => {
+            let (hms, frac) = time.split_once('.').unwrap_or((time, ""));
+            let mut hms = hms.split(':');
+            let hour: u8 = hms.next()?.parse().ok()?;
+            let minute: u8 = hms.next()?.parse().ok()?;
+            let second: u8 = hms.next().map(|s| s.parse()).transpose().ok()?.unwrap_or(0);
+            let nano: u32 = match frac.is_empty() {
+                true => 0,
+                false => format!("{frac:0<9}").get(..9)?.parse().ok()?,
+            };
+            Time::from_hms_nano(hour, minute, second, nano).ok()?
+        }
+    };
+
+    let offset = match offset {
+        "" | "Z" => UtcOffset::UTC,
+        o => {
+            let sign: i8 = if o.starts_with('-') {-1} else {1};
+            let digits: String = o[1..].chars().filter(|c| c.is_ascii_digit()).collect();
+            let hours: i8 = digits.get(..2)?.parse().ok()?;
+            let minutes: i8 = digits.get(2..4).map(|m| m.parse()).transpose().ok()?.unwrap_or(0);
+            UtcOffset::from_hms(sign * hours, sign * minutes, 0).ok()?
+        }
+    };
+
+    Some(PrimitiveDateTime::new(date, time).assume_offset(offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Atom with `name` and data load `data`.
+    fn atom(name: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut atom = ((data.len() + 8) as u32).to_be_bytes().to_vec();
+        atom.extend_from_slice(name);
+        atom.extend_from_slice(data);
+        atom
+    }
+
+    /// `data` atom with type code and locale 0.
+    fn data_atom(type_code: u32, value: &[u8]) -> Vec<u8> {
+        let mut data = type_code.to_be_bytes().to_vec();
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(value);
+        atom(b"data", &data)
+    }
+
+    /// `keys` atom with `mdta` keys.
+    fn keys_atom(keys: &[String]) -> Vec<u8> {
+        let mut data = vec![0; 4];
+        data.extend_from_slice(&(keys.len() as u32).to_be_bytes());
+        for key in keys {
+            data.extend(atom(b"mdta", key.as_bytes()));
+        }
+        atom(b"keys", &data)
+    }
+
+    #[test]
+    fn iso8601() {
+        let dt = parse_iso8601("2023-05-01T12:34:56+0200").unwrap();
+        assert_eq!(dt.date(), Date::from_calendar_date(2023, Month::May, 1).unwrap());
+        assert_eq!(dt.time(), Time::from_hms(12, 34, 56).unwrap());
+        assert_eq!(dt.offset(), UtcOffset::from_hms(2, 0, 0).unwrap());
+
+        let dt = parse_iso8601("2023-05-01T12:34:56.5-05:30").unwrap();
+        assert_eq!(dt.time(), Time::from_hms_milli(12, 34, 56, 500).unwrap());
+        assert_eq!(dt.offset(), UtcOffset::from_hms(-5, -30, 0).unwrap());
+
+        let dt = parse_iso8601("2023-05-01 12:34Z").unwrap();
+        assert_eq!(dt.time(), Time::from_hms(12, 34, 0).unwrap());
+        assert_eq!(dt.offset(), UtcOffset::UTC);
+
+        let dt = parse_iso8601("2023-05-01").unwrap();
+        assert_eq!(dt.time(), Time::MIDNIGHT);
+        assert_eq!(dt.offset(), UtcOffset::UTC);
+
+        assert!(parse_iso8601("2023-13-01").is_none());
+        assert!(parse_iso8601("2023-05-01T25:00:00").is_none());
+        assert!(parse_iso8601("May 2023").is_none());
+    }
+
+    #[test]
+    fn quicktime_keys() {
+        // Key index 0x80 (128), i.e. non-ASCII atom name
+        let keys: Vec<String> = (1..=128).map(|i| format!("com.example.key{i}")).collect();
+        let mut ilst = atom(&1u32.to_be_bytes(), &data_atom(1, b"Apple"));
+        ilst.extend(atom(&128u32.to_be_bytes(), &data_atom(22, &[0x01, 0x00])));
+        ilst.extend(atom(&2u32.to_be_bytes(), &data_atom(1, b"2023-05-01T12:34:56Z")));
+
+        let mut data = keys_atom(&keys);
+        data.extend(atom(b"ilst", &ilst));
+        let meta = Meta::new(&data).unwrap();
+
+        assert_eq!(meta.keys().len(), 128);
+        assert_eq!(meta.get("com.example.key1"), Some(&MetaValue::Utf8("Apple".to_owned())));
+        assert_eq!(meta.get("com.example.key128").and_then(|v| v.as_int()), Some(256));
+        // Not a date key
+        assert_eq!(meta.get("com.example.key2").and_then(|v| v.as_str()), Some("2023-05-01T12:34:56Z"));
+    }
+
+    #[test]
+    fn itunes_items() {
+        let mut ilst = atom(b"\xa9nam", &data_atom(1, b"Title"));
+        ilst.extend(atom(b"\xa9day", &data_atom(1, b"2023-05-01")));
+        let mut freeform = atom(b"mean", b"\0\0\0\0com.apple.iTunes");
+        freeform.extend(atom(b"name", b"\0\0\0\0iTunSMPB"));
+        freeform.extend(data_atom(1, b"value"));
+        ilst.extend(atom(b"----", &freeform));
+
+        // ISO full atom 'meta', i.e. preceded by version and flags
+        let mut data = vec![0; 4];
+        data.extend(atom(b"ilst", &ilst));
+        let meta = Meta::new(&data).unwrap();
+
+        assert_eq!(meta.get("©nam").and_then(|v| v.as_str()), Some("Title"));
+        assert!(meta.get("©day").and_then(|v| v.as_date()).is_some());
+        assert_eq!(meta.get("com.apple.iTunes:iTunSMPB").and_then(|v| v.as_str()), Some("value"));
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(MetaValue::new("", 21, &[0xff, 0xfe]), MetaValue::Int(-2));
+        assert_eq!(MetaValue::new("", 22, &[0xff, 0xfe]), MetaValue::UInt(0xfffe));
+        assert_eq!(MetaValue::new("", 23, &1.5f32.to_be_bytes()), MetaValue::Float32(1.5));
+        assert_eq!(MetaValue::new("", 2, &[0, b'h', 0, b'i']), MetaValue::Utf16("hi".to_owned()));
+        assert_eq!(
+            MetaValue::new("", 0, &[1, 2]),
+            MetaValue::Binary{type_code: 0, data: vec![1, 2]}
+        );
+    }
 
-}
\ No newline at end of file
+    #[test]
+    fn corrupt_atom_size() {
+        let mut data = atom(b"ilst", &[]);
+        data[3] = 0xff;
+        assert!(Meta::new(&data).is_err());
+    }
+}
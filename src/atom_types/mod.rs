@@ -10,8 +10,10 @@ mod stts;
 mod stsc;
 mod stss;
 mod ctts;
+mod cslg;
 mod tmcd;
 mod tkhd;
+mod tref;
 mod hdlr;
 mod mdhd;
 mod meta;
@@ -26,19 +28,160 @@ pub use dref::{Dref, DrefTable};
 pub use elst::{Elst, EditListTable};
 pub use ftyp::Ftyp;
 pub use smhd::Smhd;
-pub use sdtp::Sdtp;
+pub use sdtp::{Sdtp, SampleDependency};
 pub use stco::Stco;
 pub use co64::Co64;
 pub use stsz::Stsz;
 pub use stts::Stts;
 pub use stss::Stss;
 pub use stsc::Stsc;
-pub use ctts::Ctts;
+pub use ctts::{Ctts, OffsetTableEntry};
+pub use cslg::Cslg;
 pub use tmcd::Tmcd;
 pub use hdlr::Hdlr;
 pub use tkhd::Tkhd;
+pub use tref::{Tref, TrackReference};
 pub use mdhd::Mdhd;
+pub(crate) use mdhd::derive_language_code;
+pub use meta::{Meta, MetaItem, MetaKey, MetaValue, Metadata};
 pub use mvhd::Mvhd;
 pub use stsd::{Stsd, SampleDescription, AudioFormat, VideoFormat};
 pub use vmhd::Vmhd;
 pub(crate) use types::AtomType;
+
+/// Reads a time or duration value in a full atom,
+/// which is 64-bit for version 1, otherwise 32-bit.
+#[binrw::parser(reader, endian)]
+pub(crate) fn versioned_u64(version: u8) -> binrw::BinResult<u64> {
+    match version {
+        1 => <u64 as binrw::BinRead>::read_options(reader, endian, ()),
+        _ => <u32 as binrw::BinRead>::read_options(reader, endian, ()).map(u64::from),
+    }
+}
+
+/// Reads a signed time value in a full atom,
+/// which is 64-bit for version 1, otherwise 32-bit.
+#[binrw::parser(reader, endian)]
+pub(crate) fn versioned_i64(version: u8) -> binrw::BinResult<i64> {
+    match version {
+        1 => <i64 as binrw::BinRead>::read_options(reader, endian, ()),
+        _ => <i32 as binrw::BinRead>::read_options(reader, endian, ()).map(i64::from),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use binrw::BinReaderExt;
+
+    use crate::testing::be_u32;
+
+    use super::*;
+
+    fn be_u64(values: &[u64]) -> Vec<u8> {
+        values.iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect()
+    }
+
+    /// Version and zeroed flags, followed by `fields`.
+    fn full(version: u8, fields: &[Vec<u8>]) -> Vec<u8> {
+        [vec![version, 0, 0, 0], fields.concat()].concat()
+    }
+
+    /// Values that do not fit in 32 bits
+    const TIME: u64 = 0x1_0000_0001;
+    const DURATION: u64 = 0x2_0000_0002;
+
+    #[test]
+    fn mvhd() {
+        let tail = [
+            be_u32(&[0x0001_0000]), vec![1, 0], vec![0; 10 + 36 + 24], be_u32(&[3]),
+        ].concat();
+
+        let v0 = full(0, &[be_u32(&[10, 20, 1000, 5000]), tail.to_owned()]);
+        let mvhd: Mvhd = Cursor::new(v0).read_be().unwrap();
+        assert_eq!(mvhd.version(), 0);
+        assert_eq!((mvhd.creation_time, mvhd.modification_time), (10, 20));
+        assert_eq!(mvhd.time_scale, 1000);
+        assert_eq!(mvhd.duration().whole_seconds(), 5);
+        assert_eq!(mvhd.next_track_id, 3);
+
+        let v1 = full(1, &[be_u64(&[TIME, TIME + 1]), be_u32(&[1000]), be_u64(&[DURATION]), tail]);
+        let mvhd: Mvhd = Cursor::new(v1).read_be().unwrap();
+        assert_eq!(mvhd.version(), 1);
+        assert_eq!((mvhd.creation_time, mvhd.modification_time), (TIME, TIME + 1));
+        assert_eq!(mvhd.duration, DURATION);
+        assert_eq!(mvhd.preferred_volume, 0x100);
+        assert_eq!(mvhd.next_track_id, 3);
+    }
+
+    #[test]
+    fn tkhd() {
+        let tail = [
+            vec![0; 8], vec![0, 1, 0, 2, 1, 0, 0, 0], vec![0; 36], be_u32(&[1920 << 16, 1080 << 16]),
+        ].concat();
+
+        let v0 = full(0, &[be_u32(&[10, 20, 7, 0, 5000]), tail.to_owned()]);
+        let tkhd: Tkhd = Cursor::new(v0).read_be().unwrap();
+        assert_eq!(tkhd.version(), 0);
+        assert_eq!(tkhd.track_id(), 7);
+        assert_eq!(tkhd.duration(), 5000);
+        assert_eq!((tkhd.layer(), tkhd.alternate_group(), tkhd.volume()), (1, 2, 1.0));
+        assert_eq!((tkhd.width(), tkhd.height()), (1920.0, 1080.0));
+
+        let v1 = full(1, &[be_u64(&[TIME, TIME + 1]), be_u32(&[7, 0]), be_u64(&[DURATION]), tail]);
+        let tkhd: Tkhd = Cursor::new(v1).read_be().unwrap();
+        assert_eq!(tkhd.version(), 1);
+        assert_eq!((tkhd.creation_time, tkhd.modification_time), (TIME, TIME + 1));
+        assert_eq!(tkhd.track_id(), 7);
+        assert_eq!(tkhd.duration(), DURATION);
+        assert_eq!((tkhd.width(), tkhd.height()), (1920.0, 1080.0));
+    }
+
+    #[test]
+    fn mdhd() {
+        // Packed ISO 639-2/T 'eng'
+        let tail = vec![0x15, 0xc7, 0, 0];
+
+        let v0 = full(0, &[be_u32(&[10, 20, 48000, 96000]), tail.to_owned()]);
+        let mdhd: Mdhd = Cursor::new(v0).read_be().unwrap();
+        assert_eq!(mdhd.version(), 0);
+        assert_eq!((mdhd.creation_time(), mdhd.modification_time()), (10, 20));
+        assert_eq!(mdhd.time_scale(), 48000);
+        assert_eq!(mdhd.duration().whole_seconds(), 2);
+        assert_eq!(mdhd.language(), "eng");
+
+        let v1 = full(1, &[be_u64(&[TIME, TIME + 1]), be_u32(&[48000]), be_u64(&[DURATION]), tail]);
+        let mdhd: Mdhd = Cursor::new(v1).read_be().unwrap();
+        assert_eq!(mdhd.version(), 1);
+        assert_eq!((mdhd.creation_time(), mdhd.modification_time()), (TIME, TIME + 1));
+        assert_eq!(mdhd.duration_unscaled(), DURATION);
+        assert_eq!(mdhd.language(), "eng");
+    }
+
+    #[test]
+    fn elst() {
+        let v0 = full(0, &[be_u32(&[2, 1000, u32::MAX, 0x0001_0000, 2000, 500, 0x0001_0000])]);
+        let elst: Elst = Cursor::new(v0).read_be().unwrap();
+        let edits = elst.edit_list_table();
+        assert_eq!(elst.version(), 0);
+        assert_eq!(edits.len(), 2);
+        assert!(edits[0].is_empty());
+        assert_eq!((edits[1].track_duration(), edits[1].media_time(), edits[1].rate()), (2000, 500, 1.0));
+
+        let v1 = full(1, &[
+            be_u32(&[2]),
+            be_u64(&[DURATION, u64::MAX]), be_u32(&[0x0001_0000]),
+            be_u64(&[DURATION + 1, TIME]), be_u32(&[0x0000_8000]),
+        ]);
+        let elst: Elst = Cursor::new(v1).read_be().unwrap();
+        let edits = elst.edit_list_table();
+        assert_eq!(elst.version(), 1);
+        assert_eq!(edits.len(), 2);
+        assert!(edits[0].is_empty());
+        assert_eq!(edits[0].track_duration(), DURATION);
+        assert_eq!((edits[1].track_duration(), edits[1].media_time(), edits[1].rate()), (DURATION + 1, TIME as i64, 0.5));
+    }
+}
@@ -7,7 +7,9 @@
 use binrw::BinRead;
 use time::{Duration, ext::NumericalDuration};
 
-use crate::mp4_time_zero;
+use crate::mp4_datetime;
+
+use super::versioned_u64;
 
 /// Movie header atom (`mvhd`).
 /// 
@@ -17,12 +19,15 @@ use crate::mp4_time_zero;
 #[derive(Debug, BinRead)]
 #[br(big)]
 pub struct Mvhd {
-    _version: u8,
+    /// Version 1 uses 64-bit times and duration.
+    pub(crate) version: u8,
     _flags: [u8; 3],
     /// Seconds since midnight, 1904-01-01 UTC
-    pub creation_time: u32, // should be UTC
+    #[br(parse_with = versioned_u64, args(version))]
+    pub creation_time: u64, // should be UTC
     /// Seconds since midnight, 1904-01-01 UTC
-    pub modification_time: u32, // should be UTC
+    #[br(parse_with = versioned_u64, args(version))]
+    pub modification_time: u64, // should be UTC
     /// Number of time units that pass in one second
     pub time_scale: u32,
     /// Unscaled duration. I.e. "time units"
@@ -30,7 +35,8 @@ pub struct Mvhd {
     /// to derive a value in seconds.
     /// 
     /// Corresponds to the longest track.
-    pub duration: u32,
+    #[br(parse_with = versioned_u64, args(version))]
+    pub duration: u64,
     /// Fixed point number (16.16)
     /// representing preferred play rate
     /// (1.0 = normal playback).
@@ -51,16 +57,23 @@ pub struct Mvhd {
 }
 
 impl Mvhd {
+    /// Atom version. Version 1 uses 64-bit times and duration.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
     /// Creation time as UTC datetime.
     /// May default to MP4 default time
     /// `1904-01-01 00:00:00` depending on device and settings.
-    pub fn creation_time(&self) -> time::PrimitiveDateTime {
-        mp4_time_zero() + Duration::seconds(self.creation_time as i64)
+    /// `None` if outside supported datetime range.
+    pub fn creation_time(&self) -> Option<time::PrimitiveDateTime> {
+        mp4_datetime(self.creation_time)
     }
 
     /// Modification time as UTC datetime.
-    pub fn modification_time(&self) -> time::PrimitiveDateTime {
-        mp4_time_zero() + Duration::seconds(self.modification_time as i64)
+    /// `None` if outside supported datetime range.
+    pub fn modification_time(&self) -> Option<time::PrimitiveDateTime> {
+        mp4_datetime(self.modification_time)
     }
 
     /// Duration of the longest track in seconds.
@@ -1,6 +1,6 @@
 //! Sample dependency flags atom (`sdtp`)
 //!
-//! Location:
+//! Location: `moov/trak[multiple]/mdia/minf/stbl/sdtp`
 //!
 //! See: <https://developer.apple.com/documentation/quicktime-file-format/sample_dependency_flags_atom>
 
@@ -14,35 +14,141 @@ use binrw::BinRead;
 /// that follows the sdtp atom.
 ///
 /// See: <https://developer.apple.com/documentation/quicktime-file-format/sample_dependency_flags_atom>
-#[derive(Debug, Default, BinRead)]
+#[derive(Debug, Default, Clone, BinRead)]
 #[br(big, import {data_size: u32})] // attempt to use size for deriving sample flags table size instead.
 pub struct Sdtp {
     _version: u8,
     _flags: [u8; 3],
     /// Sample dependency flags table.
-    /// A table of 8-bit values indicating the sample flag settings.
-    #[br(count = (data_size - 4) / 2)]
-    pub(crate) sample_flags_table: Vec<SampleFlagsTable>
+    /// A table of 8-bit values indicating the sample flag settings,
+    /// one for each sample.
+    #[br(count = data_size.saturating_sub(4))]
+    pub(crate) sample_flags_table: Vec<SampleDependency>
 }
 
 impl Sdtp {
-    pub fn sample_flags_table(&self) -> &[SampleFlagsTable] {
+    pub fn sample_flags_table(&self) -> &[SampleDependency] {
         &self.sample_flags_table
     }
 }
 
-#[derive(Debug, Default, BinRead)]
-pub struct SampleFlagsTable {
+/// Sample dependency flags for a single sample,
+/// four 2-bit values, where 0 means unknown.
+///
+/// See: <https://developer.apple.com/documentation/quicktime-file-format/sample_dependency_flags_atom>
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, BinRead)]
+pub struct SampleDependency {
     pub(crate) sample_dependency_flag: u8,
-    pub(crate) sample: u8,
 }
 
-impl SampleFlagsTable {
+impl SampleDependency {
+    /// Raw 8-bit flags.
     pub fn sample_dependency_flag(&self) -> u8 {
         self.sample_dependency_flag
     }
 
-    pub fn sample(&self) -> u8 {
-        self.sample
+    /// Returns `true` if the sample is a leading sample,
+    /// i.e. precedes the preceding sync sample in presentation order.
+    /// `None` if unknown.
+    pub fn is_leading(&self) -> Option<bool> {
+        match (self.sample_dependency_flag >> 6) & 0b11 {
+            1 | 3 => Some(true),
+            2 => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the sample depends on other samples,
+    /// i.e. is not an I-frame. `None` if unknown.
+    pub fn depends_on(&self) -> Option<bool> {
+        match (self.sample_dependency_flag >> 4) & 0b11 {
+            1 => Some(true),
+            2 => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if other samples depend on the sample,
+    /// `false` if the sample is disposable. `None` if unknown.
+    pub fn is_depended_on(&self) -> Option<bool> {
+        match (self.sample_dependency_flag >> 2) & 0b11 {
+            1 => Some(true),
+            2 => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the sample contains redundant coding.
+    /// `None` if unknown.
+    pub fn has_redundancy(&self) -> Option<bool> {
+        match self.sample_dependency_flag & 0b11 {
+            1 => Some(true),
+            2 => Some(false),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use binrw::BinReaderExt;
+
+    use super::*;
+
+    fn dependency(flag: u8) -> SampleDependency {
+        SampleDependency { sample_dependency_flag: flag }
+    }
+
+    #[test]
+    fn unknown() {
+        let d = dependency(0);
+        assert_eq!(d.is_leading(), None);
+        assert_eq!(d.depends_on(), None);
+        assert_eq!(d.is_depended_on(), None);
+        assert_eq!(d.has_redundancy(), None);
+    }
+
+    #[test]
+    fn bits() {
+        // I-frame: not leading, depends on no other sample,
+        // others depend on it, no redundancy
+        let d = dependency(0b10_10_01_10);
+        assert_eq!(d.sample_dependency_flag(), 0xa6);
+        assert_eq!(d.is_leading(), Some(false));
+        assert_eq!(d.depends_on(), Some(false));
+        assert_eq!(d.is_depended_on(), Some(true));
+        assert_eq!(d.has_redundancy(), Some(false));
+
+        // Disposable B-frame: depends on others, none depend on it
+        let d = dependency(0b00_01_10_01);
+        assert_eq!(d.is_leading(), None);
+        assert_eq!(d.depends_on(), Some(true));
+        assert_eq!(d.is_depended_on(), Some(false));
+        assert_eq!(d.has_redundancy(), Some(true));
+    }
+
+    #[test]
+    fn leading() {
+        // Leading with dependency before the referenced I-frame
+        assert_eq!(dependency(0b01 << 6).is_leading(), Some(true));
+        // Not leading
+        assert_eq!(dependency(0b10 << 6).is_leading(), Some(false));
+        // Leading without dependency
+        assert_eq!(dependency(0b11 << 6).is_leading(), Some(true));
+        // Reserved value for other fields
+        assert_eq!(dependency(0b11 << 4).depends_on(), None);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn one_byte_per_sample() {
+        let data = vec![0, 0, 0, 0, 0x20, 0x10, 0x18];
+        let size = data.len() as u32;
+        let sdtp = Cursor::new(data).read_be_args::<Sdtp>(binrw::args! {data_size: size}).unwrap();
+        assert_eq!(sdtp.sample_flags_table().len(), 3);
+        assert_eq!(sdtp.sample_flags_table()[0].depends_on(), Some(false));
+        assert_eq!(sdtp.sample_flags_table()[1].depends_on(), Some(true));
+        assert_eq!(sdtp.sample_flags_table()[2].is_depended_on(), Some(false));
+    }
+}
@@ -1,6 +1,6 @@
 use binrw::BinRead;
 
-#[derive(Debug, BinRead)]
+#[derive(Debug, Clone, BinRead)]
 #[br(import {size: u32})]
 pub struct Audio {
     pub(crate) version: u16,
@@ -51,7 +51,7 @@ impl Audio {
     }
 }
 
-#[derive(Debug, BinRead)]
+#[derive(Debug, Clone, BinRead)]
 #[br(import {size: u32, version: u16})]
 pub enum AudioVersion {
     #[br(pre_assert(version == 0))]
@@ -71,7 +71,7 @@ pub enum AudioVersion {
     ),
 }
 
-#[derive(Debug, BinRead)]
+#[derive(Debug, Clone, BinRead)]
 #[br(import {size: u32})]
 pub struct AudioVersion0 {
     /// A 16-bit integer that indicates the number of
@@ -91,7 +91,7 @@ pub struct AudioVersion0 {
     pub extensions: Vec<u8>
 }
 
-#[derive(Debug, BinRead)]
+#[derive(Debug, Clone, BinRead)]
 #[br(import {size: u32})]
 pub struct AudioVersion1 {
     /// A 16-bit integer that indicates the number of
@@ -120,7 +120,7 @@ pub struct AudioVersion1 {
     pub extensions: Vec<u8>
 }
 
-#[derive(Debug, BinRead)]
+#[derive(Debug, Clone, BinRead)]
 #[br(import {size: u32})]
 pub struct AudioVersion2 {
     _always3: i16,
@@ -5,7 +5,7 @@ use binrw::BinRead;
 use super::{Audio, Video};
 use crate::support::chars_from_be_u32;
 
-#[derive(Debug, BinRead)]
+#[derive(Debug, Clone, BinRead)]
 #[br(import {size: u32, data_format: DataFormat})]
 pub enum DataLoad {
     #[br(pre_assert(data_format.is_video()))]
@@ -8,7 +8,7 @@ use crate::{Tmcd, Mp4Error};
 
 use super::{Audio, DataFormat, DataLoad, Video};
 
-#[derive(Debug, BinRead)]
+#[derive(Debug, Clone, BinRead)]
 pub struct SampleDescription {
     // General fields. Apply to all stsd atoms.
     // 20 bytes.
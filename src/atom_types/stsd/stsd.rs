@@ -11,7 +11,7 @@ use super::{Audio, AudioFormat, DataFormat, Video, VideoFormat, SampleDescriptio
 /// Video sample description atom (`stsd`).
 ///
 /// See: <https://developer.apple.com/documentation/quicktime-file-format/video_sample_description>
-#[derive(Debug, Default, BinRead)]
+#[derive(Debug, Default, Clone, BinRead)]
 pub struct Stsd {
     _version: u8,
     _flags: [u8; 3],
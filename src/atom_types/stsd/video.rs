@@ -2,7 +2,7 @@ use binrw::BinRead;
 
 use crate::support::counted_string;
 
-#[derive(Debug, BinRead)]
+#[derive(Debug, Clone, BinRead)]
 #[br(import {size: u32})]
 pub struct Video {
     /// A 16-bit integer that holds the sample description version.
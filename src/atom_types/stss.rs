@@ -7,44 +7,56 @@
 use binrw::BinRead;
 
 /// Sync sample atom (`stss`).
+/// Lists key frames (sync samples), i.e. samples that can be decoded
+/// without any other sample. If not present, all samples are sync samples.
 ///
-/// Location:
+/// Location: `moov/trak[multiple]/mdia/minf/stbl/stss`
 ///
 /// See: <https://developer.apple.com/documentation/quicktime-file-format/sync_sample_atom>
-#[derive(Debug, Default, BinRead)]
+#[derive(Debug, Default, Clone, BinRead)]
 #[br(big)]
 pub struct Stss {
     _version: u8,
     _flags: [u8; 3],
     _number_of_entries: u32,
+    /// 1-based sample numbers for sync samples,
+    /// in increasing order.
     #[br(count = _number_of_entries)]
-    pub(crate) sync_sample_table: Vec<SyncSample>
+    pub(crate) sync_sample_table: Vec<u32>
 }
 
 impl Stss {
-    pub fn sync_sample_table(&self) -> &[SyncSample] {
+    /// Returns 1-based sample numbers for sync samples.
+    pub fn sync_sample_table(&self) -> &[u32] {
         &self.sync_sample_table
     }
-}
 
-/// Table of sample numbers.
-///
-/// Note that field sizes are not specifed in Apple's QuickTime documentation,
-/// and that 2 x `u16` is pure guess based on example file where
-/// number of entries is 88 over 352 bytes = 4 bytes / sync sample,
-/// possibly meaning two 16-bit integers (or one u8 + [u8; 3], or...).
-#[derive(Debug, Default, BinRead)]
-pub struct SyncSample {
-    pub(crate) entry_number: u16,
-    pub(crate) sample: u16
+    /// Returns `true` if the sample with 1-based
+    /// sample number `sample` is a sync sample.
+    pub fn is_sync(&self, sample: u32) -> bool {
+        self.sync_sample_table.binary_search(&sample).is_ok()
+    }
 }
 
-impl SyncSample {
-    pub fn entry_number(&self) -> u16 {
-        self.entry_number
-    }
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
 
-    pub fn sample(&self) -> u16 {
-        self.sample
+    use binrw::BinReaderExt;
+
+    use super::*;
+
+    #[test]
+    fn sync_samples() {
+        let mut data = vec![0, 0, 0, 0, 0, 0, 0, 3];
+        for sample in [1u32, 6, 70_000] {
+            data.extend_from_slice(&sample.to_be_bytes());
+        }
+        let stss = Cursor::new(data).read_be::<Stss>().unwrap();
+        assert_eq!(stss.sync_sample_table(), &[1, 6, 70_000]);
+        assert!(stss.is_sync(1));
+        assert!(stss.is_sync(70_000));
+        assert!(!stss.is_sync(2));
+        assert!(!stss.is_sync(0));
     }
-}
\ No newline at end of file
+}
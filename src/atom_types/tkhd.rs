@@ -5,7 +5,9 @@
 //! See: <https://developer.apple.com/documentation/quicktime-file-format/track_header_atom>
 
 use binrw::BinRead;
-use time::{Duration, PrimitiveDateTime};
+use time::PrimitiveDateTime;
+
+use super::versioned_u64;
 
 /// Track header atom (`tkhd`).
 ///
@@ -15,16 +17,19 @@ use time::{Duration, PrimitiveDateTime};
 #[derive(Debug, BinRead)]
 #[br(big)]
 pub struct Tkhd {
-    _version: u8,
+    /// Version 1 uses 64-bit times and duration.
+    pub(crate) version: u8,
     _flags: [u8; 3],
     /// Indicates the creation calendar date and time for the track header.
     /// Represents the calendar date and time in seconds since midnight,
     /// January 1, 1904, preferably using coordinated universal time (UTC).
-    pub(crate) creation_time: u32,
+    #[br(parse_with = versioned_u64, args(version))]
+    pub(crate) creation_time: u64,
     /// Indicates the last change date for the track header.
     /// Represents the calendar date and time in seconds since midnight,
     /// January 1, 1904, preferably using coordinated universal time (UTC).
-    pub(crate) modification_time: u32,
+    #[br(parse_with = versioned_u64, args(version))]
+    pub(crate) modification_time: u64,
     /// Uniquely identifies the track.
     /// Value 0 cannot be used.
     pub(crate) track_id: u32,
@@ -37,7 +42,8 @@ pub struct Tkhd {
     /// of all of the track’s edits.
     /// If there is no edit list, then the duration is the sum of the sample durations,
     /// converted into the movie timescale.
-    pub(crate) duration: u32,
+    #[br(parse_with = versioned_u64, args(version))]
+    pub(crate) duration: u64,
     _reserved2: [u8; 8],
     /// This track’s spatial priority in its movie.
     layer: u16,
@@ -59,6 +65,11 @@ pub struct Tkhd {
 }
 
 impl Tkhd {
+    /// Atom version. Version 1 uses 64-bit times and duration.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
     pub fn track_id(&self) -> u32 {
         self.track_id
     }
@@ -88,7 +99,7 @@ impl Tkhd {
     }
 
     /// This track's unscaled duration.
-    pub fn duration(&self) -> u32 {
+    pub fn duration(&self) -> u64 {
         self.duration
     }
 
@@ -101,23 +112,15 @@ impl Tkhd {
         self.matrix_structure.as_slice()
     }
 
-    /// Creation time as duration.
-    fn creation_duration(&self) -> Duration {
-        Duration::seconds(self.creation_time as i64)
-    }
-
     /// Creation datetime for this track.
-    pub fn creation_time(&self) -> PrimitiveDateTime {
-        crate::consts::mp4_time_zero() + self.creation_duration()
-    }
-
-    /// Modification time as duration.
-    fn modification_duration(&self) -> Duration {
-        Duration::seconds(self.modification_time as i64)
+    /// `None` if outside supported datetime range.
+    pub fn creation_time(&self) -> Option<PrimitiveDateTime> {
+        crate::consts::mp4_datetime(self.creation_time)
     }
 
     /// Modification datetime for this track.
-    pub fn modification_time(&self) -> PrimitiveDateTime {
-        crate::consts::mp4_time_zero() + self.modification_duration()
+    /// `None` if outside supported datetime range.
+    pub fn modification_time(&self) -> Option<PrimitiveDateTime> {
+        crate::consts::mp4_datetime(self.modification_time)
     }
 }
\ No newline at end of file
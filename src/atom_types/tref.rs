@@ -0,0 +1,130 @@
+//! Track reference atom (`tref`).
+//!
+//! Location: `moov/trak[multiple]/tref`
+//!
+//! See: <https://developer.apple.com/documentation/quicktime-file-format/track_reference_atom>
+
+use std::collections::BTreeMap;
+
+use binrw::{helpers::until_eof, BinRead};
+
+use crate::support::string_from_bytes;
+
+/// Track reference atom (`tref`).
+///
+/// Contains one track reference type atom for each type of reference,
+/// each listing the IDs of the referenced tracks.
+///
+/// Location: `moov/trak[multiple]/tref`
+///
+/// See: <https://developer.apple.com/documentation/quicktime-file-format/track_reference_atom>
+#[derive(Debug, Default, Clone, BinRead)]
+#[br(big)]
+pub struct Tref {
+    #[br(parse_with = until_eof)]
+    pub(crate) references: Vec<TrackReference>,
+}
+
+impl Tref {
+    pub fn references(&self) -> &[TrackReference] {
+        &self.references
+    }
+
+    /// Returns IDs for tracks referenced via `reference_type`,
+    /// e.g. `tmcd`.
+    pub fn track_ids(&self, reference_type: &str) -> &[u32] {
+        self.references.iter()
+            .find(|r| r.reference_type == reference_type)
+            .map(|r| r.track_ids.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns reference type and referenced track IDs
+    /// as a map.
+    pub fn to_map(&self) -> BTreeMap<String, Vec<u32>> {
+        let mut map: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+        for reference in self.references.iter() {
+            map.entry(reference.reference_type.to_owned())
+                .or_default()
+                .extend_from_slice(&reference.track_ids);
+        }
+        map
+    }
+}
+
+/// Track reference type atom.
+///
+/// Reference types include:
+/// - `tmcd`: time code track for this track
+/// - `chap`: chapter or scene list track for this track
+/// - `hint`: original media track(s) for this hint track
+/// - `cdsc`: track described by this (timed metadata) track
+/// - `vdep`: auxiliary depth video track for this track
+/// - `fall`: fallback (alternate) track for this track
+/// - `sync`: synchronization source for this track
+#[derive(Debug, Default, Clone, BinRead)]
+#[br(big)]
+pub struct TrackReference {
+    /// Size in bytes, including size and reference type.
+    #[br(assert(_size >= 8, "track reference size {} is less than 8", _size))]
+    _size: u32,
+    #[br(map(|data: [u8; 4]| string_from_bytes(data)))]
+    pub(crate) reference_type: String,
+    /// Padded to `_size`, so that the next
+    /// track reference type atom is read at the correct
+    /// position if the size is not a multiple of 4.
+    #[br(count = (_size - 8) / 4, pad_size_to = _size - 8)]
+    pub(crate) track_ids: Vec<u32>,
+}
+
+impl TrackReference {
+    /// Reference type, e.g. `tmcd`.
+    pub fn reference_type(&self) -> &str {
+        &self.reference_type
+    }
+
+    /// IDs for referenced tracks.
+    pub fn track_ids(&self) -> &[u32] {
+        &self.track_ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use binrw::BinReaderExt;
+
+    use super::*;
+
+    fn reference(size: u32, reference_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        [&size.to_be_bytes(), reference_type.as_slice(), data].concat()
+    }
+
+    #[test]
+    fn track_references() {
+        let data = [
+            reference(16, b"tmcd", &[0, 0, 0, 3, 0, 0, 0, 4]),
+            // Two trailing bytes
+            reference(14, b"chap", &[0, 0, 0, 5, 0xff, 0xff]),
+            reference(8, b"sync", &[]),
+            reference(12, b"tmcd", &[0, 0, 0, 6]),
+        ].concat();
+        let tref = Cursor::new(data).read_be::<Tref>().unwrap();
+        assert_eq!(tref.references().len(), 4);
+        assert_eq!(tref.track_ids("tmcd"), &[3, 4]);
+        assert_eq!(tref.track_ids("chap"), &[5]);
+        assert!(tref.track_ids("sync").is_empty());
+        assert!(tref.track_ids("hint").is_empty());
+        assert_eq!(tref.to_map().get("tmcd"), Some(&vec![3, 4, 6]));
+    }
+
+    #[test]
+    fn invalid_size() {
+        let data = [
+            reference(12, b"tmcd", &[0, 0, 0, 3]),
+            reference(4, b"chap", &[0, 0, 0, 5]),
+        ].concat();
+        assert!(Cursor::new(data).read_be::<Tref>().is_err());
+    }
+}
@@ -6,7 +6,7 @@
 
 use crate::AtomHeader;
 
-use super::{Co64, Ctts, Dref, Elst, Ftyp, Hdlr, Mdhd, Mvhd, Sdtp, Smhd, Stco, Stsc, Stsd, Stss, Stsz, Stts, Tkhd, Tmcd, Vmhd};
+use super::{Co64, Cslg, Ctts, Dref, Elst, Ftyp, Hdlr, Mdhd, Mvhd, Sdtp, Smhd, Stco, Stsc, Stsd, Stss, Stsz, Stts, Tkhd, Tmcd, Vmhd};
 
 /// MP4 atom Four CC.
 /// See atom type in <https://developer.apple.com/documentation/quicktime-file-format/atoms>.
@@ -14,6 +14,8 @@ use super::{Co64, Ctts, Dref, Elst, Ftyp, Hdlr, Mdhd, Mvhd, Sdtp, Smhd, Stco, St
 pub(crate) enum AtomType {
     /// Composition offset atom
     Ctts(Ctts),
+    /// Composition shift least greatest atom
+    Cslg(Cslg),
     /// Data Information Atoms
     Dref(Dref),
     Edts(AtomHeader),
@@ -0,0 +1,208 @@
+//! Chapters.
+//!
+//! Chapters are stored in one of:
+//! - A QuickTime text (`text`) or 3GPP timed text (`tx3g`) track,
+//!   referenced from another track via `tref/chap`.
+//!   Each sample is a chapter, with the title as sample data.
+//! - A Nero chapter list (`chpl`) in user data (`moov/udta`).
+//!
+//! ```rs
+//! use mp4iter::Mp4;
+//! use std::path::Path;
+//!
+//! fn main() -> Result<(), mp4iter::Mp4Error> {
+//!     let mut mp4 = Mp4::new(Path::new("VIDEO.MP4"))?;
+//!     for chapter in mp4.chapters()? {
+//!         println!("{} {} {}", chapter.start, chapter.duration, chapter.title);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use time::Duration;
+
+/// Single chapter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    /// Start time, relative to the start of the MP4.
+    pub start: Duration,
+    /// Duration.
+    pub duration: Duration,
+    /// Title.
+    pub title: String,
+}
+
+impl Chapter {
+    /// Parses text sample from a chapter track (`text` or `tx3g`),
+    /// i.e. a 16-bit size followed by the title,
+    /// either UTF-8 or UTF-16 with byte order mark.
+    /// Any trailing atoms, e.g. `encd` or `styl`, are ignored.
+    pub fn from_text_sample(data: &[u8], start: Duration, duration: Duration) -> Self {
+        let size = match data.get(..2) {
+            Some(b) => u16::from_be_bytes([b[0], b[1]]) as usize,
+            None => 0,
+        };
+        // Clamp size to sample, e.g. for truncated samples
+        let text = data.get(2..).unwrap_or_default();
+        let text = &text[.. size.min(text.len())];
+
+        let title = match text {
+            [0xfe, 0xff, rest @ ..] => utf16(rest, u16::from_be_bytes),
+            [0xff, 0xfe, rest @ ..] => utf16(rest, u16::from_le_bytes),
+            _ => String::from_utf8_lossy(text).into_owned(),
+        };
+
+        Self {
+            start,
+            duration,
+            title: title.trim_end_matches('\0').to_owned(),
+        }
+    }
+
+    /// Parses Nero chapter list atom (`chpl`) data load,
+    /// i.e. excluding the header.
+    ///
+    /// Only start times are stored, so each chapter
+    /// lasts until the next one, and the last chapter
+    /// until `end`, usually the duration of the MP4.
+    pub fn from_chpl(data: &[u8], end: Duration) -> Option<Vec<Self>> {
+        // version, flags, and for version 1
+        // an additional 4 bytes
+        let mut pos = match data.first()? {
+            0 => 4,
+            _ => 8,
+        };
+        let count = *data.get(pos)?;
+        pos += 1;
+
+        let mut chapters: Vec<Self> = Vec::new();
+        for _ in 0..count {
+            let bytes: [u8; 8] = data.get(pos .. pos + 8)?.try_into().ok()?;
+            // Start time in 100 nanosecond units
+            let start = Duration::nanoseconds(i64::from_be_bytes(bytes).saturating_mul(100));
+            let len = *data.get(pos + 8)? as usize;
+            let title = data.get(pos + 9 .. pos + 9 + len)?;
+            pos += 9 + len;
+
+            chapters.push(Self {
+                start,
+                duration: Duration::ZERO,
+                title: String::from_utf8_lossy(title).into_owned(),
+            });
+        }
+
+        // Set durations from start of following chapter
+        let starts: Vec<Duration> = chapters.iter()
+            .skip(1)
+            .map(|c| c.start)
+            .chain(std::iter::once(end))
+            .collect();
+        for (chapter, next) in chapters.iter_mut().zip(starts) {
+            chapter.duration = (next - chapter.start).max(Duration::ZERO);
+        }
+
+        Some(chapters)
+    }
+
+    /// End time, relative to the start of the MP4.
+    pub fn end(&self) -> Duration {
+        self.start + self.duration
+    }
+}
+
+/// Decodes UTF-16 string with byte order `to_u16`.
+fn utf16(data: &[u8], to_u16: fn([u8; 2]) -> u16) -> String {
+    let units: Vec<u16> = data.chunks_exact(2)
+        .map(|b| to_u16([b[0], b[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Text sample with 16-bit size.
+    fn text_sample(text: &[u8]) -> Vec<u8> {
+        let mut data = (text.len() as u16).to_be_bytes().to_vec();
+        data.extend_from_slice(text);
+        data
+    }
+
+    #[test]
+    fn text_sample_utf8() {
+        let mut data = text_sample("Kapitel ett".as_bytes());
+        // Trailing 'encd' atom
+        data.extend_from_slice(&[0, 0, 0, 12, b'e', b'n', b'c', b'd', 0, 0, 1, 0]);
+        let chapter = Chapter::from_text_sample(&data, Duration::seconds(2), Duration::seconds(3));
+        assert_eq!(chapter.title, "Kapitel ett");
+        assert_eq!(chapter.start, Duration::seconds(2));
+        assert_eq!(chapter.end(), Duration::seconds(5));
+    }
+
+    #[test]
+    fn text_sample_utf16() {
+        let be = text_sample(&[0xfe, 0xff, 0, b'I', 0, b'n', 0x00, 0xe5]);
+        assert_eq!(Chapter::from_text_sample(&be, Duration::ZERO, Duration::ZERO).title, "Inå");
+        let le = text_sample(&[0xff, 0xfe, b'I', 0, b'n', 0, 0xe5, 0x00]);
+        assert_eq!(Chapter::from_text_sample(&le, Duration::ZERO, Duration::ZERO).title, "Inå");
+    }
+
+    #[test]
+    fn text_sample_truncated() {
+        let chapter = Chapter::from_text_sample(&[0], Duration::ZERO, Duration::ZERO);
+        assert_eq!(chapter.title, "");
+        // Size exceeds sample
+        let data = text_sample("Kapitel tre".as_bytes());
+        let chapter = Chapter::from_text_sample(&data[..data.len() - 4], Duration::ZERO, Duration::ZERO);
+        assert_eq!(chapter.title, "Kapitel");
+        let chapter = Chapter::from_text_sample(&[0, 9], Duration::ZERO, Duration::ZERO);
+        assert_eq!(chapter.title, "");
+    }
+
+    /// `chpl` data load with chapters as `(START_SECONDS, TITLE)`.
+    fn chpl(version: u8, chapters: &[(i64, &str)]) -> Vec<u8> {
+        let mut data = vec![version, 0, 0, 0];
+        if version != 0 {
+            data.extend_from_slice(&[0; 4]);
+        }
+        data.push(chapters.len() as u8);
+        for (start, title) in chapters {
+            data.extend_from_slice(&(start * 10_000_000).to_be_bytes());
+            data.push(title.len() as u8);
+            data.extend_from_slice(title.as_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn chpl_durations() {
+        for version in [0, 1] {
+            let data = chpl(version, &[(0, "Intro"), (10, "Middle"), (25, "End")]);
+            let chapters = Chapter::from_chpl(&data, Duration::seconds(30)).unwrap();
+            let expected: Vec<(Duration, Duration, &str)> = vec![
+                (Duration::ZERO, Duration::seconds(10), "Intro"),
+                (Duration::seconds(10), Duration::seconds(15), "Middle"),
+                (Duration::seconds(25), Duration::seconds(5), "End"),
+            ];
+            assert_eq!(
+                chapters.iter().map(|c| (c.start, c.duration, c.title.as_str())).collect::<Vec<_>>(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn chpl_start_after_end() {
+        let data = chpl(0, &[(0, "A"), (40, "B")]);
+        let chapters = Chapter::from_chpl(&data, Duration::seconds(30)).unwrap();
+        assert_eq!(chapters[1].duration, Duration::ZERO);
+    }
+
+    #[test]
+    fn chpl_truncated() {
+        let data = chpl(0, &[(0, "Intro"), (10, "Middle")]);
+        assert!(Chapter::from_chpl(&data[..data.len() - 2], Duration::seconds(30)).is_none());
+        assert!(Chapter::from_chpl(&[], Duration::seconds(30)).is_none());
+    }
+}
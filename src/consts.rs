@@ -5,6 +5,7 @@ use time::{self, PrimitiveDateTime, Month};
 /// within its specified, total size.
 ///
 /// Only container atoms in the main MP4 tree are listed.
+/// Other containers can be added via `AtomRegistry`.
 ///
 /// - `moov`: offset tables, timing, metadata, telemetry
 /// - `trak`: moov.trak (multiple)
@@ -45,4 +46,13 @@ pub const CONTAINER: [&'static str; 9] = [
 pub fn mp4_time_zero() -> PrimitiveDateTime {
     time::Date::from_calendar_date(1904, Month::January, 1).unwrap()
         .with_hms_milli(0, 0, 0, 0).unwrap()
+}
+
+/// Datetime for unscaled MP4 time, i.e. seconds since
+/// MP4 time zero (see `mp4_time_zero()`).
+/// Returns `None` if the result is outside the supported
+/// datetime range, e.g. for a corrupt version 1 (64-bit) value.
+pub fn mp4_datetime(seconds: u64) -> Option<PrimitiveDateTime> {
+    let seconds = i64::try_from(seconds).ok()?;
+    mp4_time_zero().checked_add(time::Duration::seconds(seconds))
 }
\ No newline at end of file
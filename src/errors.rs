@@ -37,7 +37,8 @@ pub enum Mp4Error {
     NoSuchAtom(String),
     /// Track with specified name/ID not found.
     NoSuchTrack(String),
-    /// Zero size atom.
+    /// Zero size atom that is not a top-level atom
+    /// (i.e. can not extend to end of file).
     ZeroSizeAtom{name: String, offset: u64},
     /// Atom ouf of bounds.
     /// `(GOT_POS, MIN_POS, MAX_POS)`
@@ -61,6 +62,19 @@ pub enum Mp4Error {
     ZeroSizeData,
     TryFromInt(TryFromIntError),
     EndOfFile,
+    /// No files in `Mp4Sequence`.
+    EmptySequence,
+    /// Tracks differ between chapters in `Mp4Sequence`.
+    SequenceMismatch,
+    /// Invalid atom path query, e.g. for `Mp4::select()`.
+    InvalidPath(String),
+    /// No parser registered for atom in `AtomRegistry`.
+    NoSuchParser(String),
+    /// Registered parser for atom returned another type than requested.
+    ParsedTypeMismatch(String),
+    /// Time in seconds since MP4 time zero (1904-01-01)
+    /// outside supported datetime range.
+    InvalidDateTime(u64),
 }
 
 impl std::error::Error for Mp4Error {}
@@ -98,6 +112,12 @@ impl fmt::Display for Mp4Error {
             Self::ZeroSizeData => write!(f, "No data to read"),
             Self::TryFromInt(err) => write!(f, "{err}"),
             Self::EndOfFile => write!(f, "Reached end of file"),
+            Self::EmptySequence => write!(f, "No files in MP4 sequence."),
+            Self::SequenceMismatch => write!(f, "Tracks differ between chapters in MP4 sequence."),
+            Self::InvalidPath(path) => write!(f, "Invalid atom path '{path}'."),
+            Self::NoSuchParser(name) => write!(f, "No parser registered for atom '{name}'."),
+            Self::ParsedTypeMismatch(name) => write!(f, "Parser registered for atom '{name}' returned another type than requested."),
+            Self::InvalidDateTime(seconds) => write!(f, "Time {seconds} seconds since 1904-01-01 is outside supported datetime range."),
         }
     }
 }
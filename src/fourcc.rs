@@ -10,6 +10,8 @@ use std::fmt::Display;
 pub enum FourCC {
     /// Composition offset atom
     Ctts,
+    /// Composition shift least greatest atom
+    Cslg,
     /// Data Information Atoms
     Dinf,
     Dref,
@@ -63,6 +65,8 @@ impl FourCC {
         assert_eq!(fourcc.len(), 4, "FourCC must have size 4.");
         match fourcc {
             // Atoms
+            b"ctts" => Self::Ctts,
+            b"cslg" => Self::Cslg,
             b"dinf" => Self::Dinf,
             b"dref" => Self::Dref,
             b"edts" => Self::Edts,
@@ -114,6 +118,7 @@ impl FourCC {
     pub fn from_str(fourcc: &str) -> Self {
         match fourcc {
             "ctts" => Self::Ctts,
+            "cslg" => Self::Cslg,
             "dinf" => Self::Dinf,
             "dref" => Self::Dref,
             "edts" => Self::Edts,
@@ -151,6 +156,7 @@ impl FourCC {
     pub fn to_str(&self) -> &str {
         match self {
             Self::Ctts => "ctts",
+            Self::Cslg => "cslg",
             Self::Dinf => "dinf",
             Self::Dref => "dref",
             Self::Edts => "edts",
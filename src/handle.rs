@@ -0,0 +1,323 @@
+//! Thread-safe, cloneable MP4 handle for reading samples in parallel.
+//!
+//! `Track` mutably borrows the `Mp4` reader, so only one track can be read
+//! at a time. `Mp4Handle` instead reads the `moov` atom once, then reads
+//! sample data via positional reads (`pread` on Unix, see `ByteSource`),
+//! rather than via a shared seek position.
+//! `Mp4Handle` and `TrackHandle` are `Send + Sync` (if the byte source is),
+//! and cheap to clone, so that several tracks can be read at the same time,
+//! e.g. using `rayon`.
+//!
+//! ```rs
+//! use mp4iter::Mp4Handle;
+//! use rayon::prelude::*;
+//! use std::path::Path;
+//!
+//! fn main() -> Result<(), mp4iter::Mp4Error> {
+//!     let mp4 = Mp4Handle::open(Path::new("VIDEO.MP4"))?;
+//!
+//!     // Read all tracks in parallel
+//!     let sizes = mp4.tracks()
+//!         .par_iter()
+//!         .map(|track| track.samples().map(|s| s.map(|s| s.len())).sum::<Result<usize, _>>())
+//!         .collect::<Result<Vec<_>, _>>()?;
+//!
+//!     // Read ranges of a single track in parallel
+//!     let track = mp4.track("GoPro MET")?;
+//!     let ranges = [0 .. track.len() / 2, track.len() / 2 .. track.len()];
+//!     let samples = ranges.into_par_iter()
+//!         .flat_map_iter(|range| track.samples_range(range))
+//!         .collect::<Result<Vec<_>, _>>()?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use std::{ops::Range, path::{Path, PathBuf}, sync::Arc};
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use time::Duration;
+
+use crate::{
+    recovery::{Recovered, Recovery},
+    source::{FileSource, SourceReader},
+    track::{Chunk, ParsableTrackId, TrackAttributes, TrackIdentifier},
+    ByteSource,
+    Mp4,
+    Mp4Error,
+    Sample,
+    SampleOffset,
+};
+
+/// Thread-safe, cloneable MP4 handle.
+///
+/// Cloning only clones reference counted pointers
+/// to the byte source and the parsed track attributes.
+#[derive(Debug)]
+pub struct Mp4Handle<S = FileSource> {
+    /// Path to MP4, if created from a file.
+    path: Option<PathBuf>,
+    source: Arc<S>,
+    /// Attributes, including sample offsets,
+    /// for all tracks.
+    tracks: Arc<Vec<TrackAttributes>>,
+}
+
+impl <S> Clone for Mp4Handle<S> {
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            source: Arc::clone(&self.source),
+            tracks: Arc::clone(&self.tracks),
+        }
+    }
+}
+
+impl Mp4Handle {
+    /// Opens MP4 file and reads attributes for all tracks.
+    /// Samples are read using positional reads.
+    pub fn open(path: &Path) -> Result<Self, Mp4Error> {
+        let handle = Self::from_source(FileSource::open(path)?)?;
+        Ok(Self {
+            path: Some(path.to_owned()),
+            ..handle
+        })
+    }
+
+    /// Recovery mode for an MP4 file with a missing or truncated `moov` atom,
+    /// e.g. a recording interrupted by power loss.
+    /// Sample offsets are rebuilt by scanning `mdat`, using the `reference`
+    /// MP4 file from the same camera to recognise samples.
+    /// See `recovery` module for limitations.
+    pub fn recover(path: &Path, reference: &Path) -> Result<Self, Mp4Error> {
+        let source = FileSource::open(path)?;
+        let recovered = Recovery::from_path(reference)?.scan(&source)?;
+        Ok(Self {
+            path: Some(path.to_owned()),
+            ..Self::from_recovered(source, recovered)
+        })
+    }
+}
+
+impl <S: ByteSource> Mp4Handle<S> {
+    /// New `Mp4Handle` from a range-read byte source.
+    /// Reads the `moov` atom and attributes for all tracks.
+    pub fn from_source(source: S) -> Result<Self, Mp4Error> {
+        let source = Arc::new(source);
+        let tracks = {
+            let mut mp4 = Mp4::from_source(source.as_ref())?;
+            TrackAttributes::all(&mut mp4, true)?
+        };
+
+        Ok(Self {
+            path: None,
+            source,
+            tracks: Arc::new(tracks),
+        })
+    }
+
+    /// New `Mp4Handle` from tracks recovered from a byte source
+    /// with missing `moov` atom (see `Recovery::scan()`).
+    pub fn from_recovered(source: S, recovered: Recovered) -> Self {
+        Self {
+            path: None,
+            source: Arc::new(source),
+            tracks: Arc::new(recovered.tracks),
+        }
+    }
+
+    /// Returns a new `Mp4` over the same byte source,
+    /// e.g. for reading individual atoms.
+    /// Note that this reads the `moov` atom again.
+    pub fn mp4(&self) -> Result<Mp4<SourceReader<Arc<S>>>, Mp4Error> {
+        Mp4::from_source(Arc::clone(&self.source))
+    }
+
+    /// Returns the underlying byte source.
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Returns path to MP4 if created from a file.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Size of MP4 in bytes.
+    pub fn len(&self) -> u64 {
+        self.source.len()
+    }
+
+    /// Returns `true` if the MP4 is empty.
+    pub fn is_empty(&self) -> bool {
+        self.source.is_empty()
+    }
+
+    /// Returns attributes for all tracks.
+    pub fn track_list(&self) -> &[TrackAttributes] {
+        &self.tracks
+    }
+
+    /// Returns first track matching `identifier`,
+    /// i.e. track ID, track name, or sub type.
+    pub fn track(&self, identifier: impl ParsableTrackId) -> Result<TrackHandle<S>, Mp4Error> {
+        let identifier = identifier.to_trackid();
+        self.tracks.iter()
+            .position(|attr| identifier == TrackIdentifier::Id(attr.id)
+                || identifier == TrackIdentifier::Name(&attr.name)
+                || identifier == TrackIdentifier::SubType(&attr.sub_type))
+            .map(|index| TrackHandle {
+                mp4: self.clone(),
+                index,
+            })
+            .ok_or_else(|| Mp4Error::NoSuchTrack(identifier.to_string()))
+    }
+
+    /// Returns all tracks.
+    pub fn tracks(&self) -> Vec<TrackHandle<S>> {
+        (0 .. self.tracks.len())
+            .map(|index| TrackHandle {
+                mp4: self.clone(),
+                index,
+            })
+            .collect()
+    }
+
+    /// Duration of the longest track.
+    pub fn duration(&self) -> Duration {
+        self.tracks.iter()
+            .map(|attr| attr.duration())
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+/// Thread-safe, cloneable MP4 track.
+/// Any number of tracks, or clones of the same track,
+/// can be read at the same time.
+#[derive(Debug)]
+pub struct TrackHandle<S = FileSource> {
+    mp4: Mp4Handle<S>,
+    /// Index into `Mp4Handle` tracks.
+    index: usize,
+}
+
+impl <S> Clone for TrackHandle<S> {
+    fn clone(&self) -> Self {
+        Self {
+            mp4: self.mp4.clone(),
+            index: self.index,
+        }
+    }
+}
+
+impl <S: ByteSource> TrackHandle<S> {
+    /// Returns track attributes.
+    pub fn attributes(&self) -> &TrackAttributes {
+        &self.mp4.tracks[self.index]
+    }
+
+    /// Number of samples in track.
+    pub fn len(&self) -> usize {
+        self.offsets().len()
+    }
+
+    /// Returns `true` if the track contains no samples.
+    pub fn is_empty(&self) -> bool {
+        self.offsets().is_empty()
+    }
+
+    pub fn name(&self) -> &str {
+        self.attributes().name()
+    }
+
+    pub fn id(&self) -> u32 {
+        self.attributes().id()
+    }
+
+    pub fn sub_type(&self) -> &str {
+        self.attributes().sub_type()
+    }
+
+    pub fn offsets(&self) -> &[SampleOffset] {
+        self.attributes().offsets()
+    }
+
+    /// Duration for track.
+    pub fn duration(&self) -> Duration {
+        self.attributes().duration()
+    }
+
+    /// Returns sample at `index` (0-based).
+    pub fn sample(&self, index: usize) -> Result<Sample, Mp4Error> {
+        self.samples_range(index .. index + 1)
+            .next()
+            .unwrap_or(Err(Mp4Error::SampleOffsetError))
+    }
+
+    /// Returns an iterator over the track's samples.
+    ///
+    /// Samples are read one chunk at a time (see `stco`/`co64`),
+    /// using a single positional read per chunk.
+    pub fn samples(&self) -> impl Iterator<Item = Result<Sample, Mp4Error>> + '_ {
+        self.samples_range(0 .. self.len())
+    }
+
+    /// Returns a parallel iterator over the track's samples.
+    ///
+    /// Samples are batched by chunk (see `stco`/`co64`),
+    /// with each chunk read via a single positional read,
+    /// in parallel via `rayon`.
+    /// Collecting into a `Vec` preserves sample order.
+    pub fn par_samples(&self) -> impl ParallelIterator<Item = Result<Sample, Mp4Error>> + '_
+    where
+        S: Send + Sync
+    {
+        let offsets = self.offsets();
+
+        Chunk::ranges(offsets)
+            .into_par_iter()
+            .flat_map_iter(move |(range, t)| {
+                match Chunk::read_at(self.mp4.source.as_ref(), &offsets[range.to_owned()]) {
+                    Ok(chunk) => chunk.samples(&offsets[range], t),
+                    Err(err) => vec![Err(err)],
+                }
+            })
+    }
+
+    /// Returns an iterator over samples in `range` (sample indices, 0-based),
+    /// with relative timestamps counted from the start of the track.
+    /// Out of bounds indices are ignored.
+    ///
+    /// Non-overlapping ranges can be read at the same time,
+    /// e.g. via `rayon`.
+    pub fn samples_range(&self, range: Range<usize>) -> impl Iterator<Item = Result<Sample, Mp4Error>> + '_ {
+        let offsets = self.offsets();
+        let end = range.end.min(offsets.len());
+        let start = range.start.min(end);
+
+        // Relative timestamp for first sample in range
+        let mut t: Duration = offsets[.. start].iter()
+            .map(|o| o.duration)
+            .sum();
+        // Current chunk
+        let mut chunk: Option<Chunk> = None;
+
+        offsets[start .. end].iter()
+            .enumerate()
+            .map(move |(i, offset)| {
+                let rel_t = t;
+                t += offset.duration;
+
+                if chunk.as_ref().map(|c| c.index) != Some(offset.chunk) {
+                    // Only read the part of the chunk within range
+                    chunk = Some(Chunk::read_at(self.mp4.source.as_ref(), &offsets[start + i .. end])?);
+                }
+
+                chunk.as_ref()
+                    .ok_or(Mp4Error::SampleOffsetError)?
+                    .sample(offset)
+                    .map(|s| s.with_time(rel_t))
+            })
+    }
+}
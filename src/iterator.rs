@@ -1,3 +1,327 @@
-pub struct Mp4Iterator {
-    
-}
\ No newline at end of file
+//! Depth-aware, fallible walk over all atoms in an MP4.
+//!
+//! Unlike iterating over `Mp4` directly, which only yields
+//! top-level atom headers, `Mp4Iterator` descends into container atoms and yields
+//! nesting depth and FourCC path for each atom.
+//! Errors, e.g. for a corrupt atom size, are returned
+//! and end the walk.
+//!
+//! ```rs
+//! use mp4iter::Mp4;
+//! use std::path::Path;
+//!
+//! fn main() -> Result<(), mp4iter::Mp4Error> {
+//!     let mut mp4 = Mp4::new(Path::new("VIDEO.MP4"))?;
+//!     let mut walker = mp4.walk();
+//!     while let Some(result) = walker.next() {
+//!         let (depth, path, header) = result?;
+//!         println!("{}{path} @{}", "  ".repeat(depth), header.offset());
+//!         // Do not descend into 'udta'
+//!         if header.name().to_str() == "udta" {
+//!             walker.skip_children();
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::{Atom, AtomHeader, Mp4Error, Mp4Reader, TargetReader};
+
+/// Atom level, i.e. the child atoms
+/// of a container or the top-level atoms.
+#[derive(Debug, Clone)]
+struct Level {
+    /// Reader to read child atoms with.
+    target: TargetReader,
+    /// Offset for converting target reader positions
+    /// to absolute positions.
+    base: u64,
+    /// Position for next child atom header,
+    /// in target reader positions.
+    pos: u64,
+    /// End of container, in target reader positions.
+    end: u64,
+    /// Container FourCC. Empty for top-level.
+    name: String,
+}
+
+/// Depth-aware, fallible walk over all atoms in an MP4, in file order.
+/// Created via `Mp4::walk()`.
+///
+/// Yields `(DEPTH, PATH, HEADER)`, where depth is 0 for top-level atoms,
+/// path is the FourCC path, e.g. `moov/trak/tkhd`, and header offsets
+/// are absolute.
+///
+/// Container atoms (see `CONTAINER` and `AtomRegistry`) are descended into by default.
+/// Call `Mp4Iterator::skip_children()` directly after a container is yielded
+/// to skip its child atoms, or `Mp4Iterator::descend()` to read
+/// the data load of any other atom as child atoms.
+///
+/// Atoms within `moov` are read from the in-memory `moov` reader.
+#[derive(Debug)]
+pub struct Mp4Iterator<'a, R: Read + Seek> {
+    reader: &'a mut Mp4Reader<R>,
+    /// Open levels, top-level first.
+    levels: Vec<Level>,
+    /// Child level for the most recently yielded atom.
+    last: Option<Level>,
+    /// Whether to descend into the most recently yielded atom.
+    descend: bool,
+    /// Set on error.
+    done: bool,
+}
+
+impl <'a, R: Read + Seek> Mp4Iterator<'a, R> {
+    pub(crate) fn new(reader: &'a mut Mp4Reader<R>) -> Self {
+        let len = reader.len;
+        Self {
+            reader,
+            levels: vec![Level {
+                target: TargetReader::File,
+                base: 0,
+                pos: 0,
+                end: len,
+                name: String::new(),
+            }],
+            last: None,
+            descend: false,
+            done: false,
+        }
+    }
+
+    /// Walk over top-level atoms only, starting at
+    /// file position `pos`.
+    pub(crate) fn top_level(reader: &'a mut Mp4Reader<R>, pos: u64) -> Self {
+        let mut walker = Self::new(reader);
+        walker.levels[0].pos = pos;
+        walker
+    }
+
+    /// Skip the child atoms of the most recently
+    /// yielded atom.
+    pub fn skip_children(&mut self) {
+        self.descend = false;
+    }
+
+    /// Descend into the most recently yielded atom,
+    /// reading its data load as child atoms, even if
+    /// it is not a container.
+    ///
+    /// Note that some atoms contain other fields
+    /// before their child atoms (e.g. ISO `meta`
+    /// has version and flags). These will be read as
+    /// an atom header and likely result in an error.
+    pub fn descend(&mut self) {
+        self.descend = self.last.is_some();
+    }
+
+    /// Current nesting depth, i.e. the depth for the next atom,
+    /// unless the end of the current container is reached.
+    pub fn depth(&self) -> usize {
+        (self.levels.len() + self.descend as usize).saturating_sub(1)
+    }
+
+    /// Returns atom for a header yielded by the walk,
+    /// positioned at its data load, e.g. to parse
+    /// a leaf atom during the walk.
+    /// Does not affect walk position.
+    pub fn atom(&mut self, header: &AtomHeader) -> Result<Atom<'_, R>, Mp4Error> {
+        self.reader.atom_abs(header)
+    }
+
+    /// Child level for `header` (in target reader positions).
+    fn child_level(&self, level: &Level, header: &AtomHeader) -> Level {
+        let name = match level.name.is_empty() {
+            true => header.name.to_str().to_owned(),
+            false => format!("{}/{}", level.name, header.name),
+        };
+        // moov is read from the in-memory moov reader
+        if matches!(level.target, TargetReader::File)
+            && header.name.to_str() == "moov"
+            && !self.reader.moov_reader.is_empty()
+            && header.offset == self.reader.moov_header.offset
+        {
+            return Level {
+                target: TargetReader::Moov,
+                base: self.reader.moov_header.data_offset(),
+                pos: 0,
+                end: self.reader.moov_reader.len(),
+                name,
+            }
+        }
+        // Containers may have other fields preceding child atoms (e.g. 'meta')
+        let skip = match header.container {
+            true => header.next,
+            false => 0,
+        };
+        Level {
+            target: level.target,
+            base: level.base,
+            pos: header.data_offset() + skip,
+            end: header.end(),
+            name,
+        }
+    }
+
+    fn next_header(&mut self) -> Option<Result<(usize, String, AtomHeader), Mp4Error>> {
+        if let (Some(child), true) = (self.last.take(), self.descend) {
+            self.levels.push(child);
+        }
+        self.descend = false;
+
+        // Ignore trailing bytes too few for an atom header, e.g. padding
+        while self.levels.last().is_some_and(|l| l.pos + 8 > l.end) {
+            self.levels.pop();
+        }
+        let level = self.levels.last()?.to_owned();
+
+        let mut header = match self.reader.header(&level.target, Some(SeekFrom::Start(level.pos))) {
+            Ok(hdr) => hdr,
+            // Use absolute offset
+            Err(Mp4Error::ZeroSizeAtom { name, .. }) => return Some(Err(Mp4Error::ZeroSizeAtom {
+                name,
+                offset: level.pos + level.base,
+            })),
+            Err(Mp4Error::UnexpectedAtomSize { len, .. }) => return Some(Err(Mp4Error::UnexpectedAtomSize {
+                len,
+                offset: level.pos + level.base,
+            })),
+            Err(err) => return Some(Err(err)),
+        };
+        if level.pos + header.atom_size > level.end {
+            return Some(Err(Mp4Error::UnexpectedAtomSize {
+                len: header.atom_size,
+                offset: level.pos + level.base,
+            }))
+        }
+
+        self.descend = header.is_container();
+        self.last = Some(self.child_level(&level, &header));
+        if let Some(l) = self.levels.last_mut() {
+            l.pos += header.atom_size;
+        }
+
+        let depth = self.levels.len() - 1;
+        let path = self.last.as_ref()
+            .map(|l| l.name.to_owned())
+            .unwrap_or_default();
+        header.offset += level.base;
+
+        Some(Ok((depth, path, header)))
+    }
+}
+
+impl <'a, R: Read + Seek> Iterator for Mp4Iterator<'a, R> {
+    type Item = Result<(usize, String, AtomHeader), Mp4Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None
+        }
+        let result = self.next_header();
+        if !matches!(result, Some(Ok(_))) {
+            self.done = true;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{testing::{atom, container, mp4, TestTrack}, Mp4};
+
+    use super::*;
+
+    fn tracks() -> Vec<TestTrack> {
+        vec![
+            TestTrack::new(1, "One", vec![vec![vec![1; 4]]]),
+            TestTrack::new(2, "Two", vec![vec![vec![2; 4]]]),
+        ]
+    }
+
+    /// Walks all atoms, returning depth and path.
+    fn walk(walker: Mp4Iterator<'_, impl Read + Seek>) -> Vec<(usize, String)> {
+        walker.map(|r| r.map(|(depth, path, _)| (depth, path)))
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn depth_and_path() {
+        let bytes = mp4(&tracks()[..1]);
+        let mut mp4 = Mp4::from_slice(&bytes).unwrap();
+
+        let expected: Vec<(usize, String)> = [
+            (0, "ftyp"),
+            (0, "mdat"),
+            (0, "moov"),
+            (1, "moov/mvhd"),
+            (1, "moov/trak"),
+            (2, "moov/trak/tkhd"),
+            (2, "moov/trak/mdia"),
+            (3, "moov/trak/mdia/mdhd"),
+            (3, "moov/trak/mdia/hdlr"),
+            (3, "moov/trak/mdia/minf"),
+            (4, "moov/trak/mdia/minf/stbl"),
+            (5, "moov/trak/mdia/minf/stbl/stsd"),
+            (5, "moov/trak/mdia/minf/stbl/stts"),
+            (5, "moov/trak/mdia/minf/stbl/stsc"),
+            (5, "moov/trak/mdia/minf/stbl/stsz"),
+            (5, "moov/trak/mdia/minf/stbl/stco"),
+        ].into_iter().map(|(d, p)| (d, p.to_owned())).collect();
+        assert_eq!(walk(mp4.walk()), expected);
+
+        // Absolute offsets, also for atoms read via the moov reader
+        for result in mp4.walk() {
+            let (_, _, header) = result.unwrap();
+            let offset = header.offset as usize;
+            assert_eq!(&bytes[offset + 4 .. offset + 8], header.name.to_str().as_bytes());
+        }
+    }
+
+    #[test]
+    fn skip_children() {
+        let bytes = mp4(&tracks());
+        let mut mp4 = Mp4::from_slice(&bytes).unwrap();
+
+        let mut walker = mp4.walk();
+        let mut paths: Vec<String> = Vec::new();
+        while let Some(result) = walker.next() {
+            let (depth, path, header) = result.unwrap();
+            // Skip first track only
+            if header.name.to_str() == "trak" && !paths.contains(&path) {
+                walker.skip_children();
+                assert_eq!(walker.depth(), depth);
+            }
+            paths.push(path);
+        }
+
+        assert_eq!(paths.iter().filter(|p| p.as_str() == "moov/trak").count(), 2);
+        assert_eq!(paths.iter().filter(|p| p.as_str() == "moov/trak/tkhd").count(), 1);
+        assert_eq!(paths.iter().filter(|p| p.ends_with("/stco")).count(), 1);
+    }
+
+    #[test]
+    fn invalid_child_size() {
+        for size in [4_u32, 0xFFFF] {
+            let mut child = atom(b"abcd", &[0; 4]);
+            child[..4].copy_from_slice(&size.to_be_bytes());
+            let mut tracks = tracks();
+            tracks[0].atoms.push(container(b"udta", &[child]));
+            let bytes = mp4(&tracks);
+            let mut mp4 = Mp4::from_slice(&bytes).unwrap();
+
+            let udta = bytes.windows(4).position(|w| w == b"udta").unwrap() - 4;
+            let mut walker = mp4.walk();
+            let err = walker.find_map(|r| r.err()).unwrap();
+            assert!(
+                matches!(err, Mp4Error::UnexpectedAtomSize { len, offset } if len == size as u64 && offset == udta as u64 + 8),
+                "{err:?}"
+            );
+            // Ends walk
+            assert!(walker.next().is_none());
+        }
+    }
+}
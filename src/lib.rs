@@ -10,10 +10,10 @@
 //! use std::path::Path;
 //!
 //! fn main() -> std::io::Result<()> {
-//!     let mp4 = Mp4::new(Path::new("VIDEO.MP4"))?;
+//!     let mut mp4 = Mp4::new(Path::new("VIDEO.MP4"))?;
 //!
-//!     for atom_header in mp4.into_iter() {
-//!         println!("{atom_header:?}")
+//!     for result in mp4.by_ref() {
+//!         println!("{:?}", result?)
 //!     }
 //!
 //!     // Derives duration for MP4 for longest track.
@@ -22,6 +22,10 @@
 //!     // Extracts offsets for GoPro GPMF telemetry (handlre name 'GoPro MET')
 //!     println!("{:#?}", mp4.offsets("GoPro MET"));
 //!
+//!     // MP4 data already in memory works the same way
+//!     let bytes = std::fs::read("VIDEO.MP4")?;
+//!     let mp4 = Mp4::from_reader(std::io::Cursor::new(bytes))?;
+//!
 //!     Ok(())
 //! }
 //! ```
@@ -34,24 +38,55 @@ pub mod consts;
 pub mod support;
 pub mod track;
 pub mod errors;
+pub mod source;
+pub mod moov;
+pub mod handle;
+pub mod recovery;
+pub mod sequence;
+pub mod tree;
+pub mod visitor;
+pub mod registry;
+pub mod tags;
+pub mod location;
+pub mod chapters;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+#[cfg(feature = "async")]
+pub mod async_mp4;
+#[cfg(test)]
+pub(crate) mod testing;
 
 // Internal reader
 pub(crate) mod reader;
 pub(crate) use reader::{Mp4Reader, TargetReader};
 pub use reader::ReadOption;
+pub use moov::{MoovBuffer, MoovReader};
 
 pub mod iterator;
 
 pub use mp4::Mp4;
+pub use handle::{Mp4Handle, TrackHandle};
+pub use recovery::{Recovery, Recovered};
+pub use sequence::{ChapterReader, Mp4Sequence};
+pub use tree::{AtomTree, AtomNode, AtomPath};
+pub use iterator::Mp4Iterator;
+pub use visitor::{AtomVisitor, Visit};
+pub use registry::{AtomRegistry, ParsedAtom};
+pub use tags::{Tag, Tags};
+pub use location::{Location, LocationSource};
+pub use chapters::Chapter;
 pub use fourcc::FourCC;
-pub use track::{Track, TrackAttributes, TrackIdentifier, Sample, SampleOffsets, SampleOffset};
-pub use atom::{Atom, AtomHeader};
+pub use track::{Track, TrackAttributes, TrackIdentifier, Sample, SampleRef, SampleOffsets, SampleOffset, Edit};
+pub use atom::{Atom, AtomHeader, UserType};
 pub use atom_types::{
     Co64,
+    Cslg,
+    Ctts,
     Dref,
     Elst,
     Ftyp,
     Sdtp,
+    SampleDependency,
     Smhd,
     Stts,
     Stss,
@@ -59,7 +94,11 @@ pub use atom_types::{
     Stco,
     Hdlr,
     Tkhd,
+    Tref,
     Mdhd,
+    Meta,
+    Metadata,
+    MetaValue,
     Mvhd,
     Stsd,
     Tmcd,
@@ -68,5 +107,10 @@ pub use atom_types::{
     VideoFormat, // stsd component
     SampleDescription, // stsd component
 };
-pub use consts::{CONTAINER, mp4_time_zero};
+pub use consts::{CONTAINER, mp4_time_zero, mp4_datetime};
 pub use errors::Mp4Error;
+pub use source::ByteSource;
+#[cfg(feature = "mmap")]
+pub use mmap::MappedFile;
+#[cfg(feature = "async")]
+pub use async_mp4::{AsyncMp4, AsyncTrack, AsyncSamples};
@@ -0,0 +1,234 @@
+//! Recording location from metadata.
+//!
+//! Cameras and phones without a telemetry track often store
+//! a single location for the clip, in one of:
+//! - `com.apple.quicktime.location.ISO6709` in QuickTime metadata (`moov/meta`)
+//! - `©xyz` in user data (`moov/udta`)
+//! - 3GPP location information (`moov/udta/loci`)
+//!
+//! The first two are ISO 6709 strings, e.g. `+59.3293+018.0686+012.000/`.
+//!
+//! ```rs
+//! use mp4iter::Mp4;
+//! use std::path::Path;
+//!
+//! fn main() -> Result<(), mp4iter::Mp4Error> {
+//!     let mut mp4 = Mp4::new(Path::new("VIDEO.MP4"))?;
+//!     if let Some(loc) = mp4.location()? {
+//!         println!("{} {} {:?} ({:?})", loc.latitude, loc.longitude, loc.altitude, loc.source);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+/// Atom the location was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocationSource {
+    /// `com.apple.quicktime.location.ISO6709` in `moov/meta`.
+    QuickTime,
+    /// `©xyz` in `moov/udta`.
+    Xyz,
+    /// 3GPP `loci` in `moov/udta`.
+    Loci,
+}
+
+/// Recording location.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    /// Latitude in decimal degrees.
+    pub latitude: f64,
+    /// Longitude in decimal degrees.
+    pub longitude: f64,
+    /// Altitude in meters, if specified.
+    pub altitude: Option<f64>,
+    /// Atom the location was read from.
+    pub source: LocationSource,
+}
+
+impl Location {
+    /// Parses ISO 6709 string, e.g. `+59.3293+018.0686+012.000/`.
+    ///
+    /// Latitude and longitude may be specified as
+    /// degrees (`±DD.D±DDD.D`), degrees and minutes (`±DDMM.M±DDDMM.M`),
+    /// or degrees, minutes, and seconds (`±DDMMSS.S±DDDMMSS.S`).
+    /// Altitude is optional. Any coordinate reference system
+    /// following altitude is ignored.
+    pub fn from_iso6709(value: &str, source: LocationSource) -> Option<Self> {
+        let value = value.trim()
+            .trim_end_matches('\0')
+            .trim_end_matches('/');
+
+        // Ignore coordinate reference system, e.g. 'CRSWGS_84'
+        let value = match value.find(|c: char| c.is_ascii_alphabetic()) {
+            Some(i) => &value[..i],
+            None => value,
+        };
+
+        // Split into signed components, e.g. ["+59.3293", "+018.0686", "+012.000"]
+        let mut components: Vec<&str> = Vec::new();
+        let mut start = 0;
+        for (i, c) in value.char_indices().skip(1) {
+            if c == '+' || c == '-' {
+                components.push(&value[start..i]);
+                start = i;
+            }
+        }
+        components.push(&value[start..]);
+
+        let latitude = iso6709_degrees(components.first()?, 2)?;
+        let longitude = iso6709_degrees(components.get(1)?, 3)?;
+        let altitude = components.get(2)
+            .and_then(|a| a.parse::<f64>().ok());
+
+        if latitude.abs() > 90. || longitude.abs() > 180. {
+            return None
+        }
+
+        Some(Self {
+            latitude,
+            longitude,
+            altitude,
+            source,
+        })
+    }
+
+    /// Parses 3GPP location information atom (`loci`) data load,
+    /// i.e. excluding the header.
+    ///
+    /// See 3GPP TS 26.244.
+    pub fn from_loci(data: &[u8]) -> Option<Self> {
+        // version, flags, pad + language
+        let mut pos = 6;
+        // Name, null terminated UTF-8,
+        // or UTF-16 with byte order mark
+        let name = data.get(pos..)?;
+        pos += match name.starts_with(&[0xfe, 0xff]) || name.starts_with(&[0xff, 0xfe]) {
+            true => name.chunks_exact(2).position(|c| c == [0, 0])? * 2 + 2,
+            false => name.iter().position(|b| *b == 0)? + 1,
+        };
+        // Role
+        pos += 1;
+
+        let fixed = |pos: usize| -> Option<f64> {
+            let bytes: [u8; 4] = data.get(pos .. pos + 4)?.try_into().ok()?;
+            Some(i32::from_be_bytes(bytes) as f64 / 65536.)
+        };
+
+        Some(Self {
+            longitude: fixed(pos)?,
+            latitude: fixed(pos + 4)?,
+            altitude: fixed(pos + 8),
+            source: LocationSource::Loci,
+        })
+    }
+}
+
+/// Parses signed ISO 6709 latitude (`int_len` = 2)
+/// or longitude (`int_len` = 3) as decimal degrees.
+fn iso6709_degrees(value: &str, int_len: usize) -> Option<f64> {
+    let sign = match value.chars().next()? {
+        '+' => 1.,
+        '-' => -1.,
+        _ => return None,
+    };
+    let value = &value[1..];
+    let (int, frac) = value.split_once('.').unwrap_or((value, ""));
+    let frac: f64 = format!("0.{frac}").parse().ok()?;
+
+    let degrees = match int.len().checked_sub(int_len)? {
+        // ±DD.D
+        0 => int.parse::<f64>().ok()? + frac,
+        // ±DDMM.M
+        2 => int[..int_len].parse::<f64>().ok()?
+            + (int[int_len..].parse::<f64>().ok()? + frac) / 60.,
+        // ±DDMMSS.S
+        4 => int[..int_len].parse::<f64>().ok()?
+            + int[int_len .. int_len + 2].parse::<f64>().ok()? / 60.
+            + (int[int_len + 2 ..].parse::<f64>().ok()? + frac) / 3600.,
+        _ => return None,
+    };
+
+    Some(sign * degrees)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn iso6709_decimal() {
+        let loc = Location::from_iso6709("+59.3293+018.0686+012.000/", LocationSource::QuickTime).unwrap();
+        assert!(approx(loc.latitude, 59.3293));
+        assert!(approx(loc.longitude, 18.0686));
+        assert_eq!(loc.altitude, Some(12.));
+        assert_eq!(loc.source, LocationSource::QuickTime);
+
+        // No altitude, negative values
+        let loc = Location::from_iso6709("-33.8688-070.6693/", LocationSource::Xyz).unwrap();
+        assert!(approx(loc.latitude, -33.8688));
+        assert!(approx(loc.longitude, -70.6693));
+        assert_eq!(loc.altitude, None);
+    }
+
+    #[test]
+    fn iso6709_degrees_minutes() {
+        let loc = Location::from_iso6709("+5919.758+01804.116/", LocationSource::Xyz).unwrap();
+        assert!(approx(loc.latitude, 59. + 19.758 / 60.));
+        assert!(approx(loc.longitude, 18. + 4.116 / 60.));
+    }
+
+    #[test]
+    fn iso6709_degrees_minutes_seconds() {
+        let loc = Location::from_iso6709("-591945.5+0180405.25-010.5CRSWGS_84/", LocationSource::Xyz).unwrap();
+        assert!(approx(loc.latitude, -(59. + 19. / 60. + 45.5 / 3600.)));
+        assert!(approx(loc.longitude, 18. + 4. / 60. + 5.25 / 3600.));
+        assert_eq!(loc.altitude, Some(-10.5));
+    }
+
+    #[test]
+    fn iso6709_invalid() {
+        assert!(Location::from_iso6709("", LocationSource::Xyz).is_none());
+        assert!(Location::from_iso6709("59.3293+018.0686/", LocationSource::Xyz).is_none());
+        assert!(Location::from_iso6709("+91.0000+018.0686/", LocationSource::Xyz).is_none());
+        assert!(Location::from_iso6709("+59.3293/", LocationSource::Xyz).is_none());
+    }
+
+    /// `loci` data load with `name`, longitude, latitude, altitude.
+    fn loci(name: &[u8], lon: f64, lat: f64, alt: f64) -> Vec<u8> {
+        let mut data = vec![0, 0, 0, 0, 0x15, 0xc7];
+        data.extend_from_slice(name);
+        data.push(0); // role
+        for value in [lon, lat, alt] {
+            data.extend_from_slice(&((value * 65536.) as i32).to_be_bytes());
+        }
+        data.extend_from_slice(b"earth\0\0");
+        data
+    }
+
+    #[test]
+    fn loci_utf8() {
+        let loc = Location::from_loci(&loci(b"Home\0", 18.0686, 59.3293, 12.5)).unwrap();
+        assert!((loc.longitude - 18.0686).abs() < 1e-4);
+        assert!((loc.latitude - 59.3293).abs() < 1e-4);
+        assert_eq!(loc.altitude, Some(12.5));
+        assert_eq!(loc.source, LocationSource::Loci);
+    }
+
+    #[test]
+    fn loci_utf16() {
+        let loc = Location::from_loci(&loci(&[0xfe, 0xff, 0, b'H', 0, 0], -70.5, -33.25, -1.)).unwrap();
+        assert_eq!(loc.longitude, -70.5);
+        assert_eq!(loc.latitude, -33.25);
+        assert_eq!(loc.altitude, Some(-1.));
+    }
+
+    #[test]
+    fn loci_truncated() {
+        let data = loci(b"Home\0", 18., 59., 0.);
+        assert!(Location::from_loci(&data[..10]).is_none());
+    }
+}
@@ -0,0 +1,66 @@
+//! Memory-mapped MP4 backend. Requires the `mmap` feature.
+//!
+//! Use `Mp4::open_mmap()` to read an MP4 via a memory-mapped file.
+//! The `moov` atom will then reference the mapping rather than being
+//! copied into memory, and `Track::sample_slices()` yields
+//! each sample as a borrowed slice of the mapped file.
+//!
+//! ```rs
+//! use mp4iter::Mp4;
+//! use std::path::Path;
+//!
+//! fn main() -> Result<(), mp4iter::Mp4Error> {
+//!     let mut mp4 = Mp4::open_mmap(Path::new("GOPRO_VIDEO.MP4"))?;
+//!     let track = mp4.track("GoPro MET")?;
+//!     for result in track.sample_slices() {
+//!         let sample = result?;
+//!         println!("{:?} {} bytes", sample.relative(), sample.len());
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::{fs::File, path::Path, sync::Arc};
+
+use memmap2::Mmap;
+
+use crate::Mp4Error;
+
+/// Read-only memory-mapped file.
+///
+/// Cheap to clone, since clones share the same mapping.
+#[derive(Debug, Clone)]
+pub struct MappedFile {
+    map: Arc<Mmap>,
+}
+
+impl AsRef<[u8]> for MappedFile {
+    fn as_ref(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+impl MappedFile {
+    /// Memory-maps the file at `path`.
+    ///
+    /// Note that the file must not be truncated or modified
+    /// by another process while mapped, since this is
+    /// undefined behaviour.
+    pub fn open(path: &Path) -> Result<Self, Mp4Error> {
+        let file = File::open(path)?;
+        // Safety: read-only mapping, see note above regarding
+        // external modifications of the file.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(Self { map: Arc::new(map) })
+    }
+
+    /// Size of mapped file in bytes.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the mapped file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
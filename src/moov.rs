@@ -0,0 +1,355 @@
+//! In-memory buffer and reader over the `moov` atom's data load.
+//!
+//! The buffer is either fully read into memory (default),
+//! a byte range within a memory-mapped file (`Mp4::open_mmap()`),
+//! or lazily loaded (`Mp4::open_lazy()`), where only atom headers
+//! are read when opening the MP4, and the data load for an individual atom,
+//! e.g. a sample table (`stsz`, `stco`...), is read the first time it is accessed.
+//!
+//! Note that positions are relative to the start of the `moov` data load.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, Read, Seek, SeekFrom},
+    ops::Range,
+};
+
+use crate::support::seek_pos;
+#[cfg(feature = "mmap")]
+use crate::mmap::MappedFile;
+
+/// Buffer over the `moov` atom's data load.
+#[derive(Debug, Clone)]
+pub enum MoovBuffer {
+    /// `moov` data load copied into memory.
+    Owned(Vec<u8>),
+    /// `moov` data load as a byte range
+    /// within a memory-mapped file.
+    #[cfg(feature = "mmap")]
+    Mapped(MappedFile, Range<usize>),
+    /// `moov` data load with atom headers in memory,
+    /// and atom data loads read on demand.
+    Lazy(LazyMoov),
+}
+
+impl Default for MoovBuffer {
+    fn default() -> Self {
+        Self::Owned(Vec::new())
+    }
+}
+
+impl MoovBuffer {
+    /// Size of the `moov` data load in bytes.
+    pub fn len(&self) -> u64 {
+        match self {
+            Self::Lazy(lazy) => lazy.len,
+            _ => self.as_slice().map(|s| s.len() as u64).unwrap_or_default(),
+        }
+    }
+
+    /// Returns `true` if the `moov` data load is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the full `moov` data load as a slice.
+    /// Returns `None` for a lazily loaded `moov` atom.
+    pub fn as_slice(&self) -> Option<&[u8]> {
+        match self {
+            Self::Owned(buf) => Some(buf),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(map, range) => Some(&map.as_ref()[range.to_owned()]),
+            Self::Lazy(_) => None,
+        }
+    }
+
+    /// Returns loaded bytes from `pos` and on.
+    /// Empty if `pos` is out of bounds or not yet loaded.
+    fn slice_from(&self, pos: u64) -> &[u8] {
+        match self {
+            Self::Lazy(lazy) => lazy.slice_from(pos),
+            _ => {
+                let buf = self.as_slice().unwrap_or_default();
+                usize::try_from(pos).ok()
+                    .and_then(|p| buf.get(p ..))
+                    .unwrap_or_default()
+            }
+        }
+    }
+}
+
+/// Lazily loaded `moov` data load.
+///
+/// Holds all atom headers, and the data load for
+/// atoms that have been accessed.
+#[derive(Debug, Clone, Default)]
+pub struct LazyMoov {
+    /// Size of the `moov` data load in bytes.
+    len: u64,
+    /// Loaded byte ranges, keyed by start position.
+    segments: BTreeMap<u64, Vec<u8>>,
+    /// Byte ranges for all non-container atoms (header included),
+    /// sorted by start position.
+    leaves: Vec<Range<u64>>,
+    /// Whether corresponding atom in `leaves` has been loaded.
+    loaded: Vec<bool>,
+}
+
+impl LazyMoov {
+    pub(crate) fn new(len: u64) -> Self {
+        Self {
+            len,
+            ..Self::default()
+        }
+    }
+
+    /// Adds header bytes for an atom at `pos`.
+    /// If the atom is not a container, `atom_size` is used
+    /// to set its byte range for reading its data load later.
+    pub(crate) fn add_header(&mut self, pos: u64, header: Vec<u8>, atom_size: Option<u64>) {
+        if let Some(size) = atom_size {
+            self.leaves.push(pos .. pos + size);
+            self.loaded.push(false);
+        }
+        self.segments.insert(pos, header);
+    }
+
+    /// Returns byte ranges for atoms that have not yet been loaded,
+    /// and that intersect `range`. If `range` is empty, the atom
+    /// that contains `range.start` is returned (if not yet loaded).
+    pub(crate) fn missing(&self, range: Range<u64>) -> Vec<(usize, Range<u64>)> {
+        // Index of first atom that ends after start of range
+        let first = self.leaves.partition_point(|leaf| leaf.end <= range.start);
+        let end = range.end.max(range.start + 1);
+        self.leaves[first ..].iter()
+            .enumerate()
+            .take_while(|(_, leaf)| leaf.start < end)
+            .map(|(i, leaf)| (first + i, leaf.to_owned()))
+            .filter(|(i, _)| !self.loaded[*i])
+            .collect()
+    }
+
+    /// Sets the full byte content (header included)
+    /// for the atom with index `leaf`
+    /// (as returned by `LazyMoov::missing()`).
+    pub(crate) fn load(&mut self, leaf: usize, bytes: Vec<u8>) {
+        self.segments.insert(self.leaves[leaf].start, bytes);
+        self.loaded[leaf] = true;
+    }
+
+    /// Size of the `moov` data load in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the `moov` data load is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if the byte at `pos` is in memory.
+    pub(crate) fn is_loaded(&self, pos: u64) -> bool {
+        !self.slice_from(pos).is_empty()
+    }
+
+    /// Number of bytes currently in memory.
+    pub fn loaded_len(&self) -> u64 {
+        self.segments.values()
+            .map(|s| s.len() as u64)
+            .sum()
+    }
+
+    fn slice_from(&self, pos: u64) -> &[u8] {
+        match self.segments.range(..= pos).next_back() {
+            Some((start, bytes)) => bytes.get((pos - start) as usize ..).unwrap_or_default(),
+            None => &[],
+        }
+    }
+}
+
+/// Reader over the `moov` atom's data load.
+#[derive(Debug, Clone, Default)]
+pub struct MoovReader {
+    buffer: MoovBuffer,
+    pos: u64,
+}
+
+impl MoovReader {
+    pub fn new(buffer: MoovBuffer) -> Self {
+        Self {
+            buffer,
+            pos: 0,
+        }
+    }
+
+    /// Returns the underlying buffer.
+    pub fn get_ref(&self) -> &MoovBuffer {
+        &self.buffer
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut MoovBuffer {
+        &mut self.buffer
+    }
+
+    /// Returns the underlying buffer.
+    pub fn into_inner(self) -> MoovBuffer {
+        self.buffer
+    }
+
+    /// Current position.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Size of the `moov` data load in bytes.
+    pub fn len(&self) -> u64 {
+        self.buffer.len()
+    }
+
+    /// Returns `true` if the `moov` data load is empty.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    fn not_loaded(&self) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("moov data at position {} has not been loaded", self.pos)
+        )
+    }
+}
+
+impl Read for MoovReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.fill_buf()?.read(buf)?;
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for MoovReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos >= self.len() {
+            return Ok(&[]);
+        }
+        let bytes = self.buffer.slice_from(self.pos);
+        if bytes.is_empty() {
+            return Err(self.not_loaded());
+        }
+        Ok(bytes)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt as u64;
+    }
+}
+
+impl Seek for MoovReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = seek_pos(self.pos, self.len(), pos)?;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{testing::{mp4, TestTrack}, Mp4};
+
+    use super::*;
+
+    /// `moov` data load with a container atom holding two atoms,
+    /// with only headers added.
+    fn lazy_moov() -> (Vec<u8>, LazyMoov) {
+        let bytes: Vec<u8> = [
+            &[0, 0, 0, 32][..], b"trak",
+            &[0, 0, 0, 12], b"AAAA", &[1, 2, 3, 4],
+            &[0, 0, 0, 12], b"BBBB", &[5, 6, 7, 8],
+        ].concat();
+        let mut lazy = LazyMoov::new(bytes.len() as u64);
+        lazy.add_header(0, bytes[0 .. 8].to_vec(), None);
+        lazy.add_header(8, bytes[8 .. 16].to_vec(), Some(12));
+        lazy.add_header(20, bytes[20 .. 28].to_vec(), Some(12));
+        (bytes, lazy)
+    }
+
+    #[test]
+    fn missing() {
+        let (bytes, mut lazy) = lazy_moov();
+        assert_eq!(lazy.loaded_len(), 24);
+        assert!(lazy.is_loaded(10));
+        assert!(!lazy.is_loaded(16));
+
+        // Container header is not a leaf
+        assert!(lazy.missing(0 .. 8).is_empty());
+        // Empty range returns the atom containing its start
+        assert_eq!(lazy.missing(16 .. 16), vec![(0, 8 .. 20)]);
+        assert_eq!(lazy.missing(18 .. 22), vec![(0, 8 .. 20), (1, 20 .. 32)]);
+        assert_eq!(lazy.missing(20 .. 32), vec![(1, 20 .. 32)]);
+        assert!(lazy.missing(32 .. 40).is_empty());
+
+        lazy.load(0, bytes[8 .. 20].to_vec());
+        assert!(lazy.is_loaded(16));
+        assert_eq!(lazy.missing(0 .. 32), vec![(1, 20 .. 32)]);
+        assert_eq!(lazy.loaded_len(), 28);
+    }
+
+    #[test]
+    fn read_not_loaded() {
+        let (bytes, mut lazy) = lazy_moov();
+        let mut reader = MoovReader::new(MoovBuffer::Lazy(lazy.to_owned()));
+
+        // Headers are in memory
+        let mut buf = [0; 8];
+        reader.seek(SeekFrom::Start(8)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[4..], b"AAAA");
+        // Data loads are not
+        let err = reader.read_exact(&mut buf[..4]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        lazy.load(0, bytes[8 .. 20].to_vec());
+        let mut reader = MoovReader::new(MoovBuffer::Lazy(lazy));
+        reader.seek(SeekFrom::Start(16)).unwrap();
+        reader.read_exact(&mut buf[..4]).unwrap();
+        assert_eq!(buf[..4], [1, 2, 3, 4]);
+        // Reads do not continue past a segment
+        reader.seek(SeekFrom::Start(18)).unwrap();
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        assert_eq!(reader.read(&mut buf).unwrap(), 8);
+        assert_eq!(&buf[4..], b"BBBB");
+        assert!(reader.read(&mut buf).is_err());
+        // End of data load
+        reader.seek(SeekFrom::End(0)).unwrap();
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn lazy_sample_table() {
+        let tracks = [
+            TestTrack::new(1, "One", vec![vec![vec![1; 4], vec![2; 4]], vec![vec![3; 8]]]),
+            TestTrack::new(2, "Two", vec![vec![vec![4; 2]]]),
+        ];
+        let bytes = mp4(&tracks);
+        let mut mp4 = Mp4::from_reader_lazy(Cursor::new(bytes)).unwrap();
+
+        let loaded = |mp4: &mut Mp4<Cursor<Vec<u8>>>| match mp4.moov_reader().get_ref() {
+            MoovBuffer::Lazy(lazy) => lazy.loaded.to_owned(),
+            _ => panic!("moov is not lazily loaded"),
+        };
+        // mvhd, then tkhd, mdhd, hdlr, stsd, stts, stsc, stsz, stco for each track
+        assert_eq!(loaded(&mut mp4), vec![false; 17]);
+
+        let samples: Vec<Vec<u8>> = mp4.track(1_u32, true).unwrap()
+            .samples()
+            .map(|s| s.unwrap().raw().to_vec())
+            .collect();
+        assert_eq!(samples, tracks[0].samples());
+
+        // Only mvhd and the atoms for the first track are loaded
+        let mut expected = vec![true; 9];
+        expected.extend([false; 8]);
+        assert_eq!(loaded(&mut mp4), expected);
+    }
+}
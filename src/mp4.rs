@@ -2,6 +2,10 @@
 //!
 //! If an atom can not be located, try running `Mp4::reset()` first to set reader position to 0.
 //!
+//! `Mp4` is generic over any byte source that implements `Read + Seek`,
+//! and defaults to `File`. Use `Mp4::from_reader()` for e.g. MP4 data already
+//! in memory (`Cursor<Vec<u8>>`, `&[u8]`).
+//!
 //! ```rs
 //! use mp4iter::Mp4;
 //! use std::path::Path;
@@ -9,9 +13,9 @@
 //! fn main() -> std::io::Result<()> {
 //!     let mut mp4 = Mp4::new(Path::new("GOPRO_VIDEO.MP4"))?;
 //!
-//!     // Iterate over atom headers
-//!     for atom_header in mp4.into_iter() {
-//!         println!("{atom_header:?}")
+//!     // Iterate over top-level atom headers
+//!     for result in mp4.by_ref() {
+//!         println!("{:?}", result?)
 //!     }
 //!
 //!     // Duration for longest track.
@@ -41,38 +45,44 @@
 //! ```
 
 use std::{
-    borrow::BorrowMut, fs::File, io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom}, path::{Path, PathBuf}
+    fs::File, io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom}, path::{Path, PathBuf}
 };
 
 use crate::{
-    atom_types::Stsc, reader::AtomReadOrigin, track::{ParsableTrackId, Track, TrackAttributes, TrackIdentifier}, Atom, AtomHeader, AudioFormat, Co64, Dref, Ftyp, Hdlr, Mdhd, Mp4Error, Mp4Reader, Mvhd, ReadOption, SampleOffsets, Sdtp, Smhd, Stco, Stsd, Stss, Stsz, Stts, TargetReader, Tkhd, Tmcd, VideoFormat, Vmhd
+    atom_types::Stsc, reader::AtomReadOrigin, track::{ParsableTrackId, Track, TrackAttributes, TrackIdentifier}, tree::AtomTree, iterator::Mp4Iterator, visitor::{self, AtomVisitor}, registry::{AtomRegistry, ParsedAtom}, Atom, AtomHeader, AudioFormat, Chapter, Co64, Dref, Ftyp, Hdlr, Location, LocationSource, Mdhd, Metadata, MoovReader, Tags, Mp4Error, Mp4Reader, Mvhd, ReadOption, SampleOffsets, Sdtp, Smhd, Stco, Stsd, Stss, Stsz, Stts, TargetReader, Tkhd, Tmcd, VideoFormat, Vmhd
 };
+use crate::source::{ByteSource, SourceReader};
+#[cfg(feature = "mmap")]
+use crate::MappedFile;
 use binrw::{endian::Endian, BinRead, BinReaderExt};
 use time::{ext::NumericalDuration};
 
 /// MP4 reader.
+///
+/// Generic over any byte source implementing `Read + Seek`.
+/// Defaults to `File`.
 #[derive(Debug)]
-pub struct Mp4 {
-    /// Path.
-    path: PathBuf,
-    /// Reader split between a `BufReader` over the full file,
+pub struct Mp4<R = File> {
+    /// Path. `None` if created from a reader.
+    pub(crate) path: Option<PathBuf>,
+    /// Reader split between a `BufReader` over the full MP4,
     /// and an in-memory buffer over the `moov` atom.
-    pub(crate) reader: Mp4Reader,
+    pub(crate) reader: Mp4Reader<R>,
 }
 
-impl Seek for Mp4 {
+impl <R: Read + Seek> Seek for Mp4<R> {
     fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
         self.reader.file_reader.seek(pos)
     }
 }
 
-impl Read for Mp4 {
+impl <R: Read + Seek> Read for Mp4<R> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         self.reader.file_reader.read(buf)
     }
 }
 
-impl BufRead for Mp4 {
+impl <R: Read + Seek> BufRead for Mp4<R> {
     fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
         self.reader.file_reader.fill_buf()
     }
@@ -82,19 +92,38 @@ impl BufRead for Mp4 {
     }
 }
 
-impl Iterator for Mp4 {
-    type Item = AtomHeader;
+impl <R: Read + Seek> Iterator for Mp4<R> {
+    type Item = Result<AtomHeader, Mp4Error>;
 
-    /// 'Next' funtion for non-fallible iterator over atom headers.
-    /// Returns `None` for errors, i.e. iteration will simply end.
+    /// 'Next' funtion for fallible iterator over top-level atom headers,
+    /// starting at current file position.
+    /// An error, e.g. for a corrupt atom size, is returned once,
+    /// after which iteration ends.
+    ///
+    /// Use `Mp4::walk()` to also iterate over child atoms,
+    /// with nesting depth.
     fn next(&mut self) -> Option<Self::Item> {
-        // Read atom header, seek to position of next atom
         // Uses Mp4Reader's BufReader, not cursor over moov
-        self.next_header(true).ok()
+        let pos = match self.pos_file() {
+            Ok(p) => p,
+            Err(err) => return Some(Err(err)),
+        };
+        let result = Mp4Iterator::top_level(&mut self.reader, pos)
+            .next()?
+            .map(|(_, _, header)| header);
+        // Seek to position of next atom, or to end of file
+        // on errors to end iteration
+        let next = match &result {
+            Ok(header) => SeekFrom::Start(header.end()),
+            Err(_) => SeekFrom::End(0),
+        };
+        if let Err(err) = self.reader.seek(&TargetReader::File, next) {
+            return Some(Err(err))
+        }
+        Some(result)
     }
 }
 
-// impl <R: Seek + Read + BinRead + BinReaderExt> Mp4<R> {
 impl Mp4 {
     /// New `Mp4` from path.
     ///
@@ -103,7 +132,7 @@ impl Mp4 {
     pub fn new(path: &Path) -> Result<Self, Mp4Error> {
         let file = File::open(path)?;
         Ok(Self {
-            path: path.to_owned(),
+            path: Some(path.to_owned()),
             reader: Mp4Reader::new(file)?,
         })
     }
@@ -116,28 +145,130 @@ impl Mp4 {
     ) -> Result<Self, Mp4Error> {
         let file = File::open(path)?;
         Ok(Self {
-            path: path.to_owned(),
+            path: Some(path.to_owned()),
             reader: Mp4Reader::with_capacity(file, Some(capacity))?,
         })
     }
 
-    /// Mp4 file size in bytes.
+    /// New `Mp4` from path, where only the atom headers
+    /// within the `moov` atom are read when opening the file.
+    /// The data load for an individual atom, e.g. a sample table
+    /// such as `stsz` or `stco`, is read the first time it is accessed.
+    ///
+    /// Useful for e.g. long recordings with `moov` atoms
+    /// of several hundred MB.
+    pub fn open_lazy(path: &Path) -> Result<Self, Mp4Error> {
+        let file = File::open(path)?;
+        Ok(Self {
+            path: Some(path.to_owned()),
+            reader: Mp4Reader::lazy(file, None)?,
+        })
+    }
+}
+
+impl <'a> Mp4<Cursor<&'a [u8]>> {
+    /// New `Mp4` from a byte slice,
+    /// e.g. an upload or an archive member already in memory.
+    pub fn from_slice(bytes: &'a [u8]) -> Result<Self, Mp4Error> {
+        Self::from_reader(Cursor::new(bytes))
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl Mp4<Cursor<MappedFile>> {
+    /// New `Mp4` from path via a memory-mapped file.
+    /// Requires the `mmap` feature.
+    ///
+    /// The `moov` atom references the mapping rather than
+    /// being copied into memory, and `Track::sample_slices()`
+    /// yields samples as borrowed slices of the mapped file.
+    ///
+    /// Note that the file must not be truncated or modified
+    /// by another process while mapped.
+    pub fn open_mmap(path: &Path) -> Result<Self, Mp4Error> {
+        let map = MappedFile::open(path)?;
+        Ok(Self {
+            path: Some(path.to_owned()),
+            reader: Mp4Reader::from_mapped(map, None)?,
+        })
+    }
+}
+
+impl <S: ByteSource> Mp4<SourceReader<S>> {
+    /// New `Mp4` from a range-read byte source,
+    /// e.g. an MP4 behind a range-capable object store.
+    /// Only the `moov` atom is read in full.
+    ///
+    /// `Mp4::from_source()` uses default buffer size for `BufReader`,
+    /// use `Mp4::from_source_with_capacity()` for custom buffer sizes.
+    /// Note that this also sets the minimum size for
+    /// each request to the source.
+    pub fn from_source(source: S) -> Result<Self, Mp4Error> {
+        Self::from_reader(SourceReader::new(source))
+    }
+
+    /// New `Mp4` from a range-read byte source
+    /// with custom buffer size for the underlying `BufReader`.
+    pub fn from_source_with_capacity(
+        source: S,
+        capacity: usize
+    ) -> Result<Self, Mp4Error> {
+        Self::from_reader_with_capacity(SourceReader::new(source), capacity)
+    }
+}
+
+impl <R: Read + Seek> Mp4<R> {
+    /// New `Mp4` from any reader implementing `Read + Seek`,
+    /// e.g. `Cursor<Vec<u8>>` for MP4 data already in memory.
+    ///
+    /// `Mp4::from_reader()` uses default buffer size for `BufReader`,
+    /// use `Mp4::from_reader_with_capacity()` for custom buffer sizes.
+    pub fn from_reader(reader: R) -> Result<Self, Mp4Error> {
+        Ok(Self {
+            path: None,
+            reader: Mp4Reader::new(reader)?,
+        })
+    }
+
+    /// New `Mp4` from any reader implementing `Read + Seek`
+    /// with custom buffer size for the underlying `BufReader`.
+    pub fn from_reader_with_capacity(
+        reader: R,
+        capacity: usize
+    ) -> Result<Self, Mp4Error> {
+        Ok(Self {
+            path: None,
+            reader: Mp4Reader::with_capacity(reader, Some(capacity))?,
+        })
+    }
+
+    /// New `Mp4` from any reader implementing `Read + Seek`,
+    /// with the `moov` atom loaded lazily. See `Mp4::open_lazy()`.
+    pub fn from_reader_lazy(reader: R) -> Result<Self, Mp4Error> {
+        Ok(Self {
+            path: None,
+            reader: Mp4Reader::lazy(reader, None)?,
+        })
+    }
+
+    /// Mp4 size in bytes.
     pub fn len(&self) -> u64 {
         self.reader.len(&TargetReader::File)
     }
 
     /// Mp4 file path.
-    pub fn path(&self) -> PathBuf {
+    /// Returns `None` if created from a reader.
+    pub fn path(&self) -> Option<PathBuf> {
         self.path.to_owned()
     }
 
-    /// Returns the underlying reader over the entire file.
-    pub fn file_reader(&mut self) -> &mut BufReader<File> {
+    /// Returns the underlying reader over the entire MP4.
+    pub fn file_reader(&mut self) -> &mut BufReader<R> {
         &mut self.reader.file_reader
     }
 
     /// Returns the underlying in-memory reader over the `moov` atom.
-    pub fn moov_reader(&mut self) -> &mut Cursor<Vec<u8>> {
+    pub fn moov_reader(&mut self) -> &mut MoovReader {
         &mut self.reader.moov_reader
     }
 
@@ -266,7 +397,7 @@ impl Mp4 {
         &mut self,
         target: &TargetReader,
         origin: AtomReadOrigin
-    ) -> Result<Atom, Mp4Error> {
+    ) -> Result<Atom<'_, R>, Mp4Error> {
         self.reader.atom(target, origin, true)
     }
 
@@ -281,13 +412,99 @@ impl Mp4 {
     /// encountered atom with specified FourCC.
     ///
     /// Note that some atom types may occur more than once (e.g. `trak` and its child atoms).
-    pub fn find_atom(&mut self, fourcc_name: &str, reset: bool) -> Result<Atom, Mp4Error> {
+    pub fn find_atom(&mut self, fourcc_name: &str, reset: bool) -> Result<Atom<'_, R>, Mp4Error> {
         self.reader.find_atom(&TargetReader::File, fourcc_name, reset)
     }
 
+    /// Returns a depth-aware walk over all atoms in the MP4,
+    /// starting at the first top-level atom.
+    /// Yields `(DEPTH, PATH, HEADER)` or an error,
+    /// which ends the walk. See `Mp4Iterator`.
+    ///
+    /// Reader position is not restored. Use `Mp4::reset()` if required.
+    pub fn walk(&mut self) -> Mp4Iterator<'_, R> {
+        Mp4Iterator::new(&mut self.reader)
+    }
+
+    /// Invokes `visitor` for all atoms in the MP4, in file order,
+    /// in a single pass. See `AtomVisitor`.
+    ///
+    /// Reader position is not restored. Use `Mp4::reset()` if required.
+    pub fn visit<V: AtomVisitor>(&mut self, visitor: &mut V) -> Result<(), Mp4Error> {
+        visitor::visit(&mut self.walk(), visitor)
+    }
+
+    /// Returns an owned tree of all atoms in the MP4,
+    /// with absolute byte offsets. See `AtomTree`.
+    ///
+    /// Resets reader position to the start of the MP4 when done.
+    pub fn tree(&mut self) -> Result<AtomTree, Mp4Error> {
+        let tree = AtomTree::new(&mut self.reader)?;
+        self.reset()?;
+        Ok(tree)
+    }
+
+    /// Returns all atom headers matching the path query `path`,
+    /// e.g. `moov/trak[*]/mdia/minf/stbl/stsd`, in file order.
+    /// Offsets are absolute. See `AtomPath` for syntax.
+    ///
+    /// Atoms both inside and outside `moov` are matched.
+    /// Use `Mp4::atom_at()` to read a returned header as an atom.
+    pub fn select(&mut self, path: &str) -> Result<Vec<AtomHeader>, Mp4Error> {
+        let tree = self.tree()?;
+        Ok(tree.select(path)?
+            .into_iter()
+            .map(|n| n.header.to_owned())
+            .collect())
+    }
+
+    /// Returns atom for the first match for the path query `path`,
+    /// e.g. `moov/trak[2]/mdia/hdlr` for the `hdlr` atom in the third track.
+    /// See `AtomPath` for syntax.
+    pub fn select_atom(&mut self, path: &str) -> Result<Atom<'_, R>, Mp4Error> {
+        let header = self.select(path)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Mp4Error::NoSuchAtom(path.to_owned()))?;
+        self.atom_at(&header)
+    }
+
+    /// Returns all atoms matching the path query `path`
+    /// that have a registered parser (see `AtomRegistry`),
+    /// parsed into typed values, in file order.
+    /// Atoms without a registered parser are ignored.
+    pub fn select_parsed(&mut self, path: &str) -> Result<Vec<(AtomHeader, ParsedAtom)>, Mp4Error> {
+        let mut parsed: Vec<(AtomHeader, ParsedAtom)> = Vec::new();
+        for header in self.select(path)? {
+            if let Some(value) = self.atom_at(&header)?.parse()? {
+                parsed.push((header, value));
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Returns the registry for container atoms and atom parsers.
+    pub fn registry(&self) -> &AtomRegistry {
+        &self.reader.registry
+    }
+
+    /// Returns the registry for container atoms and atom parsers,
+    /// e.g. to add containers or register parsers for vendor atoms.
+    pub fn registry_mut(&mut self) -> &mut AtomRegistry {
+        &mut self.reader.registry
+    }
+
+    /// Returns atom for `header`, which must have absolute offsets
+    /// (see `Mp4::tree()` and `Mp4::select()`),
+    /// positioned at its data load, regardless of current reader position.
+    /// Atoms within `moov` are read from the in-memory `moov` reader.
+    pub fn atom_at(&mut self, header: &AtomHeader) -> Result<Atom<'_, R>, Mp4Error> {
+        self.reader.atom_abs(header)
+    }
+
     /// Returns atom with specified FourCC within `udta` (user data)
     /// container atom.
-    pub fn find_user_data(&mut self, fourcc: &str) -> Result<Atom, Mp4Error> {
+    pub fn find_user_data(&mut self, fourcc: &str) -> Result<Atom<'_, R>, Mp4Error> {
         // reset to start of mp4,
         // since moov (that contains udta) sometimes precedes `mdat`,
         // then find moov header to set position withing moov bounds
@@ -302,6 +519,125 @@ impl Mp4 {
         }
     }
 
+    /// Returns QuickTime metadata (`meta`) for the movie (`moov/meta`),
+    /// and for each track with a `meta` atom (`moov/trak/meta`),
+    /// e.g. `com.apple.quicktime.make` as written by iPhones,
+    /// DJI, and Android phones. See `Meta`.
+    ///
+    /// Resets reader position to the start of the MP4 when done.
+    pub fn metadata(&mut self) -> Result<Metadata, Mp4Error> {
+        let tree = self.tree()?;
+        let mut metadata = Metadata::default();
+
+        if let Some(node) = tree.select("moov/meta")?.first() {
+            metadata.movie = Some(self.atom_at(node.header())?.meta()?);
+        }
+
+        for trak in tree.select("moov/trak")? {
+            if let (Some(tkhd), Some(meta)) = (tree.child(trak, "tkhd"), tree.child(trak, "meta")) {
+                let track_id = self.atom_at(tkhd.header())?.tkhd()?.track_id();
+                metadata.tracks.push((track_id, self.atom_at(meta.header())?.meta()?));
+            }
+        }
+
+        self.reset()?;
+        Ok(metadata)
+    }
+
+    /// Returns typed user data tags in `moov/udta`, i.e.
+    /// international text atoms, e.g. `©nam`, `©day`, `©too`, `©xyz`,
+    /// `©mak`, `©mod`, and iTunes-style metadata in `moov/udta/meta/ilst`.
+    /// See `Tags`.
+    ///
+    /// Resets reader position to the start of the MP4 when done.
+    pub fn tags(&mut self) -> Result<Tags, Mp4Error> {
+        let tree = self.tree()?;
+        let mut tags = Tags::default();
+
+        for node in tree.select("moov/udta/*")? {
+            match node.name().to_str() {
+                "meta" => tags.insert_meta(&self.atom_at(node.header())?.meta()?),
+                name if name.starts_with('©') => {
+                    let data = self.atom_at(node.header())?.read_data()?;
+                    tags.insert_text(name, &data);
+                },
+                _ => (),
+            }
+        }
+
+        self.reset()?;
+        Ok(tags)
+    }
+
+    /// Returns recording location, from the first present of
+    /// `com.apple.quicktime.location.ISO6709` (`moov/meta`),
+    /// `©xyz` (`moov/udta`), or 3GPP `loci` (`moov/udta`).
+    /// Returns `None` if none is present or can be parsed.
+    ///
+    /// Resets reader position to the start of the MP4 when done.
+    pub fn location(&mut self) -> Result<Option<Location>, Mp4Error> {
+        let quicktime = self.metadata()?
+            .get("com.apple.quicktime.location.ISO6709")
+            .and_then(|v| v.as_str())
+            .and_then(|s| Location::from_iso6709(s, LocationSource::QuickTime));
+        if quicktime.is_some() {
+            return Ok(quicktime)
+        }
+
+        let xyz = self.tags()?
+            .location()
+            .and_then(|s| Location::from_iso6709(s, LocationSource::Xyz));
+        if xyz.is_some() {
+            return Ok(xyz)
+        }
+
+        let mut loci: Option<Location> = None;
+        if let Some(header) = self.select("moov/udta/loci")?.first() {
+            loci = Location::from_loci(&self.atom_at(header)?.read_data()?);
+        }
+
+        self.reset()?;
+        Ok(loci)
+    }
+
+    /// Returns chapters from a chapter track (`text` or `tx3g`)
+    /// referenced via `tref/chap`, or if there is none,
+    /// from a Nero chapter list (`moov/udta/chpl`).
+    /// Returns an empty list if there are no chapters.
+    ///
+    /// For a chapter track, any edit list (`edts/elst`) is applied,
+    /// so that chapter start times match the presentation timeline.
+    ///
+    /// Resets reader position to the start of the MP4 when done.
+    pub fn chapters(&mut self) -> Result<Vec<Chapter>, Mp4Error> {
+        let chapter_track = self.track_list(true)?
+            .iter()
+            .find_map(|t| t.referenced_ids("chap").first().copied());
+
+        if let Some(track_id) = chapter_track {
+            let mut track = self.track(track_id, true)?;
+            // Apply edit list, so that chapter times match
+            // the presentation timeline
+            let chapters = track.samples_presented()
+                .map(|s| s.map(|s| Chapter::from_text_sample(s.raw(), s.relative(), s.duration())))
+                .collect::<Result<Vec<Chapter>, Mp4Error>>()?;
+            if !chapters.is_empty() {
+                self.reset()?;
+                return Ok(chapters)
+            }
+        }
+
+        let mut chapters: Vec<Chapter> = Vec::new();
+        if let Some(header) = self.select("moov/udta/chpl")?.first() {
+            let data = self.atom_at(header)?.read_data()?;
+            let end = self.duration(true)?;
+            chapters = Chapter::from_chpl(&data, end).unwrap_or_default();
+        }
+
+        self.reset()?;
+        Ok(chapters)
+    }
+
     /// Returns all atom headers for child atoms in
     /// `udta` (user data) atom.
     pub fn user_data_headers(&mut self) -> Result<Vec<AtomHeader>, Mp4Error> {
@@ -315,6 +651,7 @@ impl Mp4 {
     }
 
     /// Returns data loads for all user data atoms.
+    /// For typed values, see `Mp4::tags()`.
     pub fn user_data_cursors(&mut self) -> Result<Vec<(String, Cursor<Vec<u8>>)>, Mp4Error> {
         self.user_data_headers()?
             .into_iter()
@@ -709,7 +1046,7 @@ impl Mp4 {
     /// the main GPMF telemetry interleaved in the `mdat` atom).
     ///
     /// Path: `moov.udta`
-    pub fn udta(&mut self, reset: bool) -> Result<Atom, Mp4Error> {
+    pub fn udta(&mut self, reset: bool) -> Result<Atom<'_, R>, Mp4Error> {
         // Set reset to true, position to start of file to avoid
         // previous reads to have moved the cursor
         // past the 'udta' atom.
@@ -735,13 +1072,41 @@ impl Mp4 {
     /// Some kinds of tracks may be present more than once, such audio if multi-languge,
     /// or two video tracks for 360 cameras.
     // pub fn track(&mut self, identifier: TrackIdentifier, reset: bool) -> Result<Track, Mp4Error> {
-    pub fn track(&mut self, identifier: impl ParsableTrackId, reset: bool) -> Result<Track, Mp4Error> {
-        Track::new(self.borrow_mut(), identifier, reset)
+    pub fn track(&mut self, identifier: impl ParsableTrackId, reset: bool) -> Result<Track<'_, R>, Mp4Error> {
+        Track::new(self, identifier, reset)
     }
 
     /// Returns general info for all track in the MP4 file.
     pub fn track_list(&mut self, reset: bool) -> Result<Vec<TrackAttributes>, Mp4Error> {
-        TrackAttributes::all(self.borrow_mut(), reset)
+        TrackAttributes::all(self, reset)
+    }
+
+    /// Returns the track that track with ID `track_id` references
+    /// via `reference_type` (see `TrackAttributes::references()`),
+    /// e.g. `tmcd` for the time code track for a video track.
+    /// If several tracks are referenced, the first is returned.
+    pub fn referenced_track(&mut self, track_id: u32, reference_type: &str) -> Result<Track<'_, R>, Mp4Error> {
+        let id = self.track_list(true)?
+            .iter()
+            .find(|t| t.id() == track_id)
+            .ok_or_else(|| Mp4Error::NoSuchTrack(track_id.to_string()))?
+            .referenced_ids(reference_type)
+            .first()
+            .copied()
+            .ok_or_else(|| Mp4Error::NoSuchTrack(format!("{reference_type} for {track_id}")))?;
+        self.track(id, true)
+    }
+
+    /// Returns the first track that references track with ID `track_id`
+    /// via `reference_type` (see `TrackAttributes::references()`),
+    /// e.g. `cdsc` for the timed metadata track that describes a video track.
+    pub fn referencing_track(&mut self, track_id: u32, reference_type: &str) -> Result<Track<'_, R>, Mp4Error> {
+        let id = self.track_list(true)?
+            .iter()
+            .find(|t| t.referenced_ids(reference_type).contains(&track_id))
+            .map(|t| t.id())
+            .ok_or_else(|| Mp4Error::NoSuchTrack(format!("{reference_type} for {track_id}")))?;
+        self.track(id, true)
     }
 
     /// Returns creation time of MP4.
@@ -758,7 +1123,8 @@ impl Mp4 {
     /// Reference `mvhd`: <https://developer.apple.com/documentation/quicktime-file-format/movie_header_atom>
     pub fn creation_time(&mut self, reset: bool) -> Result<time::PrimitiveDateTime, Mp4Error> {
         let mvhd = self.mvhd(reset)?;
-        Ok(mvhd.creation_time())
+        mvhd.creation_time()
+            .ok_or(Mp4Error::InvalidDateTime(mvhd.creation_time))
     }
 
     /// Returns duration for longest track.
@@ -799,7 +1165,9 @@ impl Mp4 {
         reset: bool,
     ) -> Result<(time::PrimitiveDateTime, time::Duration), Mp4Error> {
         let mvhd = self.mvhd(reset)?;
-        Ok((mvhd.creation_time(), mvhd.duration()))
+        let creation_time = mvhd.creation_time()
+            .ok_or(Mp4Error::InvalidDateTime(mvhd.creation_time))?;
+        Ok((creation_time, mvhd.duration()))
     }
 
     pub fn time_first_frame(&mut self, reset: bool) -> Result<time::Duration, Mp4Error> {
@@ -1,33 +1,40 @@
 use std::{
-    borrow::BorrowMut,
     fs::File,
     io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom},
 };
 
 use binrw::{BinRead, BinReaderExt, Endian};
 
-use crate::{Atom, AtomHeader, FourCC, Mp4Error, CONTAINER};
+use crate::{moov::{LazyMoov, MoovBuffer, MoovReader}, registry::AtomRegistry, Atom, AtomHeader, FourCC, Meta, Mp4Error};
+#[cfg(feature = "mmap")]
+use crate::mmap::MappedFile;
 
-/// `BufReader` over a `File`,
+/// `BufReader` over any byte source implementing `Read + Seek`
+/// (defaults to `File`),
 /// with read boundaries,
 /// for e.g. atoms.
 #[derive(Debug)]
-pub(crate) struct Mp4Reader {
-    /// File size.
+pub(crate) struct Mp4Reader<R = File> {
+    /// Size of the MP4 in bytes.
     pub(crate) len: u64,
-    /// Reader over the full MP4 file.
-    pub(crate) file_reader: BufReader<File>,
+    /// Reader over the full MP4.
+    pub(crate) file_reader: BufReader<R>,
     /// Atom header representing the
     /// `moov` atom (offsets correspond to the full file).
     pub(crate) moov_header: AtomHeader,
     /// In-memory buffer/reader over the `moov` atom
     /// data load (i.e. atoms contained by `moov`).
-    pub(crate) moov_reader: Cursor<Vec<u8>>,
+    pub(crate) moov_reader: MoovReader,
+    /// Container atoms and atom parsers.
+    pub(crate) registry: AtomRegistry,
+    /// Top-level atom boundaries walked so far, in ascending order.
+    /// See `Mp4Reader::top_level_boundary()`.
+    pub(crate) top_level: Vec<u64>,
 }
 
-impl Mp4Reader {
+impl <R: Read + Seek> Mp4Reader<R> {
     /// Creates a `BufReader` with default capacity (8KiB)
-    /// for the full MP4 file,
+    /// for the full MP4,
     /// and in-memory buffer (`Cursor<Vec<u8>>`) over the `moov`
     /// atom in that file.
     ///
@@ -35,8 +42,8 @@ impl Mp4Reader {
     /// custom buffer sizes. (e.g. GoPro often stores
     /// telemetry with chunk sizes just above the default
     /// 8KiB buffer size)
-    pub(crate) fn new(file: File) -> Result<Self, Mp4Error> {
-        Self::with_capacity(file, None)
+    pub(crate) fn new(reader: R) -> Result<Self, Mp4Error> {
+        Self::with_capacity(reader, None)
     }
 
     /// Returns which reader to use for reading at `file_pos`
@@ -65,32 +72,132 @@ impl Mp4Reader {
     }
 
     pub(crate) fn with_capacity(
-        file: File,
+        reader: R,
         capacity: Option<usize>
     ) -> Result<Self, Mp4Error> {
-        let len = file.metadata()?.len();
-        let reader = match capacity {
-            Some(cap) => BufReader::with_capacity(cap, file),
-            None => BufReader::new(file),
-        };
+        Self::with_moov_loader(reader, capacity, |rdr, moov_hdr| {
+            // no need to seek, already at moov data load position after having read header
+            let buf = rdr.read_bytes(
+                &TargetReader::File,
+                ReadOption::Sized(usize::try_from(moov_hdr.data_size())?),
+                None,
+                None
+            )?;
+            Ok(MoovBuffer::Owned(buf))
+        })
+    }
 
-        let mut rdr = Self {
-            file_reader: reader,
-            len,
-            moov_header: AtomHeader::default(),
-            moov_reader: Cursor::new(Vec::new()),
+    /// Creates a reader where only the atom headers
+    /// within the `moov` atom are read into memory.
+    /// The data load for an atom is read the first time
+    /// it is accessed.
+    pub(crate) fn lazy(
+        reader: R,
+        capacity: Option<usize>
+    ) -> Result<Self, Mp4Error> {
+        Self::with_moov_loader(reader, capacity, |rdr, moov_hdr| {
+            rdr.index_moov(moov_hdr).map(MoovBuffer::Lazy)
+        })
+    }
+
+    /// Reads all atom headers within the `moov` atom,
+    /// and indexes the byte ranges for non-container atoms.
+    fn index_moov(&mut self, moov_hdr: &AtomHeader) -> Result<LazyMoov, Mp4Error> {
+        let moov_start = moov_hdr.data_offset();
+        let mut lazy = LazyMoov::new(moov_hdr.data_size());
+        let mut pos = moov_start;
+
+        while pos < moov_hdr.end() {
+            let hdr = self.header(&TargetReader::File, Some(SeekFrom::Start(pos)))?;
+            if hdr.size_eof {
+                return Err(Mp4Error::ZeroSizeAtom {
+                    name: hdr.name.to_string(),
+                    offset: hdr.offset
+                });
+            }
+            let hdr_bytes = self.read_bytes(
+                &TargetReader::File,
+                ReadOption::Sized(hdr.header_size() as usize),
+                Some(SeekFrom::Start(pos)),
+                None
+            )?;
+
+            match hdr.is_container() {
+                // Descend into container
+                true => {
+                    lazy.add_header(pos - moov_start, hdr_bytes, None);
+                    pos = hdr.data_offset();
+                },
+                false => {
+                    lazy.add_header(pos - moov_start, hdr_bytes, Some(hdr.atom_size));
+                    pos = hdr.offset + hdr.atom_size;
+                }
+            }
+        }
+
+        Ok(lazy)
+    }
+
+    /// Lazily loaded `moov` only. Reads atoms that intersect
+    /// `len` bytes from current `moov` position,
+    /// if not already in memory.
+    /// If `len` is `None`, the atom at current position is read.
+    ///
+    /// Does nothing if `target` is not `TargetReader::Moov`.
+    pub(crate) fn load_moov(
+        &mut self,
+        target: &TargetReader,
+        len: Option<usize>
+    ) -> Result<(), Mp4Error> {
+        let lazy = match (target, self.moov_reader.get_ref()) {
+            (TargetReader::Moov, MoovBuffer::Lazy(lazy)) => lazy,
+            _ => return Ok(()),
         };
 
-        if let Some(moov_hdr) = rdr.find_header(&TargetReader::File, "moov", false)? {
-            // no need to seek, already at moov data load position after having read header
-            let moov_crs = rdr.cursor(
+        let pos = self.moov_reader.position();
+        // E.g. atom headers are always in memory
+        if len.is_none() && lazy.is_loaded(pos) {
+            return Ok(());
+        }
+        let missing = lazy.missing(pos .. pos + len.unwrap_or_default() as u64);
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        // Restore file position after loading
+        let file_pos = self.file_reader.stream_position()?;
+        for (leaf, range) in missing {
+            let bytes = self.read_bytes(
                 &TargetReader::File,
-                usize::try_from(moov_hdr.data_size())?,
-                None,
+                ReadOption::Sized(usize::try_from(range.end - range.start)?),
+                Some(SeekFrom::Start(self.moov_header.data_offset() + range.start)),
                 None
             )?;
-            rdr.moov_header = moov_hdr;
-            rdr.moov_reader = moov_crs;
+            if let MoovBuffer::Lazy(lazy) = self.moov_reader.get_mut() {
+                lazy.load(leaf, bytes);
+            }
+        }
+        self.file_reader.seek(SeekFrom::Start(file_pos))?;
+
+        Ok(())
+    }
+
+    /// Locates the `moov` atom and sets its data load
+    /// as returned by `load`, which receives the reader
+    /// positioned at the `moov` data load.
+    fn with_moov_loader<F>(
+        reader: R,
+        capacity: Option<usize>,
+        load: F
+    ) -> Result<Self, Mp4Error>
+    where
+        F: FnOnce(&mut Self, &AtomHeader) -> Result<MoovBuffer, Mp4Error>
+    {
+        let mut rdr = Self::without_moov(reader, capacity)?;
+
+        if let Some(moov_hdr) = rdr.find_header(&TargetReader::File, "moov", false)? {
+            let moov_buf = load(&mut rdr, &moov_hdr)?;
+            rdr.set_moov(moov_hdr, moov_buf);
 
             // reset bufreader to start of file after reading moov
             rdr.reset_file()?;
@@ -101,6 +208,38 @@ impl Mp4Reader {
         Err(Mp4Error::MoovReadError)
     }
 
+    /// Creates a reader with an empty `moov` buffer.
+    /// Use `Mp4Reader::set_moov()` to set the `moov` data load.
+    pub(crate) fn without_moov(
+        mut reader: R,
+        capacity: Option<usize>
+    ) -> Result<Self, Mp4Error> {
+        // Derive size by seeking to end, since only `Seek`
+        // can be assumed (e.g. `Cursor<Vec<u8>>`, `&[u8]`)
+        let len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+        let reader = match capacity {
+            Some(cap) => BufReader::with_capacity(cap, reader),
+            None => BufReader::new(reader),
+        };
+
+        Ok(Self {
+            file_reader: reader,
+            len,
+            moov_header: AtomHeader::default(),
+            moov_reader: MoovReader::default(),
+            registry: AtomRegistry::default(),
+            top_level: Vec::new(),
+        })
+    }
+
+    /// Sets `moov` header and in-memory buffer over
+    /// the `moov` data load.
+    pub(crate) fn set_moov(&mut self, moov_header: AtomHeader, moov: MoovBuffer) {
+        self.moov_header = moov_header;
+        self.moov_reader = MoovReader::new(moov);
+    }
+
     /// Seeks to position `pos` for target stream.
     ///
     /// Assumes `pos` is within target reader bounds.
@@ -150,6 +289,7 @@ impl Mp4Reader {
         T: BinRead,
         <T as BinRead>::Args<'static>: Sized + Clone + Default,
     {
+        self.load_moov(target, None)?;
         match target {
             TargetReader::File => Ok(self.file_reader.read_type::<T>(endian)?),
             TargetReader::Moov => Ok(self.moov_reader.read_type::<T>(endian)?),
@@ -165,6 +305,7 @@ impl Mp4Reader {
         T: BinRead,
         <T as BinRead>::Args<'static>: Sized + Clone + Default,
     {
+        self.load_moov(target, None)?;
         match target {
             TargetReader::File => Ok(self.file_reader.read_ne::<T>()?),
             TargetReader::Moov => Ok(self.moov_reader.read_ne::<T>()?),
@@ -180,6 +321,7 @@ impl Mp4Reader {
         T: BinRead,
         <T as BinRead>::Args<'static>: Sized + Clone + Default,
     {
+        self.load_moov(target, None)?;
         match target {
             TargetReader::File => Ok(self.file_reader.read_le::<T>()?),
             TargetReader::Moov => Ok(self.moov_reader.read_le::<T>()?),
@@ -195,6 +337,7 @@ impl Mp4Reader {
         T: BinRead,
         <T as BinRead>::Args<'static>: Sized + Clone + Default,
     {
+        self.load_moov(target, None)?;
         match target {
             TargetReader::File => Ok(self.file_reader.read_be::<T>()?),
             TargetReader::Moov => Ok(self.moov_reader.read_be::<T>()?),
@@ -274,6 +417,11 @@ impl Mp4Reader {
         if let Some(p) = pos {
             self.seek(target, p)?;
         }
+        let load_len = match option {
+            ReadOption::Sized(n) => Some(n),
+            _ => None,
+        };
+        self.load_moov(target, load_len)?;
         let buf = match option {
             ReadOption::Sized(n) => {
                 let mut b = vec![0_u8; n];
@@ -309,7 +457,7 @@ impl Mp4Reader {
     pub(crate) fn len(&self, target: &TargetReader) -> u64 {
         match target {
             TargetReader::File => self.len,
-            TargetReader::Moov => self.moov_reader.get_ref().len() as u64,
+            TargetReader::Moov => self.moov_reader.len(),
         }
     }
 
@@ -489,22 +637,98 @@ impl Mp4Reader {
             hdr.size_64bit = true;
         }
 
+        // 'uuid' atoms have a 16 byte user type
+        // directly after the header
+        if hdr.name.to_str() == "uuid" {
+            hdr.user_type = Some(self.read_type::<[u8; 16]>(target, Endian::Big)?);
+        }
+
+        // Size 0 means the atom extends to end of file,
+        // which is only valid for top-level atoms, e.g. 'mdat'
+        // written by live recorders. Atoms in 'moov' can not be
+        // top-level.
         if hdr.atom_size == 0 {
-            return Err(Mp4Error::ZeroSizeAtom {
-                name: hdr.name.to_string(),
+            match target {
+                TargetReader::File if self.is_top_level(hdr.offset)? => {
+                    hdr.atom_size = self.len - hdr.offset;
+                    hdr.size_eof = true;
+                },
+                _ => return Err(Mp4Error::ZeroSizeAtom {
+                    name: hdr.name.to_string(),
+                    offset: hdr.offset,
+                }),
+            }
+        }
+
+        if hdr.atom_size < hdr.header_size() as u64 {
+            return Err(Mp4Error::UnexpectedAtomSize {
+                len: hdr.atom_size,
                 offset: hdr.offset,
-            });
+            })
         }
 
         // should this be .next() method instead?
-        hdr.next = match CONTAINER.contains(&hdr.name.to_str()) {
+        hdr.container = self.registry.is_container(&hdr.name);
+        hdr.next = match hdr.container {
             true => 0,
             false => hdr.atom_size - hdr.header_size() as u64,
         };
 
+        // Child atoms follow version and flags for ISO 'meta'
+        if hdr.container && hdr.name.to_str() == "meta" && hdr.data_size() >= 4 {
+            if Meta::is_iso(&self.read_type::<[u8; 4]>(target, Endian::Big)?) {
+                hdr.next = 4;
+            }
+            self.seek(target, SeekFrom::Current(-4))?;
+        }
+
         Ok(hdr)
     }
 
+    /// Returns `true` if file position `offset` is at the start
+    /// of a top-level atom.
+    /// File reader position is restored.
+    fn is_top_level(&mut self, offset: u64) -> Result<bool, Mp4Error> {
+        let resume = self.file_reader.stream_position()?;
+        let boundary = self.top_level_boundary(offset);
+        self.file_reader.seek(SeekFrom::Start(resume))?;
+        Ok(boundary? == offset)
+    }
+
+    /// Walks top-level atom sizes and returns the first top-level
+    /// atom boundary at or after `offset`, or the start of the last
+    /// top-level atom before `offset` if it extends to end of file
+    /// or has a corrupt size.
+    ///
+    /// Boundaries are cached, so that the walk resumes from the last
+    /// known boundary at or before `offset`, rather than from the start
+    /// of the file.
+    fn top_level_boundary(&mut self, offset: u64) -> Result<u64, Mp4Error> {
+        let mut pos = match self.top_level.partition_point(|b| *b <= offset) {
+            0 => 0,
+            i => self.top_level[i - 1],
+        };
+        while pos < offset {
+            self.file_reader.seek(SeekFrom::Start(pos))?;
+            let size = match self.file_reader.read_type::<u32>(Endian::Big)? {
+                // 64-bit size follows FourCC
+                1 => {
+                    self.file_reader.seek(SeekFrom::Current(4))?;
+                    self.file_reader.read_type::<u64>(Endian::Big)?
+                },
+                size => size as u64,
+            };
+            if size < 8 {
+                break
+            }
+            pos = pos.saturating_add(size);
+            if self.top_level.last() < Some(&pos) {
+                self.top_level.push(pos);
+            }
+        }
+        Ok(pos)
+    }
+
     /// Return a reader over the atom at current offset.
     /// Assumes offset is at atom data payload position,
     /// i.e directly after the header, adjusted for 64-bit
@@ -514,13 +738,29 @@ impl Mp4Reader {
         target: &TargetReader,
         origin: AtomReadOrigin,
         seek_to_data: bool,
-    ) -> Result<Atom, Mp4Error> {
+    ) -> Result<Atom<'_, R>, Mp4Error> {
         let header = match origin {
             AtomReadOrigin::Position(pos) => self.header(target, Some(pos)),
             AtomReadOrigin::Header(hdr) => Ok(hdr),
             AtomReadOrigin::None => self.header(target, None),
         }?;
-        Atom::new(&header, self.borrow_mut(), target, seek_to_data)
+        Atom::new(&header, self, target, seek_to_data)
+    }
+
+    /// Returns atom for `header`, which must have absolute offsets,
+    /// with reader position at data payload.
+    /// Atoms within `moov` are read from the in-memory `moov` reader.
+    pub(crate) fn atom_abs(&mut self, header: &AtomHeader) -> Result<Atom<'_, R>, Mp4Error> {
+        let mut header = header.to_owned();
+        let moov = &self.moov_header;
+        let target = match moov.data_offset() <= header.offset && header.offset < moov.end() {
+            true => {
+                header.offset -= moov.data_offset();
+                TargetReader::Moov
+            },
+            false => TargetReader::File,
+        };
+        self.atom(&target, AtomReadOrigin::Header(header), true)
     }
 
     /// Returns atom positioned at start of first
@@ -532,7 +772,7 @@ impl Mp4Reader {
         target: &TargetReader,
         fourcc: &str,
         reset: bool,
-    ) -> Result<Atom, Mp4Error> {
+    ) -> Result<Atom<'_, R>, Mp4Error> {
         if let Some(header) = self.find_header(target, fourcc, reset)? {
             self.atom(target, AtomReadOrigin::Header(header), false) // cursor is already at data payload position
         } else {
@@ -544,13 +784,13 @@ impl Mp4Reader {
     /// encountered atom with specified FourCC.
     ///
     /// Note that some atom types may occur more than once (e.g. `trak` and its child atoms).
-    fn find_atom2(
+    pub(crate) fn find_atom2(
         &mut self,
         target: &TargetReader,
         fourcc: &str,
         fourcc_sentinel: Option<&str>,
         reset: bool,
-    ) -> Result<Atom, Mp4Error> {
+    ) -> Result<Atom<'_, R>, Mp4Error> {
         if let Some(header) = self.find_header2(target, fourcc, fourcc_sentinel, reset)? {
             // cursor is at data payload position
             self.atom(target, AtomReadOrigin::Header(header), false)
@@ -774,7 +1014,7 @@ impl Mp4Reader {
         target: &TargetReader,
         pos: Option<u64>,
         ignore_container: bool,
-    ) -> Result<Atom, Mp4Error> {
+    ) -> Result<Atom<'_, R>, Mp4Error> {
         let header = self.header_closest(target, pos, ignore_container)?;
         Ok(self.atom(target, AtomReadOrigin::Header(header), true)?)
     }
@@ -798,7 +1038,7 @@ impl Mp4Reader {
         Ok(())
     }
 
-    /// Checks if `BufReader<File>` is within `moov` boundaries.
+    /// Checks if `BufReader<R>` is within `moov` boundaries.
     pub(crate) fn bounds_moov(&mut self) -> Result<(), Mp4Error> {
         self.bounds(
             &TargetReader::File,
@@ -807,6 +1047,42 @@ impl Mp4Reader {
     }
 }
 
+impl <T: AsRef<[u8]>> Mp4Reader<Cursor<T>> {
+    /// Returns `len` bytes at absolute position `pos`
+    /// as a slice of the underlying in-memory
+    /// or memory-mapped MP4, without copying.
+    ///
+    /// Does not affect reader position.
+    pub(crate) fn slice(&self, pos: u64, len: usize) -> Result<&[u8], Mp4Error> {
+        let bytes = self.file_reader.get_ref().get_ref().as_ref();
+        let start = usize::try_from(pos)?;
+        match start.checked_add(len).filter(|end| *end <= bytes.len()) {
+            Some(end) => Ok(&bytes[start .. end]),
+            None => Err(Mp4Error::BoundsError(pos + len as u64, 0, self.len)),
+        }
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl Mp4Reader<Cursor<MappedFile>> {
+    /// Creates a reader over a memory-mapped MP4,
+    /// with the `moov` data load referencing the
+    /// mapping rather than being copied into memory.
+    pub(crate) fn from_mapped(
+        map: MappedFile,
+        capacity: Option<usize>
+    ) -> Result<Self, Mp4Error> {
+        Self::with_moov_loader(Cursor::new(map.clone()), capacity, |_, moov_hdr| {
+            let start = usize::try_from(moov_hdr.data_offset())?;
+            let end = start + usize::try_from(moov_hdr.data_size())?;
+            if end > map.len() {
+                return Err(Mp4Error::BoundsError(end as u64, start as u64, map.len() as u64));
+            }
+            Ok(MoovBuffer::Mapped(map, start .. end))
+        })
+    }
+}
+
 /// Sets read behaviour.
 /// - `ReadOption::Sized(N)`: read `N` bytes
 /// - `ReadOption::Until(B)`: read until sentinel `B` encountered
@@ -824,7 +1100,7 @@ pub enum ReadOption {
 
 /// Whether reader source is that of
 /// in-memory `moov` buffer (`Cursor<Vec<u8>>`),
-/// or reader over the full file (`BufReader<File>`).
+/// or reader over the full MP4 (`BufReader<R>`).
 #[derive(Debug, Clone, Copy)]
 pub(crate) enum TargetReader {
     /// Represents the MP4-file
@@ -838,3 +1114,80 @@ pub(crate) enum AtomReadOrigin {
     Header(AtomHeader),
     None,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{testing::{atom, container, mp4, TestTrack}, Mp4};
+
+    use super::*;
+
+    /// Atom with 32-bit size 0, i.e. extending to end of file.
+    fn eof_atom(name: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut bytes = atom(name, data);
+        bytes[..4].copy_from_slice(&[0; 4]);
+        bytes
+    }
+
+    fn headers(mp4: &mut Mp4<impl Read + Seek>) -> Result<Vec<AtomHeader>, Mp4Error> {
+        mp4.walk()
+            .map(|r| r.map(|(.., header)| header))
+            .collect()
+    }
+
+    #[test]
+    fn size_eof_mdat() {
+        let bytes = [mp4(&[]), atom(b"free", &[0; 4]), eof_atom(b"mdat", &[1; 16])].concat();
+        let offset = bytes.len() as u64 - 24;
+        let mut mp4 = Mp4::from_slice(&bytes).unwrap();
+
+        let headers = headers(&mut mp4).unwrap();
+        let (mdat, rest) = headers.split_last().unwrap();
+        assert_eq!(mdat.name().to_str(), "mdat");
+        assert!(mdat.extends_to_eof());
+        assert_eq!((mdat.offset(), mdat.atom_size()), (offset, 24));
+        assert_eq!(mp4.atom_at(mdat).unwrap().read_data().unwrap(), [1; 16]);
+        assert!(rest.iter().all(|h| !h.extends_to_eof()));
+    }
+
+    #[test]
+    fn size_eof_boundaries_cached() {
+        let head = mp4(&[]);
+        let free = atom(b"free", &[0; 8]);
+        let bytes = [head.to_owned(), free.to_owned(), free, eof_atom(b"mdat", &[1; 16])].concat();
+        // ftyp, mdat, moov, free, free
+        let base = head.len() as u64;
+        let boundaries = vec![20, 28, base, base + 16, base + 32];
+        let mut reader = Mp4Reader::new(Cursor::new(bytes)).unwrap();
+
+        let mdat = reader.header(&TargetReader::File, Some(SeekFrom::Start(base + 32))).unwrap();
+        assert!(mdat.extends_to_eof());
+        assert_eq!(reader.top_level, boundaries);
+
+        // Resumes from a known boundary, without walking further
+        let err = reader.header(&TargetReader::File, Some(SeekFrom::Start(base + 24)));
+        assert!(matches!(err, Err(Mp4Error::ZeroSizeAtom { .. })), "{err:?}");
+        assert_eq!(reader.top_level, boundaries);
+        let mdat = reader.header(&TargetReader::File, Some(SeekFrom::Start(base + 32))).unwrap();
+        assert!(mdat.extends_to_eof());
+        assert_eq!(reader.top_level, boundaries);
+    }
+
+    #[test]
+    fn size_eof_nested() {
+        // Nested in top-level container, also at end of file
+        let bytes = [mp4(&[]), container(b"udta", &[eof_atom(b"free", &[0; 4])])].concat();
+        let offset = bytes.len() as u64 - 12;
+        let err = headers(&mut Mp4::from_slice(&bytes).unwrap());
+        assert!(matches!(&err, Err(Mp4Error::ZeroSizeAtom { name, offset: o }) if name == "free" && *o == offset), "{err:?}");
+
+        // Nested in moov
+        let mut track = TestTrack::new(1, "One", vec![vec![vec![1; 4]]]);
+        track.atoms.push(eof_atom(b"free", &[0; 4]));
+        let bytes = mp4(&[track]);
+        let offset = bytes.windows(4).position(|w| w == b"free").unwrap() as u64 - 4;
+        let err = headers(&mut Mp4::from_slice(&bytes).unwrap());
+        assert!(matches!(&err, Err(Mp4Error::ZeroSizeAtom { name, offset: o }) if name == "free" && *o == offset), "{err:?}");
+    }
+}
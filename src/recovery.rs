@@ -0,0 +1,830 @@
+//! Recovery mode for MP4 files with a missing or truncated `moov` atom,
+//! e.g. when a camera loses power mid-recording.
+//! The `mdat` atom is then usually intact, but there is no sample table
+//! describing where each sample is located.
+//!
+//! A known-good reference file from the same camera (and ideally with the same
+//! recording settings) is used to determine how samples for each track
+//! can be recognised in `mdat`:
+//! - H.264/H.265 (`avc1`, `avc3`, `hvc1`, `hev1`): length-prefixed NAL units,
+//!   using the NAL length size in the reference's `avcC`/`hvcC` configuration.
+//!   Access unit boundaries are detected via the NAL unit headers.
+//! - GoPro GPMF (`gpmd`): KLV structure, i.e. each sample starts with `DEVC`.
+//! - Tracks with constant sample size in the reference (e.g. PCM audio).
+//!   Assigned data that could not be attributed to any other track,
+//!   if this is the only track that can not be recognised by its content.
+//!
+//! Other tracks, e.g. AAC audio, can not be recovered.
+//! Their data is instead listed as unassigned byte ranges (see `Recovered`).
+//! Sample durations are set to the most common sample duration
+//! for the corresponding track in the reference.
+//!
+//! ```rs
+//! use mp4iter::Mp4Handle;
+//! use std::path::Path;
+//!
+//! fn main() -> Result<(), mp4iter::Mp4Error> {
+//!     let mp4 = Mp4Handle::recover(Path::new("BROKEN.MP4"), Path::new("REFERENCE.MP4"))?;
+//!     let track = mp4.track("GoPro MET")?;
+//!     for sample in track.samples() {
+//!         println!("{:?}", sample?.relative());
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::{
+    collections::HashMap,
+    io::{Read, Seek},
+    ops::Range,
+    path::Path,
+};
+
+use time::Duration;
+
+use crate::{
+    track::TrackAttributes,
+    ByteSource,
+    FourCC,
+    Mp4,
+    Mp4Error,
+    SampleOffset,
+};
+
+/// Size of each read when scanning `mdat`.
+const SCAN_BLOCK_SIZE: usize = 4 * 1024 * 1024;
+
+/// How samples for a track are recognised in `mdat`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleModel {
+    /// Length-prefixed NAL units.
+    Nal {
+        /// Size of NAL length prefix in bytes (1, 2, or 4).
+        length_size: usize,
+        /// `true` for H.265, `false` for H.264.
+        hevc: bool,
+    },
+    /// GoPro GPMF, top-level `DEVC` KLV entries.
+    Gpmf,
+    /// Constant sample size in bytes.
+    Fixed(u32),
+    /// Samples can not be recognised.
+    Unsupported,
+}
+
+impl SampleModel {
+    /// Derive sample model from a reference track.
+    fn new(attributes: &TrackAttributes) -> Self {
+        let stsd = &attributes.offsets.stsd;
+        let format = stsd.descriptions()
+            .first()
+            .map(|d| d.data_format_string())
+            .unwrap_or_default();
+
+        match format.as_str() {
+            "avc1" | "avc3" | "hvc1" | "hev1" => {
+                let hevc = format.starts_with('h');
+                let length_size = stsd.video()
+                    .and_then(|v| nal_length_size(v.extensions(), hevc))
+                    .unwrap_or(4);
+                return Self::Nal { length_size, hevc }
+            },
+            "gpmd" => return Self::Gpmf,
+            _ => ()
+        }
+
+        let mut sizes = attributes.offsets().iter().map(|o| o.size);
+        match sizes.next() {
+            Some(size) if size > 0 && sizes.all(|s| s == size) => Self::Fixed(size),
+            _ => Self::Unsupported
+        }
+    }
+
+    /// Returns `true` if samples can be recognised by their content.
+    fn is_recognisable(&self) -> bool {
+        matches!(self, Self::Nal{..} | Self::Gpmf)
+    }
+}
+
+/// Reference track.
+#[derive(Debug, Clone)]
+struct TrackModel {
+    /// Reference attributes, without sample offsets.
+    attributes: TrackAttributes,
+    model: SampleModel,
+    /// Most common sample duration in reference, unscaled.
+    duration_ticks: u32,
+    /// Largest sample size in reference.
+    max_size: u32,
+}
+
+/// Tracks recovered from an MP4 without `moov` atom.
+#[derive(Debug, Clone)]
+pub struct Recovered {
+    /// Track attributes with rebuilt sample offsets,
+    /// in the same order as in the reference.
+    pub tracks: Vec<TrackAttributes>,
+    /// Absolute byte ranges in `mdat` that could not be
+    /// attributed to any track.
+    pub unassigned: Vec<Range<u64>>,
+}
+
+/// Recovers sample offsets for an MP4 with a missing
+/// or truncated `moov` atom, using a reference file.
+#[derive(Debug, Clone)]
+pub struct Recovery {
+    tracks: Vec<TrackModel>,
+}
+
+impl Recovery {
+    /// Derives sample models for all tracks in the reference MP4.
+    pub fn new<R: Read + Seek>(reference: &mut Mp4<R>) -> Result<Self, Mp4Error> {
+        let tracks = TrackAttributes::all(reference, true)?
+            .into_iter()
+            .map(|mut attributes| {
+                let model = SampleModel::new(&attributes);
+                let offsets = std::mem::take(&mut attributes.offsets.offsets);
+                let max_size = offsets.iter().map(|o| o.size).max().unwrap_or_default();
+
+                // Most common sample duration
+                let mut counts: HashMap<u64, usize> = HashMap::new();
+                for offset in offsets.iter() {
+                    let ticks = (offset.duration.as_seconds_f64() * attributes.time_scale as f64).round() as u64;
+                    *counts.entry(ticks).or_default() += 1;
+                }
+                let duration_ticks = counts.into_iter()
+                    .max_by_key(|(ticks, count)| (*count, *ticks))
+                    .map(|(ticks, _)| ticks as u32)
+                    .unwrap_or_default();
+
+                TrackModel {
+                    attributes,
+                    model,
+                    duration_ticks,
+                    max_size,
+                }
+            })
+            .collect();
+
+        Ok(Self { tracks })
+    }
+
+    /// Derives sample models for all tracks in the reference MP4 at `path`.
+    pub fn from_path(path: &Path) -> Result<Self, Mp4Error> {
+        Self::new(&mut Mp4::new(path)?)
+    }
+
+    /// Returns track ID and sample model for each reference track.
+    pub fn models(&self) -> impl Iterator<Item = (u32, SampleModel)> + '_ {
+        self.tracks.iter()
+            .map(|t| (t.attributes.id, t.model))
+    }
+
+    /// Scans all top-level `mdat` atoms in `source`
+    /// and rebuilds sample offsets for each reference track.
+    pub fn scan<S: ByteSource + ?Sized>(&self, source: &S) -> Result<Recovered, Mp4Error> {
+        let mut scan = Scan {
+            models: &self.tracks,
+            offsets: vec![Vec::new(); self.tracks.len()],
+            chunks: vec![0; self.tracks.len()],
+            unassigned: Vec::new(),
+        };
+
+        let mdat = mdat_ranges(source)?;
+        if mdat.is_empty() {
+            return Err(Mp4Error::NoSuchAtom("mdat".to_owned()))
+        }
+        for range in mdat.into_iter() {
+            let mut buffer = ScanBuffer::new(source, range.end);
+            scan.mdat(&mut buffer, range)?;
+        }
+
+        let Scan { offsets, unassigned, .. } = scan;
+
+        let tracks = self.tracks.iter()
+            .zip(offsets)
+            .map(|(model, offsets)| {
+                let mut attributes = model.attributes.to_owned();
+                attributes.duration = (model.duration_ticks as u64).saturating_mul(offsets.len() as u64);
+                // No composition offsets for recovered samples,
+                // i.e. decode and presentation timestamps are the same
+                let mut t = Duration::ZERO;
+                attributes.offsets.offsets = offsets.into_iter()
+                    .map(|o| {
+                        let ts = t;
+                        t += o.duration;
+                        o.with_timestamps(ts, ts)
+                    })
+                    .collect();
+                attributes
+            })
+            .collect();
+
+        Ok(Recovered { tracks, unassigned })
+    }
+}
+
+/// Scan state.
+struct Scan<'a> {
+    models: &'a [TrackModel],
+    /// Recovered sample offsets for each track.
+    offsets: Vec<Vec<SampleOffset>>,
+    /// Number of chunks for each track.
+    chunks: Vec<u32>,
+    unassigned: Vec<Range<u64>>,
+}
+
+impl <'a> Scan<'a> {
+    /// Scans `range`, which must be the data load for an `mdat` atom.
+    fn mdat<S: ByteSource + ?Sized>(
+        &mut self,
+        buffer: &mut ScanBuffer<'_, S>,
+        range: Range<u64>
+    ) -> Result<(), Mp4Error> {
+        let mut pos = range.start;
+        let mut gap_start: Option<u64> = None;
+        // Track for previous sample
+        let mut previous: Option<usize> = None;
+
+        while pos < range.end {
+            match self.sample_at(buffer, pos, previous)? {
+                Some((track, size)) => {
+                    if let Some(start) = gap_start.take() {
+                        self.gap(start .. pos);
+                    }
+                    if previous != Some(track) {
+                        self.chunks[track] += 1;
+                    }
+                    let model = &self.models[track];
+                    let offset = SampleOffset::new(
+                        pos,
+                        size,
+                        model.duration_ticks,
+                        model.attributes.time_scale,
+                        true
+                    ).with_chunk(self.chunks[track] - 1);
+                    self.offsets[track].push(offset);
+                    previous = Some(track);
+                    pos += size as u64;
+                },
+                None => {
+                    gap_start.get_or_insert(pos);
+                    previous = None;
+                    pos += 1;
+                }
+            }
+        }
+
+        if let Some(start) = gap_start {
+            self.gap(start .. range.end);
+        }
+
+        Ok(())
+    }
+
+    /// Returns track index and sample size, if a sample starts at `pos`.
+    /// The track for the previous sample is tried first.
+    fn sample_at<S: ByteSource + ?Sized>(
+        &self,
+        buffer: &mut ScanBuffer<'_, S>,
+        pos: u64,
+        previous: Option<usize>
+    ) -> Result<Option<(usize, u32)>, Mp4Error> {
+        let order = previous.into_iter()
+            .chain((0 .. self.models.len()).filter(|i| Some(*i) != previous));
+
+        for track in order {
+            let model = &self.models[track];
+            let size = match model.model {
+                SampleModel::Nal { length_size, hevc } => {
+                    let nal = NalScan {
+                        length_size,
+                        hevc,
+                        max_size: model.max_size.saturating_mul(2).max(1024),
+                        // Require stronger evidence when not
+                        // directly following a sample in the same track
+                        strict: previous != Some(track),
+                    };
+                    nal.sample(buffer, pos)?
+                },
+                SampleModel::Gpmf => gpmf_sample(buffer, pos)?,
+                _ => None,
+            };
+            if let Some(size) = size {
+                return Ok(Some((track, u32::try_from(size)?)))
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Assigns data that could not be recognised to the only track
+    /// that can not be recognised by its content, if this track
+    /// has constant sample size that fits the data.
+    fn gap(&mut self, range: Range<u64>) {
+        let mut other = self.models.iter()
+            .enumerate()
+            .filter(|(_, m)| !m.model.is_recognisable());
+
+        if let (Some((track, model)), None) = (other.next(), other.next()) {
+            if let SampleModel::Fixed(size) = model.model {
+                if (range.end - range.start).is_multiple_of(size as u64) {
+                    self.chunks[track] += 1;
+                    let chunk = self.chunks[track] - 1;
+                    for pos in range.step_by(size as usize) {
+                        let offset = SampleOffset::new(
+                            pos,
+                            size,
+                            model.duration_ticks,
+                            model.attributes.time_scale,
+                            true
+                        ).with_chunk(chunk);
+                        self.offsets[track].push(offset);
+                    }
+                    return
+                }
+            }
+        }
+
+        self.unassigned.push(range);
+    }
+}
+
+/// Buffered reads over a `ByteSource` for scanning `mdat`.
+struct ScanBuffer<'a, S: ?Sized> {
+    source: &'a S,
+    /// Absolute end position for scan.
+    end: u64,
+    /// Absolute position for start of `bytes`.
+    start: u64,
+    bytes: Vec<u8>,
+}
+
+impl <'a, S: ByteSource + ?Sized> ScanBuffer<'a, S> {
+    fn new(source: &'a S, end: u64) -> Self {
+        Self {
+            source,
+            end,
+            start: 0,
+            bytes: Vec::new(),
+        }
+    }
+
+    /// Returns `len` bytes at absolute position `pos`,
+    /// or `None` if this would read past the end of the scan.
+    fn get(&mut self, pos: u64, len: usize) -> Result<Option<&[u8]>, Mp4Error> {
+        let req_end = pos + len as u64;
+        if req_end > self.end {
+            return Ok(None)
+        }
+        if pos < self.start || req_end > self.start + self.bytes.len() as u64 {
+            let read_len = (self.end - pos).min(SCAN_BLOCK_SIZE.max(len) as u64);
+            self.bytes = self.source.read_at(pos, usize::try_from(read_len)?)?;
+            self.start = pos;
+        }
+        let offset = usize::try_from(pos - self.start)?;
+        Ok(self.bytes.get(offset .. offset + len))
+    }
+
+    fn end(&self) -> u64 {
+        self.end
+    }
+}
+
+/// Length-prefixed NAL unit scan.
+struct NalScan {
+    length_size: usize,
+    hevc: bool,
+    /// Max size for a single NAL unit.
+    max_size: u32,
+    /// If set, the NAL unit following the first one
+    /// must also be valid (or end the `mdat`).
+    strict: bool,
+}
+
+/// NAL unit header properties relevant for
+/// detecting access unit (i.e. sample) boundaries.
+struct NalInfo {
+    /// Video coding layer NAL unit, i.e. a slice.
+    vcl: bool,
+    /// NAL unit starts a new access unit
+    /// if a VCL NAL unit precedes it.
+    starts_au: bool,
+}
+
+impl NalScan {
+    /// Returns size of sample starting at `pos`, if any.
+    fn sample<S: ByteSource + ?Sized>(
+        &self,
+        buffer: &mut ScanBuffer<'_, S>,
+        pos: u64
+    ) -> Result<Option<usize>, Mp4Error> {
+        let mut p = pos;
+        let mut seen_vcl = false;
+        let mut count = 0;
+
+        while let Some((info, size)) = self.nal_at(buffer, p)? {
+            if count == 0 && !info.starts_au {
+                return Ok(None)
+            }
+            if count > 0 && seen_vcl && info.starts_au {
+                break
+            }
+            seen_vcl |= info.vcl;
+            count += 1;
+            p += size as u64;
+        }
+
+        if !seen_vcl {
+            return Ok(None)
+        }
+
+        if self.strict && count == 1 && p < buffer.end() && self.nal_at(buffer, p)?.is_none() {
+            return Ok(None)
+        }
+
+        Ok(Some(usize::try_from(p - pos)?))
+    }
+
+    /// Returns header info and total size (length prefix included)
+    /// for a valid NAL unit at `pos`.
+    fn nal_at<S: ByteSource + ?Sized>(
+        &self,
+        buffer: &mut ScanBuffer<'_, S>,
+        pos: u64
+    ) -> Result<Option<(NalInfo, usize)>, Mp4Error> {
+        let Some(bytes) = buffer.get(pos, self.length_size + 3)? else {
+            return Ok(None)
+        };
+        let len = bytes[.. self.length_size].iter()
+            .fold(0_u64, |acc, b| acc << 8 | *b as u64);
+        if len < 2 || len > self.max_size as u64 {
+            return Ok(None)
+        }
+        let header = &bytes[self.length_size ..];
+        let info = match self.hevc {
+            true => hevc_nal(header),
+            false => avc_nal(header),
+        };
+        let size = self.length_size + len as usize;
+        if pos + size as u64 > buffer.end() {
+            return Ok(None)
+        }
+
+        Ok(info.map(|i| (i, size)))
+    }
+}
+
+/// H.264 NAL unit header.
+fn avc_nal(header: &[u8]) -> Option<NalInfo> {
+    if header[0] & 0x80 != 0 {
+        return None
+    }
+    let ref_idc = (header[0] >> 5) & 0x03;
+    let first_mb_zero = header[1] & 0x80 != 0;
+    let (vcl, starts_au) = match header[0] & 0x1F {
+        // IDR slice
+        5 if ref_idc != 0 => (true, first_mb_zero),
+        // Non-IDR slice
+        1 => (true, first_mb_zero),
+        // Slice data partitions
+        2 ..= 4 => (true, false),
+        // SEI, access unit delimiter
+        6 | 9 if ref_idc == 0 => (false, true),
+        // SPS, PPS
+        7 | 8 if ref_idc != 0 => (false, true),
+        // End of sequence/stream, filler, SPS extension
+        10 ..= 13 => (false, false),
+        // Prefix NAL, subset SPS, reserved
+        14 ..= 18 => (false, true),
+        // Auxiliary slice, slice extension
+        19 | 20 => (false, false),
+        _ => return None
+    };
+    Some(NalInfo { vcl, starts_au })
+}
+
+/// H.265 NAL unit header.
+fn hevc_nal(header: &[u8]) -> Option<NalInfo> {
+    if header[0] & 0x80 != 0 || header[1] & 0x07 == 0 {
+        return None
+    }
+    let first_slice = header[2] & 0x80 != 0;
+    let (vcl, starts_au) = match (header[0] >> 1) & 0x3F {
+        // Slices
+        0 ..= 9 | 16 ..= 21 => (true, first_slice),
+        // VPS, SPS, PPS, access unit delimiter, prefix SEI
+        32 ..= 35 | 39 => (false, true),
+        // End of sequence/bitstream, filler, suffix SEI
+        36 ..= 38 | 40 => (false, false),
+        _ => return None
+    };
+    Some(NalInfo { vcl, starts_au })
+}
+
+/// Returns size of GPMF sample starting at `pos`, if any,
+/// i.e. one or more consecutive top-level `DEVC` KLV entries.
+fn gpmf_sample<S: ByteSource + ?Sized>(
+    buffer: &mut ScanBuffer<'_, S>,
+    pos: u64
+) -> Result<Option<usize>, Mp4Error> {
+    let mut p = pos;
+    // 8 byte KLV header: FourCC, value type, struct size, repeat (u16)
+    while let Some(header) = buffer.get(p, 8)? {
+        if &header[0..4] != b"DEVC" || header[4] != 0 {
+            break
+        }
+        let len = header[5] as u64 * u16::from_be_bytes([header[6], header[7]]) as u64;
+        // KLV values are padded to 32-bit alignment
+        let size = 8 + len.div_ceil(4) * 4;
+        if p + size > buffer.end() {
+            break
+        }
+        p += size;
+    }
+
+    match p > pos {
+        true => Ok(Some(usize::try_from(p - pos)?)),
+        false => Ok(None),
+    }
+}
+
+/// Returns NAL length size in bytes from `avcC`/`hvcC`
+/// in video sample description extensions.
+fn nal_length_size(extensions: &[u8], hevc: bool) -> Option<usize> {
+    let (name, index) = match hevc {
+        true => (b"hvcC", 21),
+        false => (b"avcC", 4),
+    };
+    let mut pos = 0;
+    while pos + 8 <= extensions.len() {
+        let size = u32::from_be_bytes(extensions[pos .. pos + 4].try_into().ok()?) as usize;
+        if size < 8 {
+            return None
+        }
+        if &extensions[pos + 4 .. pos + 8] == name {
+            return extensions.get(pos + 8 + index)
+                .map(|b| (b & 0x03) as usize + 1)
+        }
+        pos += size;
+    }
+    None
+}
+
+/// Returns data load byte ranges for all top-level `mdat` atoms.
+/// An `mdat` atom with size 0, or that extends past the end of the file,
+/// is assumed to extend to the end of the file.
+fn mdat_ranges<S: ByteSource + ?Sized>(source: &S) -> Result<Vec<Range<u64>>, Mp4Error> {
+    let len = source.len();
+    let mut ranges: Vec<Range<u64>> = Vec::new();
+    let mut pos = 0;
+
+    while pos + 8 <= len {
+        let header = source.read_at(pos, (len - pos).min(16) as usize)?;
+        let size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+        let name = FourCC::from_slice(&header[4..8]);
+        let (header_size, atom_size) = match size {
+            0 => (8, len - pos),
+            1 if header.len() == 16 => (16, u64::from_be_bytes(header[8..16].try_into().unwrap_or_default())),
+            _ => (8, size),
+        };
+
+        let is_mdat = name.to_str() == "mdat";
+        if atom_size < header_size || pos + atom_size > len {
+            // Truncated atom, only usable if mdat
+            if is_mdat {
+                ranges.push(pos + header_size .. len);
+            }
+            break
+        }
+        if is_mdat {
+            ranges.push(pos + header_size .. pos + atom_size);
+        }
+        pos += atom_size;
+    }
+
+    Ok(ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{atom, mp4, MemorySource, TestTrack};
+
+    use super::*;
+
+    /// Recovery with a reference track for each model.
+    fn recovery(models: &[SampleModel]) -> Recovery {
+        let tracks: Vec<TestTrack> = (1 ..= models.len() as u32)
+            .map(|id| TestTrack::new(id, "Test", vec![vec![vec![0; 4]]]))
+            .collect();
+        let bytes = mp4(&tracks);
+        let attributes = TrackAttributes::all(&mut Mp4::from_slice(&bytes).unwrap(), true).unwrap();
+        Recovery {
+            tracks: attributes.into_iter()
+                .zip(models)
+                .map(|(attributes, model)| TrackModel {
+                    attributes,
+                    model: *model,
+                    duration_ticks: 100,
+                    max_size: 8 << 20,
+                })
+                .collect()
+        }
+    }
+
+    /// NAL unit with 4 byte length prefix, and `len` bytes
+    /// starting with `header`.
+    fn nal(header: &[u8], len: usize) -> Vec<u8> {
+        let mut bytes = (len as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(header);
+        bytes.resize(4 + len, 0);
+        bytes
+    }
+
+    /// Recovered samples for track at `index` as `(POSITION, SIZE)`.
+    fn samples(recovered: &Recovered, index: usize) -> Vec<(u64, u32)> {
+        recovered.tracks[index].offsets().iter()
+            .map(|o| (o.position, o.size))
+            .collect()
+    }
+
+    /// Scans `mdat` with data load `data`, i.e. with
+    /// data starting at absolute position 8.
+    fn scan(models: &[SampleModel], data: &[u8]) -> Recovered {
+        recovery(models).scan(&MemorySource(atom(b"mdat", data))).unwrap()
+    }
+
+    const AVC: SampleModel = SampleModel::Nal { length_size: 4, hevc: false };
+    const HEVC: SampleModel = SampleModel::Nal { length_size: 4, hevc: true };
+
+    #[test]
+    fn avc() {
+        // SPS, PPS, IDR slice, then two non-IDR slices
+        let key = [nal(&[0x67, 0x42], 8), nal(&[0x68, 0xCE], 4), nal(&[0x65, 0x88], 40)].concat();
+        let p1 = nal(&[0x41, 0x9A], 20);
+        let p2 = [nal(&[0x41, 0x9A], 10), nal(&[0x01, 0x00], 6)].concat();
+        let recovered = scan(&[AVC], &[key.as_slice(), &p1, &p2].concat());
+
+        assert_eq!(samples(&recovered, 0), vec![
+            (8, key.len() as u32),
+            (8 + key.len() as u64, p1.len() as u32),
+            (8 + (key.len() + p1.len()) as u64, p2.len() as u32),
+        ]);
+        assert!(recovered.unassigned.is_empty());
+        let offsets = recovered.tracks[0].offsets();
+        assert_eq!(offsets.iter().map(|o| o.chunk).collect::<Vec<_>>(), vec![0, 0, 0]);
+        assert_eq!(offsets[2].pts, Duration::milliseconds(200));
+    }
+
+    #[test]
+    fn hevc() {
+        // VPS, SPS, PPS, IDR slice, then trailing slice
+        let key = [
+            nal(&[0x40, 0x01], 12),
+            nal(&[0x42, 0x01], 12),
+            nal(&[0x44, 0x01], 6),
+            nal(&[0x26, 0x01, 0x80], 50),
+        ].concat();
+        let p1 = nal(&[0x02, 0x01, 0x80], 30);
+        let recovered = scan(&[HEVC], &[key.as_slice(), &p1].concat());
+
+        assert_eq!(samples(&recovered, 0), vec![
+            (8, key.len() as u32),
+            (8 + key.len() as u64, p1.len() as u32),
+        ]);
+        assert!(recovered.unassigned.is_empty());
+
+        // H.264 model does not match H.265 data
+        let recovered = scan(&[AVC], &[key.as_slice(), &p1].concat());
+        assert!(samples(&recovered, 0).is_empty());
+        assert_eq!(recovered.unassigned, vec![8 .. 8 + (key.len() + p1.len()) as u64]);
+    }
+
+    #[test]
+    fn nal_across_block_boundary() {
+        // Second sample's NAL header straddles the end of the first scan block,
+        // which starts at the mdat data load (absolute position 8).
+        let sps = nal(&[0x67, 0x42], 8);
+        let first = [sps.as_slice(), &nal(&[0x65, 0x88], SCAN_BLOCK_SIZE - sps.len() - 4 - 3)].concat();
+        assert_eq!(first.len(), SCAN_BLOCK_SIZE - 3);
+        let second = nal(&[0x41, 0x9A], 100);
+        let third = nal(&[0x41, 0x9A], 10);
+        let recovered = scan(&[AVC], &[first.as_slice(), &second, &third].concat());
+
+        let start = 8 + first.len() as u64;
+        assert_eq!(samples(&recovered, 0), vec![
+            (8, first.len() as u32),
+            (start, second.len() as u32),
+            (start + second.len() as u64, third.len() as u32),
+        ]);
+        assert!(recovered.unassigned.is_empty());
+    }
+
+    #[test]
+    fn truncated_final_sample() {
+        let key = [nal(&[0x67, 0x42], 8), nal(&[0x65, 0x88], 40)].concat();
+        let p1 = nal(&[0x41, 0x9A], 20);
+        let end = 8 + (key.len() + p1.len()) as u64;
+
+        // Length prefix exceeds the remaining data
+        let truncated = nal(&[0x41, 0x9A], 100)[.. 30].to_vec();
+        let recovered = scan(&[AVC], &[key.as_slice(), &p1, &truncated].concat());
+        assert_eq!(samples(&recovered, 0).len(), 2);
+        assert_eq!(recovered.unassigned, vec![end .. end + truncated.len() as u64]);
+
+        // Too short for a NAL unit header
+        let recovered = scan(&[AVC], &[key.as_slice(), &p1, &[0, 0, 0]].concat());
+        assert_eq!(samples(&recovered, 0).len(), 2);
+        assert_eq!(recovered.unassigned, vec![end .. end + 3]);
+
+        // mdat atom size exceeds file size
+        let mut bytes = atom(b"mdat", &[key.as_slice(), &p1].concat());
+        bytes[0 .. 4].copy_from_slice(&1000_u32.to_be_bytes());
+        let recovered = recovery(&[AVC]).scan(&MemorySource(bytes)).unwrap();
+        assert_eq!(samples(&recovered, 0).len(), 2);
+        assert!(recovered.unassigned.is_empty());
+    }
+
+    #[test]
+    fn unsupported() {
+        let data: Vec<u8> = (0 .. 64).collect();
+        let recovered = scan(&[SampleModel::Unsupported], &data);
+        assert!(samples(&recovered, 0).is_empty());
+        assert_eq!(recovered.unassigned, vec![8 .. 72]);
+
+        // No mdat
+        let source = MemorySource(atom(b"free", &data));
+        assert!(matches!(recovery(&[AVC]).scan(&source), Err(Mp4Error::NoSuchAtom(_))));
+    }
+
+    #[test]
+    fn gaps_assigned_to_fixed_size_track() {
+        let key = [nal(&[0x67, 0x42], 8), nal(&[0x65, 0x88], 40)].concat();
+        let audio = [7; 32];
+        let data = [key.as_slice(), &audio, &key, &audio[.. 30]].concat();
+        let recovered = scan(&[AVC, SampleModel::Fixed(16)], &data);
+
+        let key_len = key.len() as u64;
+        assert_eq!(samples(&recovered, 0), vec![(8, key.len() as u32), (8 + key_len + 32, key.len() as u32)]);
+        assert_eq!(samples(&recovered, 1), vec![(8 + key_len, 16), (8 + key_len + 16, 16)]);
+        // Not a multiple of the sample size
+        assert_eq!(recovered.unassigned, vec![8 + 2 * key_len + 32 .. 8 + data.len() as u64]);
+    }
+
+    #[test]
+    fn gpmf() {
+        // DEVC with 5 byte value, padded to 8 bytes
+        let devc = [b"DEVC".as_slice(), &[0, 1, 0, 5], &[1; 8]].concat();
+        let key = [nal(&[0x67, 0x42], 8), nal(&[0x65, 0x88], 40)].concat();
+        let data = [key.as_slice(), &devc, &devc, &key, &devc[.. 12]].concat();
+        let recovered = scan(&[AVC, SampleModel::Gpmf], &data);
+
+        assert_eq!(samples(&recovered, 1), vec![(8 + key.len() as u64, 32)]);
+        assert_eq!(samples(&recovered, 0).len(), 2);
+        assert_eq!(recovered.tracks[0].offsets()[1].chunk, 1);
+        // Truncated DEVC
+        assert_eq!(recovered.unassigned.len(), 1);
+    }
+
+    #[test]
+    fn noise() {
+        // Pseudo-random data must be fully accounted for,
+        // as samples or unassigned ranges
+        let mut x = 0x2545_F491_u32;
+        let data: Vec<u8> = (0 .. 1 << 16)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                (x >> 24) as u8
+            })
+            .collect();
+
+        for length_size in [1, 2, 4] {
+            let models = [
+                SampleModel::Nal { length_size, hevc: false },
+                SampleModel::Nal { length_size, hevc: true },
+                SampleModel::Gpmf,
+            ];
+            let recovered = scan(&models, &data);
+            let mut ranges: Vec<Range<u64>> = recovered.tracks.iter()
+                .flat_map(|t| t.offsets().iter().map(|o| o.position .. o.position + o.size as u64))
+                .chain(recovered.unassigned.iter().cloned())
+                .collect();
+            ranges.sort_by_key(|r| r.start);
+            assert_eq!(ranges.first().map(|r| r.start), Some(8));
+            assert!(ranges.windows(2).all(|w| w[0].end == w[1].start));
+            assert_eq!(ranges.last().map(|r| r.end), Some(8 + data.len() as u64));
+        }
+    }
+
+    #[test]
+    fn nal_length_size_from_extensions() {
+        let avcc = atom(b"avcC", &[1, 0x64, 0, 0x1F, 0xFD, 0xE1]);
+        let extensions = [atom(b"colr", &[0; 10]), avcc].concat();
+        assert_eq!(nal_length_size(&extensions, false), Some(2));
+        assert_eq!(nal_length_size(&extensions, true), None);
+        // Corrupt atom size
+        assert_eq!(nal_length_size(&[0, 0, 0, 4, b'a', b'v', b'c', b'C'], false), None);
+    }
+}
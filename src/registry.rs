@@ -0,0 +1,249 @@
+//! Registry for container atoms and atom parsers.
+//!
+//! By default, only the atoms in `CONTAINER` are treated as containers,
+//! and only built-in atom types can be parsed. `AtomRegistry` lets users
+//! mark other FourCCs as containers, e.g. `meta`, `ilst`, `moof`,
+//! or vendor containers, and register parsers keyed by FourCC,
+//! e.g. for vendor atoms. Registered atoms are then returned as typed
+//! values via `Atom::parse()` and `Mp4::select_parsed()`.
+//!
+//! Each `Mp4` has its own registry (see `Mp4::registry_mut()`),
+//! which includes parsers for the built-in atom types
+//! that do not depend on other atoms.
+//!
+//! ```rs
+//! use binrw::BinRead;
+//! use mp4iter::Mp4;
+//! use std::path::Path;
+//!
+//! #[derive(Debug, BinRead)]
+//! #[br(big)]
+//! struct VendorAtom {
+//!     version: u8,
+//!     value: u32,
+//! }
+//!
+//! fn main() -> Result<(), mp4iter::Mp4Error> {
+//!     let mut mp4 = Mp4::new(Path::new("VIDEO.MP4"))?;
+//!     mp4.registry_mut()
+//!         .add_container("meta")
+//!         .add_container("ilst")
+//!         .register::<VendorAtom>("XYZV");
+//!
+//!     for (header, parsed) in mp4.select_parsed("//XYZV")? {
+//!         let vendor = parsed.downcast::<VendorAtom>().unwrap();
+//!         println!("{} {vendor:?}", header.offset());
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::{any::Any, collections::{HashMap, HashSet}, fmt, io::Cursor, sync::Arc};
+
+use binrw::{BinRead, BinReaderExt};
+
+use crate::{
+    atom_types::Stsc, Co64, Cslg, Ctts, Dref, Elst, FourCC, Ftyp, Mdhd, Meta, Mp4Error, Mvhd, Sdtp, Smhd,
+    Stco, Stsd, Stss, Stsz, Stts, Tkhd, Tmcd, Tref, Vmhd, CONTAINER
+};
+
+/// Typed value returned by a registered parser.
+/// Use `downcast()` to get the concrete type.
+pub type ParsedAtom = Box<dyn Any + Send + Sync>;
+
+/// Parser for an atom's data load.
+type Parser = Arc<dyn Fn(&[u8]) -> Result<ParsedAtom, Mp4Error> + Send + Sync>;
+
+/// Container atoms and atom parsers keyed by FourCC.
+#[derive(Clone)]
+pub struct AtomRegistry {
+    /// Containers in addition to `CONTAINER`.
+    containers: HashSet<FourCC>,
+    parsers: HashMap<FourCC, Parser>,
+}
+
+impl fmt::Debug for AtomRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomRegistry")
+            .field("containers", &self.containers)
+            .field("parsers", &self.parsers.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Default for AtomRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AtomRegistry {
+    /// New registry with parsers for built-in atom types.
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        registry
+            .register::<Stts>("stts")
+            .register::<Stsz>("stsz")
+            .register::<Stco>("stco")
+            .register::<Co64>("co64")
+            .register::<Stsc>("stsc")
+            .register::<Stss>("stss")
+            .register::<Ctts>("ctts")
+            .register::<Cslg>("cslg")
+            .register::<Stsd>("stsd")
+            .register::<Dref>("dref")
+            .register::<Elst>("elst")
+            .register::<Smhd>("smhd")
+            .register::<Vmhd>("vmhd")
+            .register::<Mvhd>("mvhd")
+            .register::<Tkhd>("tkhd")
+            .register::<Mdhd>("mdhd")
+            .register::<Tmcd>("tmcd")
+            .register::<Tref>("tref")
+            .register_fn("ftyp", |data| {
+                Ok(Cursor::new(data).read_ne_args::<Ftyp>(binrw::args! {data_size: u32::try_from(data.len())?})?)
+            })
+            .register_fn("sdtp", |data| {
+                Ok(Cursor::new(data).read_ne_args::<Sdtp>(binrw::args! {data_size: u32::try_from(data.len())?})?)
+            })
+            .register_fn("meta", Meta::new);
+        registry
+    }
+
+    /// New registry without any parsers.
+    /// Containers in `CONTAINER` are always included.
+    pub fn empty() -> Self {
+        Self {
+            containers: HashSet::new(),
+            parsers: HashMap::new(),
+        }
+    }
+
+    /// Treat atoms with FourCC `fourcc` as containers,
+    /// i.e. the data load consists of child atoms.
+    ///
+    /// For `meta`, child atoms are assumed to follow
+    /// 4 bytes for version and flags (ISO, as opposed to QuickTime)
+    /// if these are all zero.
+    pub fn add_container(&mut self, fourcc: &str) -> &mut Self {
+        self.containers.insert(FourCC::from_str(fourcc));
+        self
+    }
+
+    /// Returns `true` if atoms with FourCC `fourcc` are containers,
+    /// i.e. listed in `CONTAINER`, or added via `AtomRegistry::add_container()`.
+    pub fn is_container(&self, fourcc: &FourCC) -> bool {
+        CONTAINER.contains(&fourcc.to_str()) || self.containers.contains(fourcc)
+    }
+
+    /// Register `T` as parser for atoms with FourCC `fourcc`.
+    /// The atom's data load, i.e. excluding the header,
+    /// is parsed as native endian `T`.
+    /// Replaces any existing parser for `fourcc`.
+    pub fn register<T>(&mut self, fourcc: &str) -> &mut Self
+    where
+        T: BinRead + Any + Send + Sync,
+        for<'a> <T as BinRead>::Args<'a>: Default,
+    {
+        self.register_fn(fourcc, |data| Ok(Cursor::new(data).read_ne::<T>()?))
+    }
+
+    /// Register function `parser` for atoms with FourCC `fourcc`.
+    /// `parser` receives the atom's data load, i.e. excluding the header.
+    /// Replaces any existing parser for `fourcc`.
+    pub fn register_fn<T, F>(&mut self, fourcc: &str, parser: F) -> &mut Self
+    where
+        T: Any + Send + Sync,
+        F: Fn(&[u8]) -> Result<T, Mp4Error> + Send + Sync + 'static,
+    {
+        let parser: Parser = Arc::new(move |data| parser(data).map(|t| Box::new(t) as ParsedAtom));
+        self.parsers.insert(FourCC::from_str(fourcc), parser);
+        self
+    }
+
+    /// Returns `true` if there is a parser for FourCC `fourcc`.
+    pub fn has_parser(&self, fourcc: &FourCC) -> bool {
+        self.parsers.contains_key(fourcc)
+    }
+
+    /// Parses `data`, the data load for an atom with FourCC `fourcc`.
+    /// Returns `None` if there is no parser for `fourcc`.
+    pub fn parse(&self, fourcc: &FourCC, data: &[u8]) -> Option<Result<ParsedAtom, Mp4Error>> {
+        self.parsers.get(fourcc)
+            .map(|parser| parser(data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::{testing::{atom, container, full_atom, mp4, TestTrack}, Mp4};
+
+    use super::*;
+
+    /// MP4 with custom atom `XYZV` in an ISO `meta` (version and flags
+    /// precede child atoms), and in custom container `XYZC`.
+    fn custom() -> Vec<u8> {
+        let value = atom(b"XYZV", &0xDEAD_BEEF_u32.to_be_bytes());
+        let udta = container(b"udta", &[
+            full_atom(b"meta", 0, &value),
+            container(b"XYZC", &[value.to_owned()]),
+        ]);
+        let mut track = TestTrack::new(1, "One", vec![vec![vec![1; 4]]]);
+        track.atoms.push(udta);
+        mp4(&[track])
+    }
+
+    fn paths(mp4: &mut Mp4<impl std::io::Read + std::io::Seek>) -> Vec<String> {
+        mp4.walk()
+            .map(|r| r.map(|(_, path, _)| path))
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn custom_container_and_parser() {
+        let bytes = custom();
+        let mut mp4 = Mp4::from_slice(&bytes).unwrap();
+
+        // Not containers by default
+        assert!(!paths(&mut mp4).iter().any(|p| p.ends_with("XYZV")));
+        assert!(mp4.select_parsed("//XYZV").unwrap().is_empty());
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        mp4.registry_mut()
+            .add_container("meta")
+            .add_container("XYZC")
+            .register_fn("XYZV", move |data| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(Cursor::new(data).read_be::<u32>()?)
+            });
+        assert!(mp4.registry().is_container(&FourCC::from_str("XYZC")));
+        assert!(mp4.registry().has_parser(&FourCC::from_str("XYZV")));
+
+        let paths = paths(&mut mp4);
+        assert!(paths.contains(&"moov/trak/udta/meta/XYZV".to_owned()));
+        assert!(paths.contains(&"moov/trak/udta/XYZC/XYZV".to_owned()));
+
+        let parsed = mp4.select_parsed("//XYZV").unwrap();
+        assert_eq!(parsed.len(), 2);
+        for (header, value) in parsed {
+            assert_eq!(header.name().to_str(), "XYZV");
+            assert_eq!(*value.downcast::<u32>().unwrap(), 0xDEAD_BEEF);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn builtin_parsers() {
+        let registry = AtomRegistry::new();
+        let stco = [0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 8];
+        let parsed = registry.parse(&FourCC::from_str("stco"), &stco).unwrap().unwrap();
+        assert_eq!(parsed.downcast::<Stco>().unwrap().offsets(), vec![8]);
+
+        assert!(AtomRegistry::empty().parse(&FourCC::from_str("stco"), &stco).is_none());
+        assert!(AtomRegistry::empty().is_container(&FourCC::from_str("moov")));
+    }
+}
@@ -0,0 +1,562 @@
+//! Virtual concatenation of split recordings.
+//!
+//! Cameras such as GoPro and DJI, as well as many dashcams, split long recordings
+//! into chapter files (e.g. `GX010042.MP4`, `GX020042.MP4`, ...).
+//! `Mp4Sequence` exposes each track across all chapters as a single,
+//! continuous `Track`, with sample timestamps running on from the previous chapter.
+//!
+//! Chapter files are read as if concatenated into a single file
+//! (see `ChapterReader`), so that `SampleOffset::position` is relative
+//! to the start of the first chapter. `SampleOffset::file` is the index
+//! for the chapter file that contains the sample.
+//!
+//! ```rs
+//! use mp4iter::Mp4Sequence;
+//! use std::path::Path;
+//!
+//! fn main() -> Result<(), mp4iter::Mp4Error> {
+//!     // Finds GX020042.MP4, GX030042.MP4 etc if these exist
+//!     let mut sequence = Mp4Sequence::from_path(Path::new("GX010042.MP4"))?;
+//!     let mut track = sequence.track("GoPro MET")?;
+//!     for sample in track.samples() {
+//!         println!("{:?}", sample?.relative());
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use time::Duration;
+
+use crate::{
+    support::seek_pos,
+    track::{ParsableTrackId, TrackAttributes},
+    Mp4,
+    Mp4Error,
+    SampleOffset,
+    Track,
+};
+
+/// Max time difference between the end of a chapter
+/// and the start of the next chapter, when verifying
+/// chapter files that are only numbered consecutively (DJI).
+const MAX_CHAPTER_GAP_SECONDS: i64 = 3;
+
+/// Reader over chapter files, as if these were concatenated
+/// into a single file.
+#[derive(Debug)]
+pub struct ChapterReader {
+    files: Vec<File>,
+    /// Absolute start position for each file.
+    starts: Vec<u64>,
+    /// Total size in bytes.
+    len: u64,
+    /// Current position.
+    pos: u64,
+}
+
+impl ChapterReader {
+    pub fn open(paths: &[PathBuf]) -> Result<Self, Mp4Error> {
+        let mut files: Vec<File> = Vec::new();
+        let mut starts: Vec<u64> = Vec::new();
+        let mut len = 0;
+        for path in paths.iter() {
+            let file = File::open(path)?;
+            starts.push(len);
+            len += file.metadata()?.len();
+            files.push(file);
+        }
+
+        Ok(Self {
+            files,
+            starts,
+            len,
+            pos: 0,
+        })
+    }
+
+    /// Absolute start position for each chapter file.
+    pub fn starts(&self) -> &[u64] {
+        &self.starts
+    }
+
+    /// Total size in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if all chapter files are empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Read for ChapterReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+        // Index for file that contains current position
+        let index = self.starts.partition_point(|s| *s <= self.pos) - 1;
+        let end = self.starts.get(index + 1).copied().unwrap_or(self.len);
+
+        // Only read up to the end of the current file
+        let n = (buf.len() as u64).min(end - self.pos) as usize;
+        let file = &mut self.files[index];
+        file.seek(SeekFrom::Start(self.pos - self.starts[index]))?;
+        let n = file.read(&mut buf[..n])?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for ChapterReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = seek_pos(self.pos, self.len, pos)?;
+        Ok(self.pos)
+    }
+}
+
+/// Ordered sequence of MP4 chapter files from a single recording.
+#[derive(Debug)]
+pub struct Mp4Sequence {
+    paths: Vec<PathBuf>,
+    /// One `Mp4` for each chapter, for reading track attributes.
+    chapters: Vec<Mp4>,
+    /// All chapters concatenated, for reading sample data.
+    mp4: Mp4<ChapterReader>,
+    /// Absolute start position for each chapter.
+    starts: Vec<u64>,
+}
+
+impl Mp4Sequence {
+    /// New `Mp4Sequence` from chapter files in recording order.
+    pub fn new(paths: &[PathBuf]) -> Result<Self, Mp4Error> {
+        if paths.is_empty() {
+            return Err(Mp4Error::EmptySequence)
+        }
+
+        let chapters = paths.iter()
+            .map(|p| Mp4::new(p))
+            .collect::<Result<Vec<_>, _>>()?;
+        let reader = ChapterReader::open(paths)?;
+        let starts = reader.starts().to_owned();
+
+        Ok(Self {
+            paths: paths.to_owned(),
+            chapters,
+            // Only atom headers in the first chapter's
+            // moov are read, since track attributes are
+            // instead read via each chapter
+            mp4: Mp4::from_reader_lazy(reader)?,
+            starts,
+        })
+    }
+
+    /// New `Mp4Sequence` from the chapter at `path`
+    /// and any sibling chapters detected via file name
+    /// (see `Mp4Sequence::detect()`).
+    pub fn from_path(path: &Path) -> Result<Self, Mp4Error> {
+        Self::new(&Self::detect(path)?)
+    }
+
+    /// Returns paths for all chapters in the same recording as `path`,
+    /// in recording order, by matching file name conventions:
+    /// - GoPro HERO5 and later: `GXccnnnn.MP4`, `GHccnnnn.MP4`,
+    ///   where `cc` is the chapter and `nnnn` the file number.
+    /// - GoPro HERO4 and earlier: `GOPRnnnn.MP4` for the first chapter,
+    ///   then `GPccnnnn.MP4`.
+    /// - DJI: `DJI_nnnn.MP4` or `DJI_YYYYMMDDhhmmss_nnnn_X.MP4`
+    ///   with consecutive file numbers. Since these may also be separate
+    ///   recordings, a file is only included if its creation time
+    ///   matches the end of the preceding chapter.
+    ///
+    /// Returns only `path` if no convention matches.
+    pub fn detect(path: &Path) -> Result<Vec<PathBuf>, Mp4Error> {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let (Some(stem), Some(ext)) = (
+            path.file_stem().and_then(|s| s.to_str()),
+            path.extension().and_then(|s| s.to_str()),
+        ) else {
+            return Ok(vec![path.to_owned()])
+        };
+        let file = |name: String| dir.join(format!("{name}.{ext}"));
+
+        // GoPro
+        if let Some(stems) = gopro_chapters(stem) {
+            let paths = stems.into_iter()
+                .map(file)
+                .take_while(|p| p.exists())
+                .collect::<Vec<_>>();
+            return Ok(non_empty(paths, path))
+        }
+
+        // DJI
+        if let Some(dji) = DjiName::new(stem) {
+            return dji.chapters(path, dir, ext)
+        }
+
+        Ok(vec![path.to_owned()])
+    }
+
+    /// Chapter paths in recording order.
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Number of chapters.
+    pub fn len(&self) -> usize {
+        self.chapters.len()
+    }
+
+    /// Returns `true` if there are no chapters.
+    pub fn is_empty(&self) -> bool {
+        self.chapters.is_empty()
+    }
+
+    /// Returns the chapter at `index`.
+    pub fn chapter(&mut self, index: usize) -> Option<&mut Mp4> {
+        self.chapters.get_mut(index)
+    }
+
+    /// Returns chapter path and the position within that chapter file
+    /// for a sample in a track returned by `Mp4Sequence::track()`.
+    pub fn chapter_position(&self, offset: &SampleOffset) -> Option<(&Path, u64)> {
+        let path = self.paths.get(offset.file)?;
+        let start = self.starts.get(offset.file)?;
+        Some((path, offset.position.checked_sub(*start)?))
+    }
+
+    /// Summed duration for all chapters.
+    pub fn duration(&mut self) -> Result<Duration, Mp4Error> {
+        self.chapters.iter_mut()
+            .map(|c| c.duration(true))
+            .sum()
+    }
+
+    /// Returns attributes for the track matching `identifier`,
+    /// with samples for all chapters.
+    pub fn track_attributes(&mut self, identifier: impl ParsableTrackId) -> Result<TrackAttributes, Mp4Error> {
+        let identifier = identifier.to_trackid();
+        let parts = self.chapters.iter_mut()
+            .map(|c| TrackAttributes::new(c, identifier, true))
+            .collect::<Result<Vec<_>, _>>()?;
+        merge(parts, &self.starts)
+    }
+
+    /// Returns attributes for all tracks,
+    /// with samples for all chapters.
+    ///
+    /// Tracks are matched by order, which
+    /// should be the same for all chapters.
+    pub fn track_list(&mut self) -> Result<Vec<TrackAttributes>, Mp4Error> {
+        let mut lists = self.chapters.iter_mut()
+            .map(|c| TrackAttributes::all(c, true))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let count = lists.first().map(|l| l.len()).unwrap_or_default();
+        if lists.iter().any(|l| l.len() != count) {
+            return Err(Mp4Error::SequenceMismatch)
+        }
+
+        (0 .. count)
+            .map(|_| {
+                let parts = lists.iter_mut()
+                    .map(|l| l.remove(0))
+                    .collect();
+                merge(parts, &self.starts)
+            })
+            .collect()
+    }
+
+    /// Returns the track with specified identifier (see `Mp4::track()`)
+    /// as a single track spanning all chapters.
+    pub fn track(&mut self, identifier: impl ParsableTrackId) -> Result<Track<'_, ChapterReader>, Mp4Error> {
+        let attributes = self.track_attributes(identifier)?;
+        Track::from_attributes(&mut self.mp4, attributes)
+    }
+}
+
+/// Merges track attributes for each chapter (in order)
+/// into a single track, with sample positions relative
+/// to the start of the first chapter. `starts` is the
+/// absolute start position for each chapter.
+fn merge(parts: Vec<TrackAttributes>, starts: &[u64]) -> Result<TrackAttributes, Mp4Error> {
+    let mut parts = parts.into_iter().enumerate();
+    let (_, mut attributes) = parts.next().ok_or(Mp4Error::EmptySequence)?;
+
+    let mut chunks = next_chunk(attributes.offsets());
+    for (file, part) in parts {
+        if part.id != attributes.id || part.sub_type != attributes.sub_type {
+            return Err(Mp4Error::SequenceMismatch)
+        }
+        let start = starts[file];
+        // Timestamps run on from the previous chapter
+        let elapsed: Duration = attributes.offsets().iter()
+            .map(|o| o.duration)
+            .sum();
+        let offsets = part.offsets().iter()
+            .map(|o| SampleOffset {
+                position: o.position + start,
+                chunk: o.chunk + chunks,
+                file,
+                dts: o.dts + elapsed,
+                pts: o.pts + elapsed,
+                ..*o
+            });
+        attributes.offsets.offsets.extend(offsets);
+        attributes.duration = attributes.duration.saturating_add(part.duration);
+        chunks = next_chunk(attributes.offsets());
+    }
+
+    Ok(attributes)
+}
+
+/// Returns file stems for all possible chapters of a GoPro recording,
+/// in recording order, if `stem` matches a GoPro file name convention
+/// (see `Mp4Sequence::detect()`).
+fn gopro_chapters(stem: &str) -> Option<Vec<String>> {
+    let is_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    if stem.len() != 8 {
+        return None
+    }
+
+    // HERO5 and later
+    if (stem.starts_with("GX") || stem.starts_with("GH")) && is_digits(&stem[2..]) {
+        let (prefix, number) = (&stem[..2], &stem[4..]);
+        return Some((1 ..= 99)
+            .map(|c| format!("{prefix}{c:02}{number}"))
+            .collect())
+    }
+
+    // HERO4 and earlier
+    let number = match stem {
+        s if s.starts_with("GOPR") && is_digits(&s[4..]) => &s[4..],
+        s if s.starts_with("GP") && is_digits(&s[2..]) => &s[4..],
+        _ => return None
+    };
+    Some(std::iter::once(format!("GOPR{number}"))
+        .chain((1 ..= 99).map(|c| format!("GP{c:02}{number}")))
+        .collect())
+}
+
+/// Returns index for the chunk following the last one in `offsets`.
+fn next_chunk(offsets: &[SampleOffset]) -> u32 {
+    offsets.last()
+        .map(|o| o.chunk + 1)
+        .unwrap_or_default()
+}
+
+/// Returns `paths`, or `path` if `paths` is empty.
+fn non_empty(paths: Vec<PathBuf>, path: &Path) -> Vec<PathBuf> {
+    match paths.is_empty() {
+        true => vec![path.to_owned()],
+        false => paths,
+    }
+}
+
+/// DJI file name, `DJI_nnnn` or `DJI_YYYYMMDDhhmmss_nnnn_X`.
+struct DjiName<'a> {
+    /// File number.
+    number: u32,
+    /// Number of digits in file number.
+    digits: usize,
+    /// Suffix following the file number, e.g. `_D`.
+    suffix: &'a str,
+}
+
+impl <'a> DjiName<'a> {
+    fn new(stem: &'a str) -> Option<Self> {
+        let rest = stem.strip_prefix("DJI_")?;
+        let parts: Vec<&str> = rest.split('_').collect();
+        let (number, suffix) = match parts.as_slice() {
+            [n] => (*n, ""),
+            [_, n, _] => (*n, &stem[4 + parts[0].len() + 1 + n.len() ..]),
+            _ => return None
+        };
+        if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+            return None
+        }
+        Some(Self {
+            number: number.parse().ok()?,
+            digits: number.len(),
+            suffix,
+        })
+    }
+
+    /// Returns path for file with file number `number`, if it exists.
+    /// Any timestamp in the file name is ignored.
+    fn find(&self, dir: &Path, ext: &str, number: u32) -> Option<PathBuf> {
+        let number = format!("{number:0width$}", width = self.digits);
+        if self.suffix.is_empty() {
+            let path = dir.join(format!("DJI_{number}.{ext}"));
+            return path.exists().then_some(path)
+        }
+        let ending = format!("_{number}{}.{ext}", self.suffix);
+        std::fs::read_dir(dir).ok()?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .find(|p| p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("DJI_") && n.ends_with(&ending)))
+    }
+
+    /// Returns consecutively numbered files that continue
+    /// each other in time, including `path`.
+    fn chapters(&self, path: &Path, dir: &Path, ext: &str) -> Result<Vec<PathBuf>, Mp4Error> {
+        let mut paths = vec![path.to_owned()];
+
+        // Preceding chapters
+        let mut number = self.number;
+        while let Some(prev) = number.checked_sub(1).and_then(|n| self.find(dir, ext, n)) {
+            if !is_continuation(&prev, &paths[0])? {
+                break
+            }
+            paths.insert(0, prev);
+            number -= 1;
+        }
+
+        // Following chapters
+        let mut number = self.number;
+        while let Some(next) = self.find(dir, ext, number + 1) {
+            if !is_continuation(&paths[paths.len() - 1], &next)? {
+                break
+            }
+            paths.push(next);
+            number += 1;
+        }
+
+        Ok(paths)
+    }
+}
+
+/// Returns `true` if `next` starts where `prev` ends,
+/// according to creation time and duration in `mvhd`.
+fn is_continuation(prev: &Path, next: &Path) -> Result<bool, Mp4Error> {
+    let mut prev = Mp4::new(prev)?;
+    let mut next = Mp4::new(next)?;
+    let end = prev.creation_time(true)? + prev.duration(true)?;
+    let start = next.creation_time(true)?;
+    Ok((start - end).abs() <= Duration::seconds(MAX_CHAPTER_GAP_SECONDS))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{testing::{mp4, TestTrack}, TrackIdentifier};
+
+    use super::*;
+
+    /// Track attributes for a single track MP4 with `chunks`,
+    /// and the MP4 size in bytes.
+    fn part(id: u32, chunks: Vec<Vec<Vec<u8>>>) -> (TrackAttributes, u64) {
+        let bytes = mp4(&[TestTrack::new(id, "Test", chunks)]);
+        let mut mp4 = Mp4::from_slice(&bytes).unwrap();
+        let attributes = TrackAttributes::new(&mut mp4, TrackIdentifier::Id(id), true).unwrap();
+        (attributes, bytes.len() as u64)
+    }
+
+    #[test]
+    fn gopro_names() {
+        let stems = gopro_chapters("GX020042").unwrap();
+        assert_eq!(stems.len(), 99);
+        assert_eq!(stems[..3], ["GX010042", "GX020042", "GX030042"]);
+        assert_eq!(gopro_chapters("GH011234").unwrap()[1], "GH021234");
+
+        let stems = gopro_chapters("GP010042").unwrap();
+        assert_eq!(stems[..3], ["GOPR0042", "GP010042", "GP020042"]);
+        assert_eq!(gopro_chapters("GOPR0042").unwrap(), stems);
+
+        for stem in ["GX01004", "GX0100420", "GX01A042", "GOPRO042", "GPR10042", "DJI_0001", "GXé1004"] {
+            assert!(gopro_chapters(stem).is_none(), "{stem}");
+        }
+    }
+
+    #[test]
+    fn dji_names() {
+        let dji = DjiName::new("DJI_0042").unwrap();
+        assert_eq!((dji.number, dji.digits, dji.suffix), (42, 4, ""));
+
+        let dji = DjiName::new("DJI_20240501120000_0007_D").unwrap();
+        assert_eq!((dji.number, dji.digits, dji.suffix), (7, 4, "_D"));
+
+        for stem in ["DJI_", "DJI_00A1", "DJI_20240501120000_0007", "DJI_1_2_3_4", "DJI_2024_x_D", "GX010042"] {
+            assert!(DjiName::new(stem).is_none(), "{stem}");
+        }
+    }
+
+    #[test]
+    fn merge_chapters() {
+        let (first, len) = part(1, vec![vec![vec![1; 4], vec![2; 4]], vec![vec![3; 4]]]);
+        let (second, _) = part(1, vec![vec![vec![4; 4]], vec![vec![5; 4], vec![6; 4]]]);
+        let merged = merge(vec![first.to_owned(), second.to_owned()], &[0, len]).unwrap();
+
+        assert_eq!(merged.offsets()[..3], first.offsets()[..]);
+        assert_eq!(merged.offsets().len(), 6);
+        assert_eq!(merged.duration_unscaled(), first.duration_unscaled() + second.duration_unscaled());
+
+        let elapsed = Duration::milliseconds(300);
+        for (merged, part) in merged.offsets()[3..].iter().zip(second.offsets()) {
+            assert_eq!(merged.position, part.position + len);
+            assert_eq!(merged.chunk, part.chunk + 2);
+            assert_eq!(merged.file, 1);
+            assert_eq!(merged.dts, part.dts + elapsed);
+            assert_eq!(merged.pts, part.pts + elapsed);
+            assert_eq!((merged.size, merged.duration), (part.size, part.duration));
+        }
+        assert_eq!(merged.offsets()[3].pts, elapsed);
+
+        // Different track
+        let (other, _) = part(2, vec![vec![vec![1; 4]]]);
+        assert!(matches!(merge(vec![first, other], &[0, len]), Err(Mp4Error::SequenceMismatch)));
+        assert!(matches!(merge(Vec::new(), &[]), Err(Mp4Error::EmptySequence)));
+    }
+
+    #[test]
+    fn sequence_from_files() {
+        let dir = std::env::temp_dir().join(format!("mp4iter-sequence-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let chapters = [
+            vec![vec![vec![1; 4], vec![2; 4]]],
+            vec![vec![vec![3; 4]], vec![vec![4; 4]]],
+        ];
+        // GoPro, and DJI with suffix and timestamps that differ
+        // between chapters (creation times and durations match)
+        let names = [
+            ["GX010042.MP4", "GX020042.MP4"],
+            ["DJI_20240501120000_0009_D.MP4", "DJI_20240501120500_0010_D.MP4"],
+        ];
+        for name in names.iter() {
+            for (chunks, name) in chapters.iter().zip(name) {
+                std::fs::write(dir.join(name), mp4(&[TestTrack::new(1, "Test", chunks.to_owned())])).unwrap();
+            }
+        }
+
+        for name in names.iter() {
+            let paths: Vec<PathBuf> = name.iter().map(|n| dir.join(n)).collect();
+            assert_eq!(Mp4Sequence::detect(&paths[1]).unwrap(), paths);
+
+            let mut sequence = Mp4Sequence::new(&paths).unwrap();
+            let samples: Vec<(Vec<u8>, Duration)> = sequence.track(1_u32).unwrap()
+                .samples()
+                .map(|s| {
+                    let s = s.unwrap();
+                    (s.raw().to_vec(), s.relative())
+                })
+                .collect();
+            assert_eq!(samples.iter().map(|s| s.0[0]).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+            assert_eq!(samples[3].1, Duration::milliseconds(300));
+        }
+
+        // Not part of a sequence
+        let single = dir.join("GX010043.MP4");
+        assert_eq!(Mp4Sequence::detect(&single).unwrap(), vec![single]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,288 @@
+//! Range-read byte sources, e.g. for MP4 files behind a range-capable
+//! object store, where only the `moov` atom and selected samples
+//! should be fetched rather than the full file.
+//!
+//! Implement `ByteSource` for the store, then use `Mp4::from_source()`.
+//! `Track::samples()` reads each chunk (see `stco`/`co64`) in a single request.
+//!
+//! ```rs
+//! use mp4iter::{Mp4, source::{CountingSource, FileSource}};
+//! use std::path::Path;
+//!
+//! fn main() -> Result<(), mp4iter::Mp4Error> {
+//!     let source = CountingSource::new(FileSource::open(Path::new("VIDEO.MP4"))?);
+//!     let mut mp4 = Mp4::from_source(&source)?;
+//!     let mut track = mp4.track("GoPro MET", true)?;
+//!     for sample in track.samples() {
+//!         println!("{} bytes", sample?.len());
+//!     }
+//!     println!("{} requests, {} bytes", source.requests(), source.bytes_read());
+//!     Ok(())
+//! }
+//! ```
+
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+    sync::{atomic::{AtomicU64, Ordering}, Arc},
+};
+
+use crate::{support::seek_pos, Mp4Error};
+
+/// Random access byte source.
+pub trait ByteSource {
+    /// Reads `len` bytes at absolute position `offset`.
+    ///
+    /// Should return an error if fewer than `len` bytes
+    /// could be read.
+    fn read_at(&self, offset: u64, len: usize) -> io::Result<Vec<u8>>;
+
+    /// Total size in bytes.
+    fn len(&self) -> u64;
+
+    /// Returns `true` if the source is empty.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl <S: ByteSource + ?Sized> ByteSource for &S {
+    fn read_at(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        (**self).read_at(offset, len)
+    }
+
+    fn len(&self) -> u64 {
+        (**self).len()
+    }
+}
+
+impl <S: ByteSource + ?Sized> ByteSource for Arc<S> {
+    fn read_at(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        (**self).read_at(offset, len)
+    }
+
+    fn len(&self) -> u64 {
+        (**self).len()
+    }
+}
+
+/// Reader implementing `Read + Seek` over a `ByteSource`.
+///
+/// Each call to `read()` results in a single `ByteSource::read_at()`.
+#[derive(Debug)]
+pub struct SourceReader<S> {
+    source: S,
+    /// Size of source in bytes.
+    len: u64,
+    /// Current position.
+    pos: u64,
+}
+
+impl <S: ByteSource> SourceReader<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            len: source.len(),
+            source,
+            pos: 0,
+        }
+    }
+
+    /// Returns the underlying byte source.
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+}
+
+impl <S: ByteSource> Read for SourceReader<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let rem = self.len.saturating_sub(self.pos);
+        let n = (buf.len() as u64).min(rem) as usize;
+        if n == 0 {
+            return Ok(0);
+        }
+        let bytes = self.source.read_at(self.pos, n)?;
+        if bytes.len() < n {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        buf[..n].copy_from_slice(&bytes[..n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl <S: ByteSource> Seek for SourceReader<S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = seek_pos(self.pos, self.len, pos)?;
+        Ok(self.pos)
+    }
+}
+
+/// Local file as `ByteSource`.
+///
+/// Uses positional reads (`pread` on Unix),
+/// i.e. reads do not affect a shared file position.
+#[derive(Debug)]
+pub struct FileSource {
+    file: File,
+    len: u64,
+}
+
+impl FileSource {
+    pub fn new(file: File) -> Result<Self, Mp4Error> {
+        let len = file.metadata()?.len();
+        Ok(Self { file, len })
+    }
+
+    pub fn open(path: &Path) -> Result<Self, Mp4Error> {
+        Self::new(File::open(path)?)
+    }
+}
+
+impl ByteSource for FileSource {
+    fn read_at(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let mut buf = vec![0_u8; len];
+        read_exact_at(&self.file, &mut buf, offset)?;
+        Ok(buf)
+    }
+
+    fn len(&self) -> u64 {
+        self.len
+    }
+}
+
+#[cfg(unix)]
+fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.read_exact_at(buf, offset)
+}
+
+#[cfg(windows)]
+fn read_exact_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+    while !buf.is_empty() {
+        match file.seek_read(buf, offset) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => {
+                buf = &mut buf[n..];
+                offset += n as u64;
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+/// `ByteSource` wrapper that counts requests and bytes read,
+/// e.g. to verify how many range requests a remote
+/// source would receive.
+#[derive(Debug)]
+pub struct CountingSource<S> {
+    source: S,
+    requests: AtomicU64,
+    bytes: AtomicU64,
+}
+
+impl <S: ByteSource> CountingSource<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            requests: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+        }
+    }
+
+    /// Number of calls to `read_at()`.
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// Total number of bytes read.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Resets counters to 0.
+    pub fn reset(&self) {
+        self.requests.store(0, Ordering::Relaxed);
+        self.bytes.store(0, Ordering::Relaxed);
+    }
+
+    /// Returns the wrapped byte source.
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl <S: ByteSource> ByteSource for CountingSource<S> {
+    fn read_at(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(len as u64, Ordering::Relaxed);
+        self.source.read_at(offset, len)
+    }
+
+    fn len(&self) -> u64 {
+        self.source.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{testing::{mp4, MemorySource, TestTrack}, Mp4};
+
+    use super::*;
+
+    #[test]
+    fn counting_source() {
+        let source = CountingSource::new(MemorySource((0 .. 100).collect()));
+        assert_eq!(source.read_at(10, 5).unwrap(), vec![10, 11, 12, 13, 14]);
+        assert_eq!(source.read_at(90, 10).unwrap().len(), 10);
+        assert!(source.read_at(95, 10).is_err());
+        assert_eq!(source.requests(), 3);
+        assert_eq!(source.bytes_read(), 25);
+
+        source.reset();
+        assert_eq!((source.requests(), source.bytes_read()), (0, 0));
+    }
+
+    #[test]
+    fn source_reader_seek() {
+        let mut reader = SourceReader::new(MemorySource((0 .. 100).collect()));
+        assert_eq!(reader.seek(SeekFrom::End(-10)).unwrap(), 90);
+        assert_eq!(reader.seek(SeekFrom::Current(5)).unwrap(), 95);
+        assert!(reader.seek(SeekFrom::Current(-96)).is_err());
+        // Failed seek leaves position unchanged
+        assert_eq!(reader.stream_position().unwrap(), 95);
+
+        let mut buf = [0; 10];
+        assert_eq!(reader.read(&mut buf).unwrap(), 5);
+        assert_eq!(buf[..5], [95, 96, 97, 98, 99]);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn samples_one_request_per_chunk() {
+        // Sample sizes differ between chunks, so that sample offsets
+        // are wrong unless each chunk's first sample index is correct.
+        let track = TestTrack::new(1, "Test", vec![
+            vec![vec![1; 10], vec![2; 20], vec![3; 30]],
+            vec![vec![4; 40]],
+            vec![vec![5; 50], vec![6; 60]],
+            vec![vec![7; 70], vec![8; 80], vec![9; 90], vec![10; 100]],
+        ]);
+        let source = CountingSource::new(MemorySource(mp4(&[track.to_owned()])));
+        let mut mp4 = Mp4::from_source(&source).unwrap();
+        let mut trk = mp4.track(1_u32, true).unwrap();
+        assert_eq!(trk.offsets().map(|o| o.chunk).collect::<Vec<_>>(), vec![0, 0, 0, 1, 2, 2, 3, 3, 3, 3]);
+
+        source.reset();
+        let samples: Vec<Vec<u8>> = trk.samples()
+            .map(|s| s.unwrap().raw().to_vec())
+            .collect();
+
+        assert_eq!(samples, track.samples());
+        // One request per stco entry
+        assert_eq!(source.requests(), 4);
+    }
+}
@@ -1,3 +1,5 @@
+use std::io::{self, SeekFrom};
+
 /// Single-byte chars from Big Endian `u32` value.
 /// Maps 0-255 to `char`, exceeding ascii.
 pub(crate) fn chars_from_be_u32(value: u32) -> [char; 4] {
@@ -58,4 +60,19 @@ pub(crate) fn vec2arr<T, const N: usize>(v: Vec<T>) -> [T; N] {
 pub(crate) fn str2arr<const N: usize>(value: &str) -> [char; N] {
     let val = value.chars().collect::<Vec<_>>();
     vec2arr::<char, N>(val)
-}
\ No newline at end of file
+}
+
+/// Returns new position for a seek to `seek_from`,
+/// for a reader at position `pos` over `len` bytes.
+/// Seeking beyond `len` is allowed, as for `std::io::Cursor`.
+pub(crate) fn seek_pos(pos: u64, len: u64, seek_from: SeekFrom) -> io::Result<u64> {
+    let new_pos = match seek_from {
+        SeekFrom::Start(p) => Some(p),
+        SeekFrom::End(rel) => len.checked_add_signed(rel),
+        SeekFrom::Current(rel) => pos.checked_add_signed(rel),
+    };
+    new_pos.ok_or_else(|| io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "Invalid seek to a negative or overflowing position",
+    ))
+}
@@ -0,0 +1,260 @@
+//! Typed user data tags.
+//!
+//! Combines the classic QuickTime international text atoms in `udta`,
+//! e.g. `©nam` (title), `©day` (date), `©too` (software),
+//! `©xyz` (location), `©mak` (make), `©mod` (model),
+//! with iTunes-style metadata in `udta/meta/ilst`,
+//! as used in e.g. `.m4a` and `.m4v` files.
+//!
+//! ```rs
+//! use mp4iter::Mp4;
+//! use std::path::Path;
+//!
+//! fn main() -> Result<(), mp4iter::Mp4Error> {
+//!     let mut mp4 = Mp4::new(Path::new("VIDEO.MP4"))?;
+//!     let tags = mp4.tags()?;
+//!     println!("{:?} {:?}", tags.make(), tags.model());
+//!     for (key, tag) in tags.iter() {
+//!         println!("{key} {:?} {:?}", tag.language(), tag.value());
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! See:
+//! - <https://developer.apple.com/documentation/quicktime-file-format/user_data_atoms>
+//! - <https://developer.apple.com/documentation/quicktime-file-format/metadata_item_list_atom>
+
+use std::collections::BTreeMap;
+
+use crate::{atom_types::derive_language_code, Meta, MetaValue};
+
+/// Single tag value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tag {
+    /// Language code for international text atoms.
+    /// Either a Macintosh language code (< `0x400`),
+    /// or a packed ISO 639-2/T language code.
+    /// 0 (English) for iTunes-style items.
+    pub(crate) language: u16,
+    /// Value.
+    pub(crate) value: MetaValue,
+}
+
+impl Tag {
+    /// Raw language code. Either a Macintosh language code (< `0x400`),
+    /// e.g. 0 for English, or a packed ISO 639-2/T language code.
+    pub fn language_code(&self) -> u16 {
+        self.language
+    }
+
+    /// ISO 639-2/T language code, e.g. `eng`,
+    /// if the language code is packed ISO 639-2/T.
+    /// `None` for Macintosh language codes, or if unspecified.
+    pub fn language(&self) -> Option<String> {
+        match self.language {
+            0x400 .. 0x7fff => Some(derive_language_code(self.language)),
+            _ => None,
+        }
+    }
+
+    /// Value.
+    pub fn value(&self) -> &MetaValue {
+        &self.value
+    }
+}
+
+/// User data tags, keyed by FourCC, e.g. `©nam`,
+/// or `MEAN:NAME` for iTunes freeform items (`----`).
+/// A key may have several values, e.g. one per language.
+#[derive(Debug, Default, Clone)]
+pub struct Tags {
+    tags: BTreeMap<String, Vec<Tag>>,
+}
+
+impl Tags {
+    /// Adds value for `key`.
+    pub(crate) fn insert(&mut self, key: &str, tag: Tag) {
+        self.tags.entry(key.to_owned())
+            .or_default()
+            .push(tag);
+    }
+
+    /// Adds international text atom with FourCC `key`,
+    /// i.e. one or more entries, each consisting of
+    /// a 16-bit size, a 16-bit language code, and a string.
+    /// Strings are UTF-8, or UTF-16 if starting with a byte order mark.
+    pub(crate) fn insert_text(&mut self, key: &str, data: &[u8]) {
+        let mut pos = 0;
+        let mut count = 0;
+        while pos + 4 <= data.len() {
+            let size = u16::from_be_bytes([data[pos], data[pos + 1]]) as usize;
+            let language = u16::from_be_bytes([data[pos + 2], data[pos + 3]]);
+            let Some(text) = data.get(pos + 4 .. pos + 4 + size) else {
+                break;
+            };
+            // UTF-16 if preceded by a byte order mark, otherwise UTF-8
+            let value = match text.strip_prefix(&[0xfe, 0xff]) {
+                Some(utf16) => MetaValue::new(key, 2, utf16),
+                None => MetaValue::new(key, 1, text),
+            };
+            self.insert(key, Tag {
+                language,
+                value,
+            });
+            pos += 4 + size;
+            count += 1;
+        }
+
+        // Not an international text atom
+        if count == 0 {
+            self.insert(key, Tag {
+                language: 0,
+                value: MetaValue::Binary{type_code: 0, data: data.to_owned()},
+            });
+        }
+    }
+
+    /// Adds items for iTunes-style metadata.
+    pub(crate) fn insert_meta(&mut self, meta: &Meta) {
+        for item in meta.items() {
+            self.insert(&item.key, Tag {
+                language: 0,
+                value: item.value.to_owned(),
+            });
+        }
+    }
+
+    /// Returns first value for `key`, e.g. `©nam`.
+    pub fn get(&self, key: &str) -> Option<&MetaValue> {
+        self.get_all(key).first()
+            .map(|tag| &tag.value)
+    }
+
+    /// Returns all values for `key`, e.g. `©nam`.
+    pub fn get_all(&self, key: &str) -> &[Tag] {
+        self.tags.get(key)
+            .map(|tags| tags.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns first value for `key` as string slice.
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key)?.as_str()
+    }
+
+    /// Iterates over all tags, sorted by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Tag)> {
+        self.tags.iter()
+            .flat_map(|(key, tags)| tags.iter().map(move |tag| (key.as_str(), tag)))
+    }
+
+    /// Iterates over keys, sorted.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.tags.keys().map(|k| k.as_str())
+    }
+
+    /// Number of keys.
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Returns `true` if there are no tags.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// Title (`©nam`).
+    pub fn title(&self) -> Option<&str> {
+        self.get_str("©nam")
+    }
+
+    /// Date (`©day`). Either a date,
+    /// or a string if not ISO 8601, e.g. only a year.
+    pub fn date(&self) -> Option<&MetaValue> {
+        self.get("©day")
+    }
+
+    /// Encoding software (`©too`).
+    pub fn software(&self) -> Option<&str> {
+        self.get_str("©too")
+    }
+
+    /// ISO 6709 location (`©xyz`), e.g. `+59.3293+018.0686/`.
+    pub fn location(&self) -> Option<&str> {
+        self.get_str("©xyz")
+    }
+
+    /// Camera make (`©mak`).
+    pub fn make(&self) -> Option<&str> {
+        self.get_str("©mak")
+    }
+
+    /// Camera model (`©mod`).
+    pub fn model(&self) -> Option<&str> {
+        self.get_str("©mod")
+    }
+
+    /// Cover art (`covr`).
+    pub fn cover(&self) -> Option<&MetaValue> {
+        self.get("covr")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// International text entry.
+    fn text(language: u16, string: &[u8]) -> Vec<u8> {
+        [&(string.len() as u16).to_be_bytes(), &language.to_be_bytes(), string].concat()
+    }
+
+    #[test]
+    fn international_text() {
+        let utf16: Vec<u8> = [0xfeff_u16].into_iter()
+            .chain("Título".encode_utf16())
+            .flat_map(|u| u.to_be_bytes())
+            .collect();
+
+        let mut tags = Tags::default();
+        tags.insert_text("©nam", &[
+            text(0, "Title".as_bytes()),
+            // Packed ISO 639-2/T 'spa'
+            text(0x4e01, &utf16),
+            // Packed ISO 639-2/T 'eng', null terminated
+            text(0x15c7, b"Title\0"),
+        ].concat());
+
+        let titles = tags.get_all("©nam");
+        assert_eq!(titles.len(), 3);
+        assert_eq!(titles[0].value(), &MetaValue::Utf8("Title".to_owned()));
+        assert_eq!((titles[0].language_code(), titles[0].language()), (0, None));
+        assert_eq!(titles[1].value(), &MetaValue::Utf16("Título".to_owned()));
+        assert_eq!(titles[1].language().as_deref(), Some("spa"));
+        assert_eq!(titles[2].value().as_str(), Some("Title"));
+        assert_eq!(titles[2].language().as_deref(), Some("eng"));
+        assert_eq!(tags.title(), Some("Title"));
+    }
+
+    #[test]
+    fn international_text_date() {
+        let mut tags = Tags::default();
+        tags.insert_text("©day", &text(0, b"2024-05-01T12:00:00Z"));
+        assert!(matches!(tags.date(), Some(MetaValue::Date(_))));
+    }
+
+    #[test]
+    fn not_international_text() {
+        let mut tags = Tags::default();
+        // Size exceeds data
+        tags.insert_text("©xyz", &[0, 9, 0, 0, b'a']);
+        assert_eq!(tags.get("©xyz"), Some(&MetaValue::Binary { type_code: 0, data: vec![0, 9, 0, 0, b'a'] }));
+
+        // Trailing partial entry is ignored
+        let mut tags = Tags::default();
+        tags.insert_text("©mak", &[text(0, b"Make"), vec![0, 9, 0, 0]].concat());
+        assert_eq!(tags.get_all("©mak").len(), 1);
+        assert_eq!(tags.make(), Some("Make"));
+    }
+}
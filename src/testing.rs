@@ -0,0 +1,173 @@
+//! Internal. Minimal in-memory MP4s for unit tests.
+//!
+//! Layout is `ftyp`, `mdat`, `moov`, with samples stored
+//! chunk by chunk in `mdat`, and a single padding byte
+//! between chunks so that chunks are never contiguous.
+
+use std::io;
+
+use crate::ByteSource;
+
+/// Atom with FourCC `name` and data load `data`.
+pub(crate) fn atom(name: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut bytes = (8 + data.len() as u32).to_be_bytes().to_vec();
+    bytes.extend_from_slice(name);
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+/// Full atom, i.e. `data` preceded by `version` and zeroed flags.
+pub(crate) fn full_atom(name: &[u8; 4], version: u8, data: &[u8]) -> Vec<u8> {
+    let mut load = vec![version, 0, 0, 0];
+    load.extend_from_slice(data);
+    atom(name, &load)
+}
+
+/// Container atom with `children`.
+pub(crate) fn container(name: &[u8; 4], children: &[Vec<u8>]) -> Vec<u8> {
+    atom(name, &children.concat())
+}
+
+/// Concatenated big endian `u32` values.
+pub(crate) fn be_u32(values: &[u32]) -> Vec<u8> {
+    values.iter()
+        .flat_map(|v| v.to_be_bytes())
+        .collect()
+}
+
+/// Track for `mp4()`.
+#[derive(Debug, Clone)]
+pub(crate) struct TestTrack {
+    pub(crate) id: u32,
+    /// Handler name (`hdlr`).
+    pub(crate) name: &'static str,
+    /// Handler sub type (`hdlr`).
+    pub(crate) sub_type: [u8; 4],
+    /// Media time scale (`mdhd`).
+    pub(crate) time_scale: u32,
+    /// Unscaled duration for each sample (`stts`).
+    pub(crate) sample_duration: u32,
+    /// Sample data, grouped by chunk.
+    pub(crate) chunks: Vec<Vec<Vec<u8>>>,
+    /// Atoms preceding `mdia` in `trak`, e.g. `tref` or `edts`.
+    pub(crate) atoms: Vec<Vec<u8>>,
+    /// Atoms following `mdia` in `trak`.
+    pub(crate) trailing_atoms: Vec<Vec<u8>>,
+}
+
+impl TestTrack {
+    pub(crate) fn new(id: u32, name: &'static str, chunks: Vec<Vec<Vec<u8>>>) -> Self {
+        Self {
+            id,
+            name,
+            sub_type: *b"meta",
+            time_scale: 1000,
+            sample_duration: 100,
+            chunks,
+            atoms: Vec::new(),
+            trailing_atoms: Vec::new(),
+        }
+    }
+
+    /// Returns all samples in order.
+    pub(crate) fn samples(&self) -> Vec<Vec<u8>> {
+        self.chunks.concat()
+    }
+
+    fn trak(&self, chunk_offsets: &[u32]) -> Vec<u8> {
+        let sizes: Vec<u32> = self.samples().iter()
+            .map(|s| s.len() as u32)
+            .collect();
+        let sample_count = sizes.len() as u32;
+        let duration = sample_count * self.sample_duration;
+
+        // One sample to chunk entry per chunk
+        let stsc: Vec<u32> = self.chunks.iter()
+            .enumerate()
+            .flat_map(|(i, c)| [i as u32 + 1, c.len() as u32, 1])
+            .collect();
+
+        let stbl = container(b"stbl", &[
+            full_atom(b"stsd", 0, &be_u32(&[0])),
+            full_atom(b"stts", 0, &be_u32(&[1, sample_count, self.sample_duration])),
+            full_atom(b"stsc", 0, &[be_u32(&[self.chunks.len() as u32]), be_u32(&stsc)].concat()),
+            full_atom(b"stsz", 0, &[be_u32(&[0, sample_count]), be_u32(&sizes)].concat()),
+            full_atom(b"stco", 0, &[be_u32(&[chunk_offsets.len() as u32]), be_u32(chunk_offsets)].concat()),
+        ]);
+
+        let mut hdlr = [b"mhlr".as_slice(), &self.sub_type, &[0; 12]].concat();
+        hdlr.push(self.name.len() as u8);
+        hdlr.extend_from_slice(self.name.as_bytes());
+
+        let mdia = container(b"mdia", &[
+            full_atom(b"mdhd", 0, &[be_u32(&[0, 0, self.time_scale, duration]), vec![0; 4]].concat()),
+            full_atom(b"hdlr", 0, &hdlr),
+            container(b"minf", &[stbl]),
+        ]);
+
+        let tkhd = [
+            be_u32(&[0, 0, self.id, 0, duration]),
+            vec![0; 52],
+            be_u32(&[0, 0]),
+        ].concat();
+
+        let mut children = vec![full_atom(b"tkhd", 0, &tkhd)];
+        children.extend(self.atoms.iter().cloned());
+        children.push(mdia);
+        children.extend(self.trailing_atoms.iter().cloned());
+        container(b"trak", &children)
+    }
+}
+
+/// Returns an MP4 with `tracks`.
+pub(crate) fn mp4(tracks: &[TestTrack]) -> Vec<u8> {
+    let ftyp = atom(b"ftyp", b"isom\0\0\0\0isom");
+
+    // Sample data, with chunk offsets for each track
+    let mut data: Vec<u8> = Vec::new();
+    let mut chunk_offsets: Vec<Vec<u32>> = Vec::new();
+    for track in tracks.iter() {
+        let mut offsets = Vec::new();
+        for chunk in track.chunks.iter() {
+            data.push(0);
+            offsets.push((ftyp.len() + 8 + data.len()) as u32);
+            data.extend(chunk.concat());
+        }
+        chunk_offsets.push(offsets);
+    }
+    let mdat = atom(b"mdat", &data);
+
+    let duration = tracks.iter()
+        .map(|t| t.samples().len() as u32 * t.sample_duration)
+        .max()
+        .unwrap_or_default();
+    let mvhd = [
+        be_u32(&[0, 0, 1000, duration, 0x0001_0000]),
+        vec![0; 2 + 10 + 36 + 24],
+        be_u32(&[tracks.len() as u32 + 1]),
+    ].concat();
+
+    let mut moov = vec![full_atom(b"mvhd", 0, &mvhd)];
+    moov.extend(tracks.iter()
+        .zip(chunk_offsets.iter())
+        .map(|(t, offsets)| t.trak(offsets)));
+
+    [ftyp, mdat, container(b"moov", &moov)].concat()
+}
+
+/// In-memory `ByteSource`.
+#[derive(Debug)]
+pub(crate) struct MemorySource(pub(crate) Vec<u8>);
+
+impl ByteSource for MemorySource {
+    fn read_at(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        usize::try_from(offset).ok()
+            .and_then(|start| self.0.get(start .. start.checked_add(len)?))
+            .map(|bytes| bytes.to_vec())
+            .ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+    }
+
+    fn len(&self) -> u64 {
+        self.0.len() as u64
+    }
+}
@@ -1,10 +1,12 @@
+use std::{collections::BTreeMap, io::{Read, Seek, SeekFrom}};
+
 use time::{Duration, PrimitiveDateTime, ext::NumericalDuration};
 
-use crate::{AudioFormat, Mp4, Mp4Error, SampleOffset, SampleOffsets, Tmcd, VideoFormat};
+use crate::{atom_types::EditListTable, reader::AtomReadOrigin, AudioFormat, Mp4, Mp4Error, SampleOffset, SampleOffsets, TargetReader, Tmcd, VideoFormat};
 
-use super::TrackIdentifier;
+use super::{Edit, TrackIdentifier};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TrackAttributes {
     // atom order (intermediary atoms ignored):
     // tkhd -> mdhd -> hdlr -> stsd -> stts -> stsc -> stsz -> stco
@@ -16,11 +18,20 @@ pub struct TrackAttributes {
     /// `tkhd.track_id`
     pub(crate) id: u32,
     /// Creation time.
-    /// `tkhd.creation_time`
-    pub(crate) creation_time: PrimitiveDateTime,
+    /// `tkhd.creation_time`, `None` if outside supported datetime range.
+    pub(crate) creation_time: Option<PrimitiveDateTime>,
     /// Modification time.
-    /// `tkhd.modification_time`
-    pub(crate) modification_time: PrimitiveDateTime,
+    /// `tkhd.modification_time`, `None` if outside supported datetime range.
+    pub(crate) modification_time: Option<PrimitiveDateTime>,
+
+    /// Track references, e.g. `tmcd` for the time code track
+    /// for this track, and referenced track IDs.
+    /// `tref`
+    pub(crate) references: BTreeMap<String, Vec<u32>>,
+    /// Edits, mapping media timeline to presentation timeline.
+    /// Empty if there is no edit list.
+    /// `edts/elst`
+    pub(crate) edits: Vec<Edit>,
 
     /// Track type, e.g. `soun` for an audio track.
     /// hdlr.component_sub_type ([char; 4])
@@ -31,7 +42,7 @@ pub struct TrackAttributes {
     pub(crate) time_scale: u32,
     /// Unscaled duration of track.
     /// `mdhd.duration`
-    pub(crate) duration: u32,
+    pub(crate) duration: u64,
 
     /// Width in pixels.
     /// Will be set to 0 if
@@ -50,8 +61,8 @@ pub struct TrackAttributes {
 }
 
 impl TrackAttributes {
-    pub fn new(
-        mp4: &mut Mp4,
+    pub fn new<R: Read + Seek>(
+        mp4: &mut Mp4<R>,
         identifier: TrackIdentifier,
         reset: bool
     ) -> Result<Self, Mp4Error> {
@@ -59,6 +70,8 @@ impl TrackAttributes {
             mp4.reset()?;
         }
 
+        let movie_time_scale = movie_time_scale(mp4)?;
+
         // Loop until EOF
         loop {
             // 1. find correct tkhd via hdlr.component_name
@@ -70,6 +83,7 @@ impl TrackAttributes {
                 Err(_err) => return Err(Mp4Error::NoSuchTrack(identifier.to_string())),
             };
             let track_id = TrackIdentifier::Id(tkhd.track_id());
+            let (references, edit_list) = track_atoms(mp4)?;
             let mdhd = mp4.mdhd(false)?;
             let hdlr = mp4.hdlr(false)?;
             let track_subtype = TrackIdentifier::SubType(hdlr.component_sub_type());
@@ -78,7 +92,7 @@ impl TrackAttributes {
             // Find and read past header for container atom stbl (sample table box)
             // which is the correct position for finding/reading offsets.
             // Note that atom order in the sample table box is only recommended.
-            let _ = mp4.find_header("stbl", false)?;
+            let _ = mp4.reader.find_header(&TargetReader::Moov, "stbl", false)?;
 
             // 2. find mdhd, hdlr that follow after
             if identifier == track_id || identifier == track_name || identifier == track_subtype {
@@ -87,6 +101,8 @@ impl TrackAttributes {
                     id: tkhd.track_id,
                     creation_time: tkhd.creation_time(),
                     modification_time: tkhd.modification_time(),
+                    references,
+                    edits: Edit::from_table(&edit_list, movie_time_scale, mdhd.time_scale),
                     sub_type: hdlr.component_sub_type().to_owned(),
                     time_scale: mdhd.time_scale,
                     duration: mdhd.duration,
@@ -102,20 +118,22 @@ impl TrackAttributes {
     }
 
     /// Returns attributes for all tracks.
-    pub fn all(
-        mp4: &mut Mp4,
+    pub fn all<R: Read + Seek>(
+        mp4: &mut Mp4<R>,
         reset: bool
     ) -> Result<Vec<Self>, Mp4Error> {
         if reset {
             mp4.reset()?;
         }
 
+        let movie_time_scale = movie_time_scale(mp4)?;
         let mut attributes: Vec<Self> = Vec::new();
 
         // Loop until no more tracks
         while let Ok(tkhd) = mp4.tkhd(false) {
             // Parse tkhd + mdhd first, since these precede
             // the hdlr atom containing the handler/track name.
+            let (references, edit_list) = track_atoms(mp4)?;
             let mdhd = mp4.mdhd(false)?;
             let hdlr = mp4.hdlr(false)?;
 
@@ -124,6 +142,8 @@ impl TrackAttributes {
                 id: tkhd.track_id,
                 creation_time: tkhd.creation_time(),
                 modification_time: tkhd.modification_time(),
+                references,
+                edits: Edit::from_table(&edit_list, movie_time_scale, mdhd.time_scale),
                 sub_type: hdlr.component_sub_type().to_owned(),
                 time_scale: mdhd.time_scale,
                 duration: mdhd.duration,
@@ -147,14 +167,97 @@ impl TrackAttributes {
         self.id
     }
 
-    pub fn creation_time(&self) -> PrimitiveDateTime {
+    pub fn creation_time(&self) -> Option<PrimitiveDateTime> {
         self.creation_time
     }
 
-    pub fn modification_time(&self) -> PrimitiveDateTime {
+    pub fn modification_time(&self) -> Option<PrimitiveDateTime> {
         self.modification_time
     }
 
+    /// Track references (`tref`), as reference type,
+    /// e.g. `tmcd`, and referenced track IDs.
+    ///
+    /// Reference types include `tmcd` (time code track for this track),
+    /// `chap` (chapter track for this track), `cdsc` (track described by
+    /// this metadata track), `hint`, `vdep`, `fall`, and `sync`.
+    /// See `Mp4::referenced_track()` and `Mp4::referencing_track()`
+    /// to follow references.
+    pub fn references(&self) -> &BTreeMap<String, Vec<u32>> {
+        &self.references
+    }
+
+    /// Returns IDs for tracks referenced via `reference_type`,
+    /// e.g. `tmcd`.
+    pub fn referenced_ids(&self, reference_type: &str) -> &[u32] {
+        self.references.get(reference_type)
+            .map(|ids| ids.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Edits from the edit list (`edts/elst`),
+    /// mapping the track's media timeline to the presentation timeline.
+    /// Empty if there is no edit list, in which case
+    /// the media timeline is presented as is.
+    pub fn edits(&self) -> &[Edit] {
+        &self.edits
+    }
+
+    /// Presentation time for media time 0,
+    /// i.e. the offset to add to sample timestamps
+    /// (see `Track::timestamps()`) to align the track with other tracks.
+    ///
+    /// Derived as the sum of leading empty edits (start delay),
+    /// minus the media time for the first non-empty edit.
+    /// May be negative, e.g. to skip audio priming samples.
+    /// Zero if there is no edit list.
+    pub fn presentation_offset(&self) -> Duration {
+        let mut offset = Duration::ZERO;
+        for edit in self.edits.iter() {
+            match edit.media_time {
+                Some(media_time) => return offset - media_time,
+                None => offset += edit.duration,
+            }
+        }
+        offset
+    }
+
+    /// Returns presentation start and duration for samples
+    /// that are presented according to the edit list,
+    /// as `(SAMPLE_INDEX, START, DURATION)`, sorted by sample index.
+    /// A sample is listed once for each edit that presents it.
+    ///
+    /// Edits refer to the composition timeline, i.e. to
+    /// sample presentation timestamps (`SampleOffset::pts`).
+    pub(crate) fn presentation(&self) -> Vec<(usize, Duration, Duration)> {
+        if self.edits.is_empty() {
+            return self.offsets.iter()
+                .enumerate()
+                .map(|(i, o)| (i, o.pts, o.duration))
+                .collect()
+        }
+
+        // Compare in media time scale, to avoid rounding errors
+        // from scaled timestamps
+        let time_scale = self.time_scale.max(1) as f64;
+        let samples: Vec<(i64, i64)> = self.offsets.iter()
+            .map(|o| (
+                (o.pts.as_seconds_f64() * time_scale).round() as i64,
+                (o.duration.as_seconds_f64() * time_scale).round() as i64,
+            ))
+            .collect();
+
+        let mut presentation: Vec<(usize, Duration, Duration)> = self.edits.iter()
+            .flat_map(|edit| samples.iter()
+                .enumerate()
+                .filter_map(|(i, (t, d))| edit.present(*t, *d).map(|(start, dur)| (i, start, dur)))
+            )
+            .collect();
+        // Stable, i.e. edit order is kept for repeated samples
+        presentation.sort_by_key(|(i, ..)| *i);
+        presentation
+    }
+
     /// Track sub type, e.g. `vide` for a video track.
     pub fn sub_type(&self) -> &str {
         &self.sub_type
@@ -164,7 +267,7 @@ impl TrackAttributes {
         self.time_scale
     }
 
-    pub fn duration_unscaled(&self) -> u32 {
+    pub fn duration_unscaled(&self) -> u64 {
         self.duration
     }
 
@@ -186,6 +289,34 @@ impl TrackAttributes {
         &self.offsets.offsets
     }
 
+    /// Returns sync samples (key frames) as `(SAMPLE_INDEX, OFFSET)`.
+    /// All samples are sync samples if the track has no
+    /// sync sample atom (`stss`).
+    pub fn keyframes(&self) -> impl Iterator<Item = (usize, &SampleOffset)> {
+        self.offsets().iter()
+            .enumerate()
+            .filter(|(_, o)| o.is_sync())
+    }
+
+    /// Returns the sync sample (key frame) to start decoding from
+    /// to present the sample at `time`, i.e. the last sync sample
+    /// with a presentation timestamp (`SampleOffset::pts`) at or before `time`,
+    /// as `(SAMPLE_INDEX, OFFSET)`.
+    /// Falls back to the first sync sample if `time` precedes all sync samples.
+    ///
+    /// `time` is in the track's media timeline, i.e. edits are not applied.
+    pub fn nearest_keyframe(&self, time: Duration) -> Option<(usize, &SampleOffset)> {
+        let mut keyframes = self.keyframes();
+        let first = keyframes.next()?;
+        Some(
+            std::iter::once(first)
+                .chain(keyframes)
+                .filter(|(_, o)| o.pts <= time)
+                .max_by_key(|(_, o)| o.pts)
+                .unwrap_or(first)
+        )
+    }
+
     // --- BELOW VIA STSD ATOM
 
     pub fn tmcd(&self) -> Result<Tmcd, Mp4Error> {
@@ -227,9 +358,113 @@ impl TrackAttributes {
     pub fn frame_rate_file(
         &self,
         mvhd_time_scale: u32,
-        mvhd_duration: u32,
+        mvhd_duration: u64,
     ) -> f64 {
         // video sample_count * MP4 time_scale / MP4 unscaled_duration
         self.offsets.len() as f64 * mvhd_time_scale as f64 / mvhd_duration as f64
     }
 }
+
+/// Reference type and referenced track IDs.
+type TrackReferences = BTreeMap<String, Vec<u32>>;
+
+/// Returns movie time scale (`mvhd`),
+/// then restores the `moov` reader position.
+fn movie_time_scale<R: Read + Seek>(mp4: &mut Mp4<R>) -> Result<u32, Mp4Error> {
+    let pos = mp4.pos_moov()?;
+    let time_scale = mp4.mvhd(true)?.time_scale;
+    mp4.seek_moov(SeekFrom::Start(pos))?;
+    Ok(time_scale)
+}
+
+/// Parses track references (`tref`) and edit list (`edts/elst`)
+/// for the current track, if present.
+/// Assumes reader position is after the track's `tkhd`,
+/// and leaves it after the `mdia` header.
+fn track_atoms<R: Read + Seek>(
+    mp4: &mut Mp4<R>
+) -> Result<(TrackReferences, Vec<EditListTable>), Mp4Error> {
+    let mut references: TrackReferences = BTreeMap::new();
+    let mut edit_list: Vec<EditListTable> = Vec::new();
+    // Position after 'mdia' header
+    let mut mdia: Option<u64> = None;
+
+    // 'tref' and 'edts' usually precede 'mdia' if present,
+    // but may follow it, so read until the next track
+    // or the end of 'moov'
+    while mp4.pos_moov()? < mp4.reader.len(&TargetReader::Moov) {
+        let mut atom = mp4.atom(&TargetReader::Moov, AtomReadOrigin::None)?;
+        let rel_pos_next = atom.header.next;
+        let data_size = atom.header.data_size();
+        match atom.header.name().to_str() {
+            "tref" => references = atom.tref()?.to_map(),
+            "elst" => edit_list = atom.elst()?.edit_list_table,
+            "mdia" if mdia.is_none() => {
+                mdia = Some(mp4.pos_moov()?);
+                mp4.seek_moov(SeekFrom::Current(i64::try_from(data_size)?))?;
+            },
+            // If next track is encountered we've read too far
+            "trak" => break,
+            _ => {mp4.seek_moov(SeekFrom::Current(i64::try_from(rel_pos_next)?))?;},
+        }
+    }
+
+    match mdia {
+        Some(pos) => {
+            mp4.seek_moov(SeekFrom::Start(pos))?;
+            Ok((references, edit_list))
+        },
+        // No 'mdia' for this track
+        None => Err(Mp4Error::NoSuchAtom("mdia".to_owned())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::ext::NumericalDuration;
+
+    use crate::testing::{atom, be_u32, container, full_atom, mp4, TestTrack};
+
+    use super::*;
+
+    fn tref(reference_type: &[u8; 4], ids: &[u32]) -> Vec<u8> {
+        container(b"tref", &[atom(reference_type, &be_u32(ids))])
+    }
+
+    /// Edit list with a 500ms empty edit, followed by the full media.
+    fn edts() -> Vec<u8> {
+        container(b"edts", &[
+            full_atom(b"elst", 0, &be_u32(&[2, 500, u32::MAX, 0x0001_0000, 1000, 0, 0x0001_0000]))
+        ])
+    }
+
+    #[test]
+    fn track_atoms_before_and_after_mdia() {
+        let chunks = vec![vec![vec![1; 4], vec![2; 4]]];
+        let mut one = TestTrack::new(1, "One", chunks.to_owned());
+        one.atoms.push(tref(b"tmcd", &[3]));
+        one.trailing_atoms.push(edts());
+        let mut two = TestTrack::new(2, "Two", chunks.to_owned());
+        two.trailing_atoms.extend([atom(b"free", &[0; 4]), tref(b"chap", &[3])]);
+        let three = TestTrack::new(3, "Three", chunks);
+        let bytes = mp4(&[one, two, three]);
+        let mut mp4 = Mp4::from_slice(&bytes).unwrap();
+
+        let attributes = TrackAttributes::all(&mut mp4, true).unwrap();
+        assert_eq!(attributes.len(), 3);
+        assert_eq!(attributes[0].referenced_ids("tmcd"), &[3]);
+        assert_eq!(attributes[0].edits().len(), 2);
+        assert_eq!(attributes[0].presentation_offset(), 500.milliseconds());
+        assert_eq!(attributes[1].referenced_ids("chap"), &[3]);
+        assert!(attributes[1].edits().is_empty());
+        assert!(attributes[2].references().is_empty());
+        for (i, track) in attributes.iter().enumerate() {
+            assert_eq!(track.id(), i as u32 + 1);
+            assert_eq!(track.offsets().len(), 2);
+        }
+
+        let two = TrackAttributes::new(&mut mp4, TrackIdentifier::Id(2), true).unwrap();
+        assert_eq!(two.name(), "Two");
+        assert_eq!(two.referenced_ids("chap"), &[3]);
+    }
+}
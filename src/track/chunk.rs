@@ -0,0 +1,182 @@
+//! Track chunk, i.e. consecutive samples stored contiguously in `mdat`
+//! (see `stco`/`co64`), read in a single request.
+
+use std::{io::{Read, Seek, SeekFrom}, ops::Range};
+
+use time::Duration;
+
+use crate::{ByteSource, Mp4Error, Mp4Reader, ReadOption, SampleOffset, TargetReader};
+
+use super::Sample;
+
+/// In-memory chunk.
+#[derive(Debug, Default)]
+pub(crate) struct Chunk {
+    /// 0-based chunk index.
+    pub(crate) index: u32,
+    /// Absolute byte offset for chunk.
+    position: u64,
+    bytes: Vec<u8>,
+}
+
+impl Chunk {
+    /// Reads the chunk containing the first sample in `offsets`,
+    /// spanning all directly following samples in the same chunk.
+    pub(crate) fn read<R: Read + Seek>(
+        reader: &mut Mp4Reader<R>,
+        offsets: &[SampleOffset],
+    ) -> Result<Self, Mp4Error> {
+        let (index, start, end) = Self::span(offsets)?;
+
+        // Possibly wrong assumption that relative seek may be faster
+        // on at least spinning disks...
+        let file_pos_abs = i64::try_from(reader.pos(&TargetReader::File)?)?;
+        let seek = SeekFrom::Current(i64::try_from(start)? - file_pos_abs);
+
+        let bytes = reader.read_bytes(
+            &TargetReader::File,
+            ReadOption::Sized(usize::try_from(end - start)?),
+            Some(seek),
+            None
+        )?;
+
+        Ok(Self {
+            index,
+            position: start,
+            bytes,
+        })
+    }
+
+    /// Reads the chunk containing the first sample in `offsets`,
+    /// spanning all directly following samples in the same chunk,
+    /// using a single positional read.
+    pub(crate) fn read_at<S: ByteSource + ?Sized>(
+        source: &S,
+        offsets: &[SampleOffset],
+    ) -> Result<Self, Mp4Error> {
+        let (index, start, end) = Self::span(offsets)?;
+        let bytes = source.read_at(start, usize::try_from(end - start)?)?;
+
+        Ok(Self {
+            index,
+            position: start,
+            bytes,
+        })
+    }
+
+    /// Returns sample index ranges for each chunk in `offsets`,
+    /// together with the relative timestamp for the chunk's
+    /// first sample, e.g. to read chunks in parallel.
+    pub(crate) fn ranges(offsets: &[SampleOffset]) -> Vec<(Range<usize>, Duration)> {
+        let mut ranges: Vec<(Range<usize>, Duration)> = Vec::new();
+        let mut t = Duration::ZERO;
+        for (i, offset) in offsets.iter().enumerate() {
+            match ranges.last_mut() {
+                Some((range, _)) if offsets[range.start].chunk == offset.chunk => range.end = i + 1,
+                _ => ranges.push((i .. i + 1, t)),
+            }
+            t += offset.duration;
+        }
+        ranges
+    }
+
+    /// Returns samples for `offsets`, which must all be within this chunk,
+    /// with relative timestamps starting at `time`.
+    pub(crate) fn samples(&self, offsets: &[SampleOffset], time: Duration) -> Vec<Result<Sample, Mp4Error>> {
+        let mut t = time;
+        offsets.iter()
+            .map(|offset| {
+                let rel_t = t;
+                t += offset.duration;
+                self.sample(offset).map(|s| s.with_time(rel_t))
+            })
+            .collect()
+    }
+
+    /// Returns chunk index, and absolute start and end position
+    /// for the chunk containing the first sample in `offsets`.
+    fn span(offsets: &[SampleOffset]) -> Result<(u32, u64, u64), Mp4Error> {
+        let index = offsets.first()
+            .ok_or(Mp4Error::SampleOffsetError)?
+            .chunk;
+        let (start, end) = offsets.iter()
+            .take_while(|o| o.chunk == index)
+            .fold((u64::MAX, 0), |(start, end), o| {
+                (start.min(o.position), end.max(o.position + o.size as u64))
+            });
+        Ok((index, start, end))
+    }
+
+    /// Returns sample at `sample_offset`, which must
+    /// be within this chunk.
+    pub(crate) fn sample(&self, sample_offset: &SampleOffset) -> Result<Sample, Mp4Error> {
+        let start = usize::try_from(sample_offset.position - self.position)?;
+        let bytes = self.bytes
+            .get(start .. start + sample_offset.size as usize)
+            .ok_or(Mp4Error::SampleOffsetError)?;
+        Ok(Sample::from_bytes(bytes.to_vec(), sample_offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{source::CountingSource, testing::MemorySource};
+
+    use super::*;
+
+    /// Sample offsets as `(POSITION, SIZE, CHUNK)`,
+    /// with 100 ms duration.
+    fn offsets(samples: &[(u64, u32, u32)]) -> Vec<SampleOffset> {
+        samples.iter()
+            .map(|(pos, size, chunk)| SampleOffset::new(*pos, *size, 100, 1000, false).with_chunk(*chunk))
+            .collect()
+    }
+
+    #[test]
+    fn ranges() {
+        let offsets = offsets(&[
+            (10, 2, 0), (12, 3, 0),
+            (20, 1, 1),
+            (30, 1, 2), (31, 1, 2), (32, 1, 2),
+        ]);
+        assert_eq!(Chunk::ranges(&offsets), vec![
+            (0 .. 2, Duration::ZERO),
+            (2 .. 3, Duration::milliseconds(200)),
+            (3 .. 6, Duration::milliseconds(300)),
+        ]);
+        assert!(Chunk::ranges(&[]).is_empty());
+    }
+
+    #[test]
+    fn read_at_single_request() {
+        let source = CountingSource::new(MemorySource((0 .. 64).collect()));
+        let offsets = offsets(&[
+            (10, 2, 0), (12, 3, 0),
+            (20, 4, 1),
+        ]);
+
+        // Spans only the first chunk
+        let chunk = Chunk::read_at(&source, &offsets).unwrap();
+        assert_eq!((chunk.index, chunk.position, chunk.bytes.len()), (0, 10, 5));
+        assert_eq!((source.requests(), source.bytes_read()), (1, 5));
+
+        let samples: Vec<Vec<u8>> = chunk.samples(&offsets[..2], Duration::seconds(1))
+            .into_iter()
+            .map(|s| {
+                let s = s.unwrap();
+                assert!(s.relative() >= Duration::seconds(1));
+                s.raw().to_vec()
+            })
+            .collect();
+        assert_eq!(samples, vec![vec![10, 11], vec![12, 13, 14]]);
+
+        // Sample outside chunk
+        assert!(chunk.sample(&offsets[2]).is_err());
+
+        let chunk = Chunk::read_at(&source, &offsets[2..]).unwrap();
+        assert_eq!(chunk.sample(&offsets[2]).unwrap().raw(), &[20, 21, 22, 23]);
+        assert_eq!(source.requests(), 2);
+
+        assert!(Chunk::read_at(&source, &[]).is_err());
+    }
+}
@@ -0,0 +1,270 @@
+//! Edits from the track's edit list (`elst`),
+//! which map the track's media timeline to the presentation timeline.
+
+use time::Duration;
+
+use crate::atom_types::EditListTable;
+
+/// Single edit, with times scaled to `time::Duration`.
+///
+/// Maps the media interval starting at `media_time`
+/// to the presentation interval `start .. start + duration`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Edit {
+    /// Start in the presentation timeline,
+    /// i.e. the sum of the durations of preceding edits.
+    pub start: Duration,
+    /// Duration in the presentation timeline.
+    pub duration: Duration,
+    /// Start time within the media.
+    /// `None` for an empty edit, i.e. nothing is presented
+    /// for this edit's duration.
+    pub media_time: Option<Duration>,
+    /// Rate at which the media is played, 1.0 for normal playback.
+    /// 0.0 means the sample at `media_time` is shown for the edit's duration (dwell).
+    pub rate: f64,
+    /// Unscaled media time, in media time scale.
+    /// `None` for an empty edit.
+    pub(crate) media_ticks: Option<i64>,
+    /// Unscaled edit duration, converted to media time scale.
+    pub(crate) duration_ticks: f64,
+    /// Media time scale (`mdhd`).
+    pub(crate) time_scale: f64,
+}
+
+impl Edit {
+    /// Scales edit list entries, where edit durations are in
+    /// movie time scale (`mvhd`), and media times in media
+    /// time scale (`mdhd`).
+    ///
+    /// An edit with a duration or media time that can not be
+    /// represented as `time::Duration`, e.g. a corrupt 64-bit value,
+    /// ends the edit list, since the start of any following edits
+    /// is then unknown.
+    pub(crate) fn from_table(
+        entries: &[EditListTable],
+        movie_time_scale: u32,
+        media_time_scale: u32,
+    ) -> Vec<Self> {
+        // Avoid division by 0
+        let movie_time_scale = movie_time_scale.max(1) as f64;
+        let media_time_scale = media_time_scale.max(1) as f64;
+
+        let mut start = Duration::ZERO;
+        let mut edits: Vec<Self> = Vec::new();
+        for entry in entries.iter() {
+            match Self::from_entry(entry, start, movie_time_scale, media_time_scale) {
+                Some(edit) => {
+                    start = edit.end();
+                    edits.push(edit);
+                },
+                None => break,
+            }
+        }
+        edits
+    }
+
+    /// Scales single edit list entry, starting at `start`
+    /// in the presentation timeline.
+    /// Returns `None` if the edit's duration, end, or media time
+    /// can not be represented as `time::Duration`.
+    fn from_entry(
+        entry: &EditListTable,
+        start: Duration,
+        movie_time_scale: f64,
+        media_time_scale: f64,
+    ) -> Option<Self> {
+        let duration = Duration::checked_seconds_f64(entry.track_duration as f64 / movie_time_scale)?;
+        // Ensure end is representable
+        start.checked_add(duration)?;
+        let media_time = match entry.is_empty() {
+            true => None,
+            false => Some(Duration::checked_seconds_f64(entry.media_time as f64 / media_time_scale)?),
+        };
+
+        Some(Self {
+            start,
+            duration,
+            media_time,
+            rate: entry.rate(),
+            media_ticks: (!entry.is_empty()).then_some(entry.media_time),
+            duration_ticks: entry.track_duration as f64 * media_time_scale / movie_time_scale,
+            time_scale: media_time_scale,
+        })
+    }
+
+    /// Returns `true` if this is an empty edit.
+    pub fn is_empty(&self) -> bool {
+        self.media_time.is_none()
+    }
+
+    /// End in the presentation timeline.
+    pub fn end(&self) -> Duration {
+        self.start + self.duration
+    }
+
+    /// Maps a sample starting at `media_ticks` in the media timeline
+    /// with duration `duration_ticks`, both in media time scale,
+    /// to the presentation timeline, as `(START, DURATION)`.
+    /// Samples partially within the edit are trimmed.
+    /// Returns `None` if the sample is not presented by this edit,
+    /// or if its presentation time can not be represented,
+    /// e.g. for a near-zero rate.
+    ///
+    /// Edits with negative rate are not supported.
+    pub(crate) fn present(&self, media_ticks: i64, duration_ticks: i64) -> Option<(Duration, Duration)> {
+        let edit_media_ticks = self.media_ticks?;
+        let sample_end = media_ticks + duration_ticks;
+
+        // Dwell: sample at media time is shown for the full edit
+        if self.rate == 0. {
+            let contains = media_ticks <= edit_media_ticks
+                && (edit_media_ticks < sample_end || duration_ticks == 0 && media_ticks == edit_media_ticks);
+            return contains.then_some((self.start, self.duration));
+        }
+
+        if self.rate < 0. {
+            return None
+        }
+
+        let edit_media_end = edit_media_ticks as f64 + self.duration_ticks * self.rate;
+        let start = media_ticks.max(edit_media_ticks) as f64;
+        let end = (sample_end as f64).min(edit_media_end);
+
+        let presented = end > start
+            // Zero duration sample within edit
+            || duration_ticks == 0 && media_ticks >= edit_media_ticks && start < edit_media_end;
+
+        if !presented {
+            return None
+        }
+
+        let offset = Duration::checked_seconds_f64((start - edit_media_ticks as f64) / self.time_scale / self.rate)?;
+        let duration = Duration::checked_seconds_f64((end - start).max(0.) / self.time_scale / self.rate)?;
+
+        Some((self.start.checked_add(offset)?, duration))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use binrw::BinReaderExt;
+
+    use crate::Elst;
+
+    use super::*;
+
+    /// Movie time scale (`mvhd`).
+    const MOVIE: u32 = 1000;
+    /// Media time scale (`mdhd`).
+    const MEDIA: u32 = 30000;
+
+    /// Version 0 `elst` data load with entries
+    /// as `(DURATION, MEDIA_TIME, RATE)`.
+    fn elst(entries: &[(u32, i32, u32)]) -> Elst {
+        let mut data = vec![0, 0, 0, 0];
+        data.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+        for (duration, media_time, rate) in entries {
+            data.extend_from_slice(&duration.to_be_bytes());
+            data.extend_from_slice(&media_time.to_be_bytes());
+            data.extend_from_slice(&rate.to_be_bytes());
+        }
+        Cursor::new(data).read_be::<Elst>().unwrap()
+    }
+
+    fn scaled(entries: &[(u32, i32, u32)]) -> Vec<Edit> {
+        Edit::from_table(elst(entries).edit_list_table(), MOVIE, MEDIA)
+    }
+
+    fn ms(ms: i64) -> Duration {
+        Duration::milliseconds(ms)
+    }
+
+    #[test]
+    fn empty_edit() {
+        // 500 ms delay, then media from start
+        let edits = scaled(&[(500, -1, 0x10000), (1000, 0, 0x10000)]);
+        assert!(edits[0].is_empty());
+        assert_eq!(edits[0].media_time, None);
+        assert_eq!(edits[1].start, ms(500));
+        assert_eq!(edits[1].end(), ms(1500));
+
+        // Nothing presented by the empty edit
+        assert_eq!(edits[0].present(0, 1001), None);
+        // Second sample (30000 time scale)
+        let (start, duration) = edits[1].present(1001, 1001).unwrap();
+        assert_eq!(start, ms(500) + Duration::seconds_f64(1001. / 30000.));
+        assert_eq!(duration, Duration::seconds_f64(1001. / 30000.));
+    }
+
+    #[test]
+    fn trim() {
+        // Media from 1000 ticks for 100 ms (3000 ticks)
+        let edits = scaled(&[(100, 1000, 0x10000)]);
+        assert_eq!(edits[0].media_time, Some(Duration::seconds_f64(1000. / 30000.)));
+
+        // Sample starting before the edit is trimmed at the start
+        let (start, duration) = edits[0].present(0, 1500).unwrap();
+        assert_eq!(start, Duration::ZERO);
+        assert_eq!(duration, Duration::seconds_f64(500. / 30000.));
+
+        // Sample ending after the edit is trimmed at the end
+        let (start, duration) = edits[0].present(3500, 1001).unwrap();
+        assert_eq!(start, Duration::seconds_f64(2500. / 30000.));
+        assert_eq!(duration, Duration::seconds_f64(500. / 30000.));
+
+        // Samples outside the edit are not presented
+        assert_eq!(edits[0].present(0, 1000), None);
+        assert_eq!(edits[0].present(4000, 1001), None);
+    }
+
+    #[test]
+    fn dwell() {
+        // Sample at media time 2002 shown for 2 seconds
+        let edits = scaled(&[(2000, 2002, 0)]);
+        assert_eq!(edits[0].rate, 0.);
+        assert_eq!(edits[0].present(2002, 1001), Some((Duration::ZERO, ms(2000))));
+        assert_eq!(edits[0].present(1001, 1001), None);
+        assert_eq!(edits[0].present(3003, 1001), None);
+    }
+
+    #[test]
+    fn rate() {
+        // Half speed
+        let edits = scaled(&[(1000, 0, 0x8000)]);
+        assert_eq!(edits[0].rate, 0.5);
+        let (start, duration) = edits[0].present(3000, 3000).unwrap();
+        assert_eq!(start, ms(200));
+        assert_eq!(duration, ms(200));
+        // Media beyond half the edit duration is not presented
+        assert_eq!(edits[0].present(15000, 1001), None);
+
+        // Negative rate
+        let edits = scaled(&[(1000, 0, 0xffff0000)]);
+        assert_eq!(edits[0].rate, -1.);
+        assert_eq!(edits[0].present(0, 1001), None);
+    }
+
+    #[test]
+    fn unrepresentable() {
+        // Near-zero rate (1/65536) for a very long edit
+        let entries = [EditListTable{track_duration: 1 << 62, media_time: 0, media_rate: 1}];
+        let edits = Edit::from_table(&entries, 1, 1);
+        assert_eq!(edits[0].present(0, 1), Some((Duration::ZERO, Duration::seconds(65536))));
+        assert!(edits[0].present(1 << 40, 1).is_some());
+
+        // Corrupt 64-bit duration ends the edit list
+        let entries = [
+            EditListTable{track_duration: 1000, media_time: -1, media_rate: 0x10000},
+            EditListTable{track_duration: u64::MAX, media_time: 0, media_rate: 0x10000},
+            EditListTable{track_duration: 1000, media_time: 0, media_rate: 0x10000},
+        ];
+        let edits = Edit::from_table(&entries, 1, MEDIA);
+        assert_eq!(edits.len(), 1);
+
+        let entries = [EditListTable{track_duration: 1000, media_time: i64::MAX, media_rate: 0x10000}];
+        assert!(Edit::from_table(&entries, MOVIE, 1).is_empty());
+    }
+}
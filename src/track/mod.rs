@@ -2,8 +2,12 @@ mod track;
 mod sample;
 mod attributes;
 mod offset;
+mod chunk;
+mod edit;
 
 pub use track::{Track, TrackIdentifier, ParsableTrackId};
 pub use attributes::TrackAttributes;
 pub use offset::{SampleOffsets, SampleOffset};
-pub use sample::Sample;
+pub use sample::{Sample, SampleRef};
+pub use edit::Edit;
+pub(crate) use chunk::Chunk;
@@ -1,18 +1,30 @@
 //! Sample offsets consisting of byte offsets, extracted from `stco` (32bit) or `co64` (64bit) atom), size in bytes (extracted from `stsz` atom),
 //! and duration (extracted from `stts` atom).
 
-use std::{collections::HashMap, io::SeekFrom};
+use std::{collections::HashMap, io::{Read, Seek, SeekFrom}};
 
 use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
 use time::Duration;
 
-use crate::{atom_types::AtomType, reader::AtomReadOrigin, Co64, Mp4, Mp4Error, Stsd, TargetReader};
+use crate::{atom_types::AtomType, reader::AtomReadOrigin, Co64, Mp4, SampleDependency, Mp4Error, Stsd, TargetReader};
+
+/// Sample table atoms required to derive sample offsets.
+/// `stco` also covers `co64`.
+const REQUIRED: [&str; 4] = ["stts", "stsc", "stsz", "stco"];
+/// Optional sample table atoms, used to derive presentation timestamps,
+/// and sync samples and sample dependencies.
+const OPTIONAL: [&str; 4] = ["ctts", "cslg", "stss", "sdtp"];
+/// Atoms that may occur in the sample table (`stbl`).
+const SAMPLE_TABLE: [&str; 20] = [
+    "stsd", "stts", "ctts", "cslg", "stss", "stps", "sdtp", "sbgp", "sgpd", "subs",
+    "saiz", "saio", "padb", "stsh", "stdp", "stz2", "stsc", "stsz", "stco", "co64",
+];
 
 /// Sample offsets consisting of byte offsets,
 /// (extracted from `stco` if 32bit or `co64` if 64bit atoms),
 /// sizes in bytes (extracted from `stsz` atom),
 /// and durations (extracted from `stts` atom).
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct SampleOffsets {
     pub(crate) stsd: Stsd,
     pub(crate) offsets: Vec<SampleOffset>
@@ -44,8 +56,13 @@ impl SampleOffsets {
     /// ```
     /// trak -> tkhd -> mdhd -> hdlr -> stts -> stsc -> stsz -> stco/co64
     /// ```
-    pub(crate) fn new(
-        mp4: &mut Mp4,
+    ///
+    /// Composition offsets (`ctts`) and composition shift (`cslg`)
+    /// are optional, and are used to derive decode and presentation
+    /// timestamps for each sample. Sync samples (`stss`) and
+    /// sample dependency flags (`sdtp`) are also optional.
+    pub(crate) fn new<R: Read + Seek>(
+        mp4: &mut Mp4<R>,
         time_scale: u32,
         time_scale_zero_ok: bool,
         moov_position: Option<SeekFrom>
@@ -63,12 +80,23 @@ impl SampleOffsets {
         let mut stsd: Option<Stsd> = None;
         let mut offset_atoms: HashMap<&str, AtomType> = HashMap::new();
 
-        loop {
+        while mp4.pos_moov()? < mp4.reader.len(&TargetReader::Moov) {
+            let has_required = REQUIRED.iter().all(|a| offset_atoms.contains_key(a));
+
             // Read "raw" atom at current position with moov reader
             let mut atom = mp4.atom(&TargetReader::Moov, AtomReadOrigin::None)?;
 
             let rel_pos_next = atom.header.next;
 
+            // Once required atoms have been found, only continue
+            // within the sample table for optional atoms,
+            // then seek back to the start of the atom following it.
+            if has_required && !SAMPLE_TABLE.contains(&atom.header.name().to_str()) {
+                let offset = atom.header.offset;
+                mp4.seek_moov(SeekFrom::Start(offset))?;
+                break
+            }
+
             // Match FourCC for each atom, ignore any that are not required
             // to derive track sample information.
             // Break if 4 of the below have been found, since only one of
@@ -88,6 +116,14 @@ impl SampleOffsets {
                 "stsz" => {offset_atoms.insert("stsz", AtomType::Stsz(atom.stsz()?));},
                 "stts" => {offset_atoms.insert("stts", AtomType::Stts(atom.stts()?));},
 
+                // optional, composition offsets and shift
+                "ctts" => {offset_atoms.insert("ctts", AtomType::Ctts(atom.ctts()?));},
+                "cslg" => {offset_atoms.insert("cslg", AtomType::Cslg(atom.cslg()?));},
+
+                // optional, sync samples and sample dependencies
+                "stss" => {offset_atoms.insert("stss", AtomType::Stss(atom.stss()?));},
+                "sdtp" => {offset_atoms.insert("sdtp", AtomType::Sdtp(atom.sdtp()?));},
+
                 // If next track is encountered we've read too far so return error
                 "trak" => return Err(Mp4Error::SampleOffsetError),
 
@@ -95,8 +131,8 @@ impl SampleOffsets {
                 _ => {mp4.seek_moov(SeekFrom::Current(i64::try_from(rel_pos_next)?))?;},
             }
 
-            // if stco, stts, stsz or stco/co64 have been found break loop
-            if offset_atoms.len() == 4 {
+            // if stco, stts, stsz or stco/co64, and all optional atoms have been found break loop
+            if offset_atoms.len() == REQUIRED.len() + OPTIONAL.len() {
                 break
             }
         }
@@ -127,48 +163,98 @@ impl SampleOffsets {
 
         let co_len = co64.offsets().len();
 
-        // Convert chunk offsets to sample offsets by merging stsc, stco, stsz
-        let sample_offsets: Vec<u64> = co64.offsets()
-            .into_par_iter()
-            .enumerate()
-            .map(|(i, co)| {
+        // Index for first sample in each chunk, and number of samples in that chunk.
+        // Derived sequentially, since each chunk's first sample index depends
+        // on the number of samples in all preceding chunks.
+        let mut first_sample = 0_usize;
+        let chunks: Vec<(usize, usize)> = (0 .. co_len)
+            .map(|i| {
                 // Get number of samples in this chunk
                 // 1-based indexing, i.e. first chunk in stsc's
                 // sample-to-chunk table will have index = 1.
-                let no_of_samples: u32 = match stsc.sample_count(i + 1) {
-                    Some(n) => n,
+                let no_of_samples = match stsc.sample_count(i + 1) {
+                    Some(n) => n as usize,
                     None => panic!("stsc index does not exist\nlen    {}\ni+1    {}\nco len {}",
                         stsc.entry_count,
                         i+1,
                         co_len
                     ),
                 };
+                let chunk = (first_sample, no_of_samples);
+                first_sample += no_of_samples;
+                chunk
+            })
+            .collect();
 
+        // Convert chunk offsets to sample offsets by merging stsc, stco, stsz,
+        // as (SAMPLE_OFFSET, CHUNK_INDEX)
+        let sample_offsets: Vec<(u64, u32)> = co64.offsets()
+            .into_par_iter()
+            .zip(chunks.into_par_iter())
+            .enumerate()
+            .map(|(i, (co, (first, no_of_samples)))| {
                 // Get sample sizes in this chunk
-                // Panics on out of bounds... better to iter and use get for each sample?
-                let smp_sizes: Vec<u32> = (&stsz.sizes()[i .. i + no_of_samples as usize]).to_vec();
+                let smp_sizes = stsz.sizes()
+                    .get(first .. first + no_of_samples)
+                    .ok_or(Mp4Error::SampleOffsetError)?;
 
+                let chunk_index = u32::try_from(i)?;
                 let mut delta = 0_u64;
-                let smp_off: Vec<u64> = smp_sizes.into_iter()
+                let smp_off: Vec<(u64, u32)> = smp_sizes.iter()
                     .map(|s| {
                         let offset = co + delta;
-                        delta += s as u64;
-                        offset
+                        delta += *s as u64;
+                        (offset, chunk_index)
                     })
                     .collect();
 
                 Ok(smp_off)
             })
-            .collect::<Result<Vec<Vec<u64>>, Mp4Error>>()?
+            .collect::<Result<Vec<Vec<(u64, u32)>>, Mp4Error>>()?
             .into_iter()
             .flatten()
             .collect();
 
-        let offsets: Vec<SampleOffset> = stts.durations()
+        // Get composition offsets, if present (e.g. video with B-frames).
+        // Limited to number of samples, in case of corrupt sample counts.
+        let ctts: Vec<i32> = match offset_atoms.get("ctts") {
+            Some(AtomType::Ctts(a)) => a.offsets().take(stsz.sizes().len()).collect(),
+            _ => Vec::new()
+        };
+
+        // Get composition shift, if present. Applied to decode timestamps,
+        // so that presentation timestamps stay on the composition timeline
+        // (which edit lists refer to). Only a positive shift guarantees
+        // decode timestamps never exceed presentation timestamps.
+        let shift = match offset_atoms.get("cslg") {
+            Some(AtomType::Cslg(a)) => a.composition_to_dts_shift.max(0),
+            _ => 0
+        };
+
+        // Get sync samples, if present. Otherwise all samples are sync samples.
+        let stss = match offset_atoms.get("stss") {
+            Some(AtomType::Stss(a)) => Some(a),
+            _ => None
+        };
+
+        // Get sample dependency flags, if present.
+        let sdtp = match offset_atoms.get("sdtp") {
+            Some(AtomType::Sdtp(a)) => a.sample_flags_table(),
+            _ => &[]
+        };
+
+        // Avoid division by 0 for timestamps
+        let scale = time_scale.max(1) as f64;
+        let durations = stts.durations();
+        let timestamps = timestamps(&durations, &ctts, shift);
+
+        let offsets: Vec<SampleOffset> = durations
             .iter()
             .zip(stsz.sizes().iter())
             .zip(sample_offsets.iter())
-            .map(|((duration_ticks, size), position)| {
+            .zip(timestamps.iter())
+            .enumerate()
+            .map(|(i, (((duration_ticks, size), (position, chunk)), (dts, pts)))| {
                 SampleOffset::new(
                     *position,
                     *size,
@@ -176,6 +262,18 @@ impl SampleOffsets {
                     time_scale,
                     time_scale_zero_ok
                 )
+                .with_chunk(*chunk)
+                .with_timestamps(
+                    Duration::seconds_f64(*dts as f64 / scale),
+                    Duration::seconds_f64(*pts as f64 / scale),
+                )
+                .with_dependency(
+                    match stss {
+                        Some(s) => s.is_sync(i as u32 + 1),
+                        None => true,
+                    },
+                    sdtp.get(i).copied().unwrap_or_default(),
+                )
             })
             .collect();
 
@@ -207,6 +305,25 @@ impl SampleOffsets {
     }
 }
 
+/// Unscaled decode and presentation timestamps as `(DTS, PTS)`,
+/// one for each sample duration (`stts`).
+///
+/// DTS is the sum of preceding sample durations minus composition
+/// shift `shift` (`cslg`), PTS the sum of preceding sample durations
+/// plus the sample's composition offset (`ctts`), if any.
+fn timestamps(durations: &[u32], ctts: &[i32], shift: i64) -> Vec<(i64, i64)> {
+    let mut decode_ticks = 0_i64;
+    durations.iter()
+        .enumerate()
+        .map(|(i, duration)| {
+            let dts = decode_ticks - shift;
+            let pts = decode_ticks + ctts.get(i).copied().unwrap_or(0) as i64;
+            decode_ticks += *duration as i64;
+            (dts, pts)
+        })
+        .collect()
+}
+
 /// Sample offset consisting of byte offset,
 /// (extracted from `stco` if 32bit or `co64` if 64bit atoms),
 /// size in bytes (extracted from `stsz` atom),
@@ -215,6 +332,9 @@ impl SampleOffsets {
 pub struct SampleOffset {
     /// Offset in bytes from start of file
     /// (extracted from `stco` atom).
+    /// For an `Mp4Sequence` this is the offset from the start
+    /// of the first chapter, as if all chapter files were concatenated
+    /// (see `Mp4Sequence::chapter_position()`).
     // pub position: u32,
     pub position: u64,
     /// Size of chunk in bytes
@@ -222,7 +342,32 @@ pub struct SampleOffset {
     pub size: u32,
     /// The sample's duration, scaled according
     /// to the track's time scale (located in `mdhd` atom for each track).
-    pub duration: Duration
+    pub duration: Duration,
+    /// 0-based index for the chunk that contains the sample
+    /// (i.e. index into `stco`/`co64` chunk offsets).
+    pub chunk: u32,
+    /// 0-based index for the file that contains the sample.
+    /// Always 0, except for an `Mp4Sequence`
+    /// where this is the chapter index.
+    pub file: usize,
+    /// Decode timestamp (DTS), relative to the start of the track.
+    /// The sum of preceding sample durations (`stts`),
+    /// minus the composition shift (`cslg`), if any.
+    /// With a composition shift, DTS is negative for
+    /// the first samples.
+    pub dts: Duration,
+    /// Presentation timestamp (PTS), relative to the start of the track.
+    /// The sum of preceding sample durations (`stts`),
+    /// plus the sample's composition offset (`ctts`), if any.
+    /// Differs from `dts` for e.g. video with B-frames.
+    pub pts: Duration,
+    /// Sync sample (key frame), i.e. the sample can be decoded
+    /// without any other sample (`stss`).
+    /// `true` for all samples if the track has no `stss`.
+    pub sync: bool,
+    /// Sample dependency flags (`sdtp`).
+    /// All unknown if the track has no `sdtp`.
+    pub dependency: SampleDependency,
 }
 
 impl SampleOffset {
@@ -242,6 +387,127 @@ impl SampleOffset {
             time_scale = 1;
         }
         let duration = Duration::seconds_f64(duration_ticks as f64 / time_scale as f64);
-        Self{position, size, duration}
+        Self{
+            position,
+            size,
+            duration,
+            chunk: 0,
+            file: 0,
+            dts: Duration::ZERO,
+            pts: Duration::ZERO,
+            sync: true,
+            dependency: SampleDependency::default(),
+        }
+    }
+
+    /// Set index for the file that contains the sample.
+    pub fn with_file(self, file: usize) -> Self {
+        Self {
+            file,
+            ..self
+        }
+    }
+
+    /// Set index for the chunk that contains the sample.
+    pub fn with_chunk(self, chunk: u32) -> Self {
+        Self {
+            chunk,
+            ..self
+        }
+    }
+
+    /// Set decode (DTS) and presentation (PTS) timestamps.
+    pub fn with_timestamps(self, dts: Duration, pts: Duration) -> Self {
+        Self {
+            dts,
+            pts,
+            ..self
+        }
+    }
+
+    /// Set whether the sample is a sync sample,
+    /// and its sample dependency flags.
+    pub fn with_dependency(self, sync: bool, dependency: SampleDependency) -> Self {
+        Self {
+            sync,
+            dependency,
+            ..self
+        }
+    }
+
+    /// Returns `true` if the sample is a sync sample (key frame).
+    pub fn is_sync(&self) -> bool {
+        self.sync
+    }
+
+    /// Returns `true` if the sample is a leading sample.
+    /// `None` if unknown. See `SampleDependency::is_leading()`.
+    pub fn is_leading(&self) -> Option<bool> {
+        self.dependency.is_leading()
+    }
+
+    /// Returns `true` if the sample depends on other samples.
+    /// `None` if unknown. See `SampleDependency::depends_on()`.
+    pub fn depends_on(&self) -> Option<bool> {
+        self.dependency.depends_on()
+    }
+
+    /// Returns `true` if other samples depend on the sample.
+    /// `None` if unknown. See `SampleDependency::is_depended_on()`.
+    pub fn is_depended_on(&self) -> Option<bool> {
+        self.dependency.is_depended_on()
+    }
+
+    /// Returns `true` if the sample contains redundant coding.
+    /// `None` if unknown. See `SampleDependency::has_redundancy()`.
+    pub fn has_redundancy(&self) -> Option<bool> {
+        self.dependency.has_redundancy()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_without_ctts() {
+        assert_eq!(
+            timestamps(&[1001, 1001, 1001], &[], 0),
+            vec![(0, 0), (1001, 1001), (2002, 2002)]
+        );
+    }
+
+    #[test]
+    fn timestamps_unsigned_ctts() {
+        // I P B B, version 0 offsets
+        let ctts = [1001, 4004, 1001, 2002];
+        assert_eq!(
+            timestamps(&[1001; 4], &ctts, 0),
+            vec![(0, 1001), (1001, 5005), (2002, 3003), (3003, 5005)]
+        );
+    }
+
+    #[test]
+    fn timestamps_signed_ctts_with_shift() {
+        // Version 1 offsets, shifted by -1001, and cslg shift 1001
+        let ctts = [0, 3003, 0, 1001];
+        let ts = timestamps(&[1001; 4], &ctts, 1001);
+        assert_eq!(ts, vec![(-1001, 0), (0, 4004), (1001, 2002), (2002, 4004)]);
+        // Decode timestamps never exceed presentation timestamps
+        assert!(ts.iter().all(|(dts, pts)| dts <= pts));
+
+        let ctts = [-1001, 2002, -1001, 0];
+        let ts = timestamps(&[1001; 4], &ctts, 1001);
+        assert_eq!(ts[0], (-1001, -1001));
+        assert!(ts.iter().all(|(dts, pts)| dts <= pts));
+    }
+
+    #[test]
+    fn timestamps_short_ctts() {
+        // Missing composition offsets default to 0
+        assert_eq!(
+            timestamps(&[1001, 1001], &[2002], 0),
+            vec![(0, 2002), (1001, 1001)]
+        );
     }
 }
@@ -1,11 +1,13 @@
 //! Track sample. Wrapper over in-memory buffer `Cursor<Vec<u8>>`,
 //! complete with sample duration and relative timestamp.
+//!
+//! `SampleRef` is the borrowed, zero-copy equivalent for MP4s
+//! that are already in memory or memory-mapped.
 
 use std::io::{BufRead, Cursor, Read, Seek, SeekFrom};
 
 use time::Duration;
 
-use crate::{Mp4Error, Mp4Reader, TargetReader};
 
 use super::SampleOffset;
 
@@ -13,6 +15,8 @@ use super::SampleOffset;
 pub struct Sample {
     relative_time: Duration,
     sample_duration: Duration,
+    dts: Duration,
+    pts: Duration,
     reader: Cursor<Vec<u8>>
 }
 
@@ -45,27 +49,19 @@ impl BufRead for Sample {
 }
 
 impl Sample {
-    pub(crate) fn new(
-        reader: &mut Mp4Reader,
-        sample_offset: SampleOffset,
-    ) -> Result<Sample, Mp4Error> {
-
-        let file_pos_abs = i64::try_from(reader.pos(&TargetReader::File)?)?;
-        let file_pos_abs_next = i64::try_from(sample_offset.position)?;
-        // Possibly wrong assumption that relative seek may be faster
-        // on at least spinning disks...
-        let seek = SeekFrom::Current(file_pos_abs_next - file_pos_abs);
-
-        let mut sample = Self::default();
-        sample.reader = reader.cursor(
-            &TargetReader::File,
-            sample_offset.size as usize,
-            Some(seek),
-            None
-        )?;
-        sample.sample_duration = sample_offset.duration;
-
-        Ok(sample)
+    /// Sample from raw bytes already read
+    /// at the position specified by `sample_offset`.
+    pub(crate) fn from_bytes(
+        bytes: Vec<u8>,
+        sample_offset: &SampleOffset,
+    ) -> Self {
+        Self {
+            sample_duration: sample_offset.duration,
+            dts: sample_offset.dts,
+            pts: sample_offset.pts,
+            reader: Cursor::new(bytes),
+            ..Self::default()
+        }
     }
 
     /// Set relative timestamp,
@@ -77,6 +73,14 @@ impl Sample {
         }
     }
 
+    /// Set sample duration, e.g. when trimmed by an edit.
+    pub(crate) fn with_duration(self, sample_duration: Duration) -> Self {
+        Self {
+            sample_duration,
+            ..self
+        }
+    }
+
     /// Returns sample duration.
     pub fn duration(&self) -> Duration {
         self.sample_duration
@@ -87,6 +91,18 @@ impl Sample {
         self.relative_time
     }
 
+    /// Returns decode timestamp (DTS).
+    /// See `SampleOffset::dts`.
+    pub fn dts(&self) -> Duration {
+        self.dts
+    }
+
+    /// Returns presentation timestamp (PTS).
+    /// See `SampleOffset::pts`.
+    pub fn pts(&self) -> Duration {
+        self.pts
+    }
+
     /// Returns relative time since start of video
     /// sample duration as the tuple
     /// `(RELATIVE_TIME, SAMPLE_DURATION)`.
@@ -104,3 +120,92 @@ impl Sample {
         self.reader.get_ref().len()
     }
 }
+
+/// Borrowed track sample, i.e. a slice of an MP4
+/// that is already in memory or memory-mapped,
+/// complete with sample duration and relative timestamp.
+#[derive(Debug, Clone, Copy)]
+pub struct SampleRef<'a> {
+    relative_time: Duration,
+    sample_duration: Duration,
+    dts: Duration,
+    pts: Duration,
+    data: &'a [u8]
+}
+
+impl <'a> From<SampleRef<'a>> for Sample {
+    fn from(value: SampleRef<'a>) -> Self {
+        Self {
+            relative_time: value.relative_time,
+            sample_duration: value.sample_duration,
+            dts: value.dts,
+            pts: value.pts,
+            reader: Cursor::new(value.data.to_owned()),
+        }
+    }
+}
+
+impl <'a> SampleRef<'a> {
+    pub(crate) fn new(
+        data: &'a [u8],
+        sample_offset: &SampleOffset,
+        relative_time: Duration,
+    ) -> Self {
+        Self {
+            relative_time,
+            sample_duration: sample_offset.duration,
+            dts: sample_offset.dts,
+            pts: sample_offset.pts,
+            data
+        }
+    }
+
+    /// Returns sample duration.
+    pub fn duration(&self) -> Duration {
+        self.sample_duration
+    }
+
+    /// Returns relative time since start of video.
+    pub fn relative(&self) -> Duration {
+        self.relative_time
+    }
+
+    /// Returns decode timestamp (DTS).
+    /// See `SampleOffset::dts`.
+    pub fn dts(&self) -> Duration {
+        self.dts
+    }
+
+    /// Returns presentation timestamp (PTS).
+    /// See `SampleOffset::pts`.
+    pub fn pts(&self) -> Duration {
+        self.pts
+    }
+
+    /// Returns relative time since start of video
+    /// sample duration as the tuple
+    /// `(RELATIVE_TIME, SAMPLE_DURATION)`.
+    pub fn time(&self) -> (Duration, Duration) {
+        (self.relative(), self.duration())
+    }
+
+    /// Returns the raw bytes as a slice.
+    pub fn raw(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns a reader over the raw bytes.
+    pub fn cursor(&self) -> Cursor<&'a [u8]> {
+        Cursor::new(self.data)
+    }
+
+    /// Sample size in bytes.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns `true` if the sample is empty.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
@@ -7,26 +7,27 @@
 //! let gopro_gpmf_track = Mp4::new(&path).unwrap().track("GoPro MET");
 //! ```
 
-use std::io::{Cursor, SeekFrom};
+use std::{collections::{BTreeMap, HashMap}, fs::File, io::{Cursor, Read, Seek, SeekFrom}, sync::Mutex};
 
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use time::{Duration, PrimitiveDateTime};
 
 use crate::{AudioFormat, Mp4, Mp4Error, Mp4Reader, SampleOffset, TargetReader, Tmcd, VideoFormat};
 
-use super::{attributes::TrackAttributes, sample::Sample};
+use super::{attributes::TrackAttributes, chunk::Chunk, edit::Edit, sample::{Sample, SampleRef}};
 
 #[derive(Debug)]
-pub struct Track<'a> {
+pub struct Track<'a, R = File> {
     /// Attributes
     pub attributes: TrackAttributes,
 
-    /// Borrowed reader over the MP4-file.
-    pub(crate) reader: &'a mut Mp4Reader
+    /// Borrowed reader over the MP4.
+    pub(crate) reader: &'a mut Mp4Reader<R>
 }
 
-impl <'a> Track<'a> {
+impl <'a, R: Read + Seek> Track<'a, R> {
     pub fn new(
-        mp4: &'a mut Mp4,
+        mp4: &'a mut Mp4<R>,
         // identifier: TrackIdentifier,
         identifier: impl ParsableTrackId,
         reset: bool
@@ -51,7 +52,7 @@ impl <'a> Track<'a> {
     /// Returns track with
     /// specified numerical ID.
     pub fn from_id(
-        mp4: &'a mut Mp4,
+        mp4: &'a mut Mp4<R>,
         id: u32,
         reset: bool
     ) -> Result<Self, Mp4Error> {
@@ -63,7 +64,7 @@ impl <'a> Track<'a> {
     /// e.g. "GoPro MET" for
     /// GoPro timed telemetry track.
     pub fn from_name(
-        mp4: &'a mut Mp4,
+        mp4: &'a mut Mp4<R>,
         name: &str,
         reset: bool
     ) -> Result<Self, Mp4Error> {
@@ -74,7 +75,7 @@ impl <'a> Track<'a> {
     /// e.g. `vide` for video track
     /// (more than one may exist).
     pub fn from_subtype(
-        mp4: &'a mut Mp4,
+        mp4: &'a mut Mp4<R>,
         subtype: &str,
         reset: bool
     ) -> Result<Self, Mp4Error> {
@@ -82,7 +83,7 @@ impl <'a> Track<'a> {
     }
 
     pub fn from_attributes(
-        mp4: &'a mut Mp4,
+        mp4: &'a mut Mp4<R>,
         attributes: TrackAttributes,
     ) -> Result<Self, Mp4Error> {
         mp4.reset()?;
@@ -112,11 +113,49 @@ impl <'a> Track<'a> {
         self.attributes.id
     }
 
-    pub fn creation_time(&self) -> PrimitiveDateTime {
+    /// Track references (`tref`), as reference type,
+    /// e.g. `tmcd`, and referenced track IDs.
+    pub fn references(&self) -> &BTreeMap<String, Vec<u32>> {
+        self.attributes.references()
+    }
+
+    /// Edits from the edit list (`edts/elst`).
+    /// Empty if there is no edit list.
+    pub fn edits(&self) -> &[Edit] {
+        self.attributes.edits()
+    }
+
+    /// Presentation time for media time 0,
+    /// i.e. the offset to add to timestamps from `Track::samples()`
+    /// to align the track with other tracks.
+    /// See `TrackAttributes::presentation_offset()`.
+    pub fn presentation_offset(&self) -> Duration {
+        self.attributes.presentation_offset()
+    }
+
+    /// Returns sync samples (key frames) as `(SAMPLE_INDEX, OFFSET)`.
+    /// See `TrackAttributes::keyframes()`.
+    pub fn keyframes(&self) -> impl Iterator<Item = (usize, &SampleOffset)> {
+        self.attributes.keyframes()
+    }
+
+    /// Returns the last sync sample (key frame) at or before `time`,
+    /// as `(SAMPLE_INDEX, OFFSET)`, or the first sync sample
+    /// if `time` precedes all sync samples.
+    /// See `TrackAttributes::nearest_keyframe()`.
+    pub fn nearest_keyframe(&self, time: Duration) -> Option<(usize, &SampleOffset)> {
+        self.attributes.nearest_keyframe(time)
+    }
+
+    /// Creation datetime for this track.
+    /// `None` if outside supported datetime range.
+    pub fn creation_time(&self) -> Option<PrimitiveDateTime> {
         self.attributes.creation_time
     }
 
-    pub fn modification_time(&self) -> PrimitiveDateTime {
+    /// Modification datetime for this track.
+    /// `None` if outside supported datetime range.
+    pub fn modification_time(&self) -> Option<PrimitiveDateTime> {
         self.attributes.modification_time
     }
 
@@ -151,22 +190,145 @@ impl <'a> Track<'a> {
     }
 
     /// Returns an iterator over the track's samples.
+    ///
+    /// Samples are read one chunk at a time (see `stco`/`co64`),
+    /// i.e. all samples in a chunk are read in a single request.
     pub fn samples(&'a mut self) -> impl Iterator<Item = Result<Sample, Mp4Error>> + 'a {
+        let Self { attributes, reader } = self;
+        let offsets = attributes.offsets();
+
         // Keep track of relative timestamp
         let mut t = Duration::ZERO;
+        // Current chunk
+        let mut chunk: Option<Chunk> = None;
 
-        self.attributes.offsets
-            .iter()
-            .map(move |offset| {
+        offsets.iter()
+            .enumerate()
+            .map(move |(i, offset)| {
                 let rel_t = t;
                 t += offset.duration; // add delta to relative time for next iteration
-                (offset, rel_t)
+
+                if chunk.as_ref().map(|c| c.index) != Some(offset.chunk) {
+                    chunk = Some(Chunk::read(reader, &offsets[i..])?);
+                }
+
+                chunk.as_ref()
+                    .ok_or(Mp4Error::SampleOffsetError)?
+                    .sample(offset)
+                    .map(|s| s.with_time(rel_t))
             })
-            .map(|(offset, rel_t)| {
-                Sample::new(
-                    &mut self.reader,
-                    offset.to_owned(),
-                ).map(|s| s.with_time(rel_t))
+    }
+
+    /// Returns an iterator over the track's samples
+    /// according to the edit list (`edts/elst`),
+    /// i.e. the presentation timeline shown by players.
+    ///
+    /// Samples edited out are dropped, and relative timestamps
+    /// are presentation timestamps (see `Sample::pts()`),
+    /// adjusted for any start delay (empty edits) and media offset.
+    /// Sample durations are trimmed to the edit, and scaled for
+    /// edits with a rate other than 1.0.
+    /// Samples are yielded in sample order. A sample presented by several
+    /// edits is yielded once for each, and a dwell (rate 0) yields
+    /// the sample with the edit's duration.
+    ///
+    /// Without an edit list, relative timestamps are the samples'
+    /// presentation timestamps, i.e. the same as `Track::samples()`
+    /// for tracks without composition offsets (`ctts`).
+    pub fn samples_presented(&'a mut self) -> impl Iterator<Item = Result<Sample, Mp4Error>> + 'a {
+        let mut presentation = self.attributes.presentation().into_iter().peekable();
+
+        self.samples()
+            .enumerate()
+            .flat_map(move |(i, result)| {
+                let mut presented: Vec<(Duration, Duration)> = Vec::new();
+                while let Some((_, start, duration)) = presentation.next_if(|(j, ..)| *j == i) {
+                    presented.push((start, duration));
+                }
+                match result {
+                    Ok(sample) => {
+                        // Only clone for samples presented more than once
+                        let last = presented.pop();
+                        let mut samples: Vec<Result<Sample, Mp4Error>> = presented.into_iter()
+                            .map(|(start, duration)| Ok(sample.to_owned().with_time(start).with_duration(duration)))
+                            .collect();
+                        if let Some((start, duration)) = last {
+                            samples.push(Ok(sample.with_time(start).with_duration(duration)));
+                        }
+                        samples
+                    },
+                    Err(err) => vec![Err(err)],
+                }
+            })
+    }
+
+    /// Returns an iterator over the track's samples
+    /// in presentation order, i.e. sorted by presentation timestamp
+    /// (see `Sample::pts()`), with relative timestamps set
+    /// to the presentation timestamp.
+    ///
+    /// For video with B-frames (composition offsets in `ctts`),
+    /// decode order differs from presentation order.
+    /// Samples are still read in decode order, and are buffered
+    /// until all samples preceding them in presentation order
+    /// have been read. Otherwise, this is the same as `Track::samples()`.
+    pub fn samples_in_presentation_order(&'a mut self) -> impl Iterator<Item = Result<Sample, Mp4Error>> + 'a {
+        // Sample indices in presentation order, stable for equal timestamps
+        let mut order: Vec<usize> = (0 .. self.attributes.offsets.len()).collect();
+        order.sort_by_key(|i| self.attributes.offsets.offsets[*i].pts);
+        let mut order = order.into_iter().peekable();
+
+        let mut buffer: HashMap<usize, Result<Sample, Mp4Error>> = HashMap::new();
+
+        self.samples()
+            .enumerate()
+            .flat_map(move |(i, result)| {
+                buffer.insert(i, result);
+
+                let mut ready: Vec<Result<Sample, Mp4Error>> = Vec::new();
+                while let Some(result) = order.peek().and_then(|j| buffer.remove(j)) {
+                    order.next();
+                    ready.push(result.map(|s| {
+                        let pts = s.pts();
+                        s.with_time(pts)
+                    }));
+                }
+                ready
+            })
+    }
+
+    /// Returns a parallel iterator over the track's samples,
+    /// with the same relative timestamps as `Track::samples()`.
+    ///
+    /// Samples are batched by chunk (see `stco`/`co64`),
+    /// with chunks processed in parallel via `rayon`.
+    /// Reads from the underlying reader are serialized,
+    /// but splitting chunks into samples, and any work chained
+    /// on the iterator, e.g. parsing telemetry, runs on all threads.
+    /// Collecting into a `Vec` preserves sample order.
+    ///
+    /// See `TrackHandle::par_samples()` for parallel reads via positional reads.
+    pub fn par_samples(&mut self) -> impl ParallelIterator<Item = Result<Sample, Mp4Error>> + '_
+    where
+        R: Send
+    {
+        let Self { attributes, reader } = self;
+        let offsets = attributes.offsets();
+        let reader = Mutex::new(&mut **reader);
+
+        Chunk::ranges(offsets)
+            .into_par_iter()
+            .flat_map_iter(move |(range, t)| {
+                let chunk = {
+                    // Chunk::read() seeks relative to current position,
+                    // so a poisoned lock still leaves the reader usable.
+                    let mut rdr = reader.lock().unwrap_or_else(|e| e.into_inner());
+                    Chunk::read(&mut rdr, &offsets[range.to_owned()])
+                };
+                match chunk {
+                    Ok(chunk) => chunk.samples(&offsets[range], t),
+                    Err(err) => vec![Err(err)],
+                }
             })
     }
 
@@ -234,6 +396,22 @@ impl <'a> Track<'a> {
     }
 }
 
+impl <'a, T: AsRef<[u8]>> Track<'a, Cursor<T>> {
+    /// Returns an iterator over the track's samples
+    /// as borrowed slices of the underlying
+    /// in-memory or memory-mapped MP4, without copying
+    /// sample data (see `Mp4::open_mmap()`).
+    pub fn sample_slices(&self) -> impl Iterator<Item = Result<SampleRef<'_>, Mp4Error>> + '_ {
+        self.timestamps()
+            .zip(self.attributes.offsets.iter())
+            .map(|((rel_t, _), offset)| {
+                self.reader
+                    .slice(offset.position, offset.size as usize)
+                    .map(|data| SampleRef::new(data, offset, rel_t))
+            })
+    }
+}
+
 /// Represents ways to identify a track:
 /// - Name: String extracted from `hdlr` atom (`handler_name`).
 /// - ID: Numerical ID extracted from `tkhd` atom.
@@ -322,3 +500,70 @@ impl <'a> ParsableTrackId for TrackIdentifier<'a> {
         self.to_owned()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{be_u32, container, full_atom, mp4, TestTrack};
+
+    use super::*;
+
+    #[test]
+    fn sample_slices_from_slice() {
+        // Samples with different sizes, several per chunk
+        let track = TestTrack::new(1, "Test", vec![
+            vec![vec![1; 3], vec![2; 5]],
+            vec![vec![3; 1]],
+            vec![vec![4; 2], vec![5; 4], vec![6; 6]],
+        ]);
+        let bytes = mp4(&[track.to_owned()]);
+        let mut mp4 = Mp4::from_slice(&bytes).unwrap();
+
+        let slices: Vec<(Vec<u8>, Duration, Duration, Duration, Duration)> = mp4.track(1_u32, true).unwrap()
+            .sample_slices()
+            .map(|s| {
+                let s = s.unwrap();
+                (s.raw().to_vec(), s.relative(), s.duration(), s.dts(), s.pts())
+            })
+            .collect();
+
+        let samples: Vec<(Vec<u8>, Duration, Duration, Duration, Duration)> = mp4.track(1_u32, true).unwrap()
+            .samples()
+            .map(|s| {
+                let s = s.unwrap();
+                (s.raw().to_vec(), s.relative(), s.duration(), s.dts(), s.pts())
+            })
+            .collect();
+
+        assert_eq!(slices, samples);
+        assert_eq!(
+            slices.iter().map(|s| s.0.to_owned()).collect::<Vec<_>>(),
+            track.samples()
+        );
+        // 100 ticks per sample, time scale 1000
+        assert_eq!(slices[5].1, Duration::milliseconds(500));
+        assert!(slices.iter().all(|s| s.2 == Duration::milliseconds(100)));
+    }
+
+    #[test]
+    fn samples_presented_repeated() {
+        let mut track = TestTrack::new(1, "Test", vec![vec![vec![1; 2], vec![2; 2], vec![3; 2]]]);
+        // Second sample, a 50ms dwell on the first sample, then second and third sample
+        track.atoms.push(container(b"edts", &[full_atom(b"elst", 0, &be_u32(&[
+            3,
+            100, 100, 0x0001_0000,
+            50, 0, 0,
+            200, 100, 0x0001_0000,
+        ]))]));
+        let bytes = mp4(&[track]);
+        let mut mp4 = Mp4::from_slice(&bytes).unwrap();
+
+        let presented: Vec<(u8, i64, i64)> = mp4.track(1_u32, true).unwrap()
+            .samples_presented()
+            .map(|s| {
+                let s = s.unwrap();
+                (s.raw()[0], s.relative().whole_milliseconds() as i64, s.duration().whole_milliseconds() as i64)
+            })
+            .collect();
+        assert_eq!(presented, vec![(1, 100, 50), (2, 0, 100), (2, 150, 100), (3, 250, 100)]);
+    }
+}
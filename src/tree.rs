@@ -0,0 +1,472 @@
+//! Owned atom tree, built once from the MP4.
+//!
+//! Navigating via `Mp4::find_atom()` etc depends on the current reader position.
+//! `AtomTree` instead lists all atoms with absolute byte offsets,
+//! together with their depth, parent, and children, so that any atom
+//! can be accessed independently of reader position via `Mp4::atom_at()`.
+//!
+//! ```rs
+//! use mp4iter::Mp4;
+//! use std::path::Path;
+//!
+//! fn main() -> Result<(), mp4iter::Mp4Error> {
+//!     let mut mp4 = Mp4::new(Path::new("VIDEO.MP4"))?;
+//!     let tree = mp4.tree()?;
+//!     for node in tree.find("tkhd") {
+//!         let tkhd = mp4.atom_at(node.header())?.tkhd()?;
+//!         println!("{} {:?}", tree.path(node), tkhd);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Atoms can also be selected via a path query (see `AtomPath`):
+//!
+//! ```rs
+//! // `stsd` atoms for all tracks
+//! let stsd = mp4.select("moov/trak[*]/mdia/minf/stbl/stsd")?;
+//! // `hdlr` atom for the third track (0-based index)
+//! let hdlr = mp4.select_atom("moov/trak[2]/mdia/hdlr")?.hdlr()?;
+//! // All `stsd` atoms, regardless of depth
+//! let stsd = mp4.select("//stsd")?;
+//! ```
+
+use std::{io::{Read, Seek}, ops::Range};
+
+use crate::{iterator::Mp4Iterator, AtomHeader, FourCC, Mp4Error, Mp4Reader};
+
+/// Atom in an `AtomTree`.
+#[derive(Debug, Clone)]
+pub struct AtomNode {
+    /// Atom header, with absolute offsets.
+    pub(crate) header: AtomHeader,
+    /// Index in `AtomTree`.
+    pub(crate) index: usize,
+    /// Nesting depth. Top-level atoms have depth 0.
+    pub(crate) depth: usize,
+    /// Index for parent atom.
+    pub(crate) parent: Option<usize>,
+    /// Indices for child atoms.
+    pub(crate) children: Vec<usize>,
+}
+
+impl AtomNode {
+    /// Atom header, with absolute offsets.
+    pub fn header(&self) -> &AtomHeader {
+        &self.header
+    }
+
+    /// Atom FourCC.
+    pub fn name(&self) -> &FourCC {
+        &self.header.name
+    }
+
+    /// Index in `AtomTree`.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Nesting depth. Top-level atoms have depth 0.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Index for parent atom.
+    /// `None` for top-level atoms.
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+
+    /// Indices for child atoms.
+    pub fn children(&self) -> &[usize] {
+        &self.children
+    }
+
+    /// Absolute byte range for the atom, header included.
+    pub fn range(&self) -> Range<u64> {
+        self.header.bounds()
+    }
+
+    /// Absolute byte range for the atom's data load.
+    pub fn data_range(&self) -> Range<u64> {
+        self.header.data_offset() .. self.header.end()
+    }
+
+    /// Returns `true` if the atom is a container.
+    pub fn is_container(&self) -> bool {
+        self.header.is_container()
+    }
+}
+
+/// All atoms in an MP4, in file order
+/// (i.e. a parent precedes its children).
+#[derive(Debug, Clone, Default)]
+pub struct AtomTree {
+    nodes: Vec<AtomNode>,
+}
+
+impl AtomTree {
+    /// Builds the atom tree. Atoms in `moov` are read
+    /// via the in-memory `moov` reader.
+    /// Reader positions are not restored.
+    pub(crate) fn new<R: Read + Seek>(reader: &mut Mp4Reader<R>) -> Result<Self, Mp4Error> {
+        let mut tree = Self::default();
+        // Index for the most recent atom at each depth
+        let mut ancestors: Vec<usize> = Vec::new();
+
+        for result in Mp4Iterator::new(reader) {
+            let (depth, _, header) = result?;
+            ancestors.truncate(depth);
+            let parent = ancestors.last().copied();
+
+            let index = tree.nodes.len();
+            tree.nodes.push(AtomNode {
+                header,
+                index,
+                depth,
+                parent,
+                children: Vec::new(),
+            });
+            if let Some(p) = parent {
+                tree.nodes[p].children.push(index);
+            }
+            ancestors.push(index);
+        }
+
+        Ok(tree)
+    }
+
+    /// Number of atoms.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` if the tree contains no atoms.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns atom at `index`.
+    pub fn get(&self, index: usize) -> Option<&AtomNode> {
+        self.nodes.get(index)
+    }
+
+    /// Iterates over all atoms in file order.
+    pub fn iter(&self) -> impl Iterator<Item = &AtomNode> {
+        self.nodes.iter()
+    }
+
+    /// Iterates over top-level atoms.
+    pub fn roots(&self) -> impl Iterator<Item = &AtomNode> {
+        self.nodes.iter()
+            .filter(|n| n.parent.is_none())
+    }
+
+    /// Returns parent of `node`.
+    pub fn parent(&self, node: &AtomNode) -> Option<&AtomNode> {
+        node.parent.and_then(|p| self.get(p))
+    }
+
+    /// Iterates over the children of `node`.
+    pub fn children<'a>(&'a self, node: &'a AtomNode) -> impl Iterator<Item = &'a AtomNode> {
+        node.children.iter()
+            .filter_map(|c| self.get(*c))
+    }
+
+    /// Iterates over ancestors of `node`,
+    /// starting with its parent.
+    pub fn ancestors<'a>(&'a self, node: &'a AtomNode) -> impl Iterator<Item = &'a AtomNode> {
+        std::iter::successors(self.parent(node), |n| self.parent(n))
+    }
+
+    /// Iterates over all atoms nested within `node`,
+    /// in file order.
+    pub fn descendants<'a>(&'a self, node: &'a AtomNode) -> impl Iterator<Item = &'a AtomNode> {
+        // Descendants directly follow their ancestor in file order
+        self.nodes[node.index + 1 ..].iter()
+            .take_while(move |n| n.depth > node.depth)
+    }
+
+    /// Iterates over all atoms with FourCC `name`, in file order.
+    pub fn find<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a AtomNode> {
+        let fourcc = FourCC::from_str(name);
+        self.nodes.iter()
+            .filter(move |n| n.header.name == fourcc)
+    }
+
+    /// Returns the first child of `node` with FourCC `name`.
+    pub fn child<'a>(&'a self, node: &'a AtomNode, name: &str) -> Option<&'a AtomNode> {
+        let fourcc = FourCC::from_str(name);
+        self.children(node)
+            .find(|n| n.header.name == fourcc)
+    }
+
+    /// Returns the innermost atom that contains
+    /// absolute position `pos`.
+    pub fn at(&self, pos: u64) -> Option<&AtomNode> {
+        self.nodes.iter()
+            .rfind(|n| n.header.contains(pos))
+    }
+
+    /// Returns all atoms matching the path query `path`,
+    /// in file order. See `AtomPath` for syntax.
+    pub fn select(&self, path: &str) -> Result<Vec<&AtomNode>, Mp4Error> {
+        let path = AtomPath::new(path)?;
+
+        // `None` represents the (virtual) root
+        let mut context: Vec<Option<usize>> = vec![None];
+        for step in path.steps.iter() {
+            let mut matches: Vec<usize> = Vec::new();
+            for parent in context.iter() {
+                let candidates: Vec<usize> = match (parent, step.descendant) {
+                    (None, false) => self.roots().map(|n| n.index).collect(),
+                    (None, true) => self.iter().map(|n| n.index).collect(),
+                    (Some(p), false) => self.nodes[*p].children.to_owned(),
+                    (Some(p), true) => self.descendants(&self.nodes[*p]).map(|n| n.index).collect(),
+                };
+                let mut candidates = candidates.into_iter()
+                    .filter(|i| step.matches(&self.nodes[*i]));
+                match step.index {
+                    Some(n) => matches.extend(candidates.nth(n)),
+                    None => matches.extend(candidates),
+                }
+            }
+            // Descendant steps may overlap
+            matches.sort_unstable();
+            matches.dedup();
+            context = matches.into_iter().map(Some).collect();
+        }
+
+        Ok(context.into_iter()
+            .flatten()
+            .map(|i| &self.nodes[i])
+            .collect())
+    }
+
+    /// Returns FourCC path from the top-level
+    /// down to `node`, e.g. `moov/trak/tkhd`.
+    pub fn path(&self, node: &AtomNode) -> String {
+        let mut names: Vec<&str> = self.ancestors(node)
+            .map(|n| n.header.name.to_str())
+            .collect();
+        names.reverse();
+        names.push(node.header.name.to_str());
+        names.join("/")
+    }
+}
+
+/// Path query for selecting atoms, e.g. `moov/trak[2]/mdia/hdlr`.
+///
+/// - `/` separates atoms, where each step matches the children
+///   of the atoms matched by the previous step.
+///   The first step matches top-level atoms. A leading `/` is optional.
+/// - `//` matches atoms at any depth below the previous step
+///   (or anywhere in the file if leading), e.g. `moov//stsd` or `//stsd`.
+/// - `*` matches any FourCC, e.g. `moov/*/tkhd`.
+/// - `[n]` selects the n:th match (0-based) within each parent,
+///   e.g. `moov/trak[2]` is the third track. `[*]` selects all matches,
+///   same as leaving out the index.
+///
+/// Paths only match FourCCs and positions, not atom content.
+/// E.g. "every `stsd` under a `trak` whose handler is `soun`"
+/// can not be expressed as a path, since the handler type is set in `hdlr`.
+/// Instead, select the tracks and filter these via `AtomTree`:
+///
+/// ```rs
+/// let tree = mp4.tree()?;
+/// for trak in tree.select("moov/trak")? {
+///     let Some(hdlr) = tree.child(trak, "mdia").and_then(|m| tree.child(m, "hdlr")) else {
+///         continue
+///     };
+///     if mp4.atom_at(hdlr.header())?.hdlr()?.component_sub_type() == "soun" {
+///         let stsd = tree.descendants(trak).filter(|n| n.name().to_str() == "stsd");
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtomPath {
+    steps: Vec<PathStep>,
+}
+
+/// Single step in an `AtomPath`.
+#[derive(Debug, Clone, PartialEq)]
+struct PathStep {
+    /// FourCC to match. `None` matches any FourCC.
+    name: Option<FourCC>,
+    /// Index among matches within each parent.
+    /// `None` selects all matches.
+    index: Option<usize>,
+    /// Match atoms at any depth, rather than only direct children.
+    descendant: bool,
+}
+
+impl PathStep {
+    fn matches(&self, node: &AtomNode) -> bool {
+        self.name.as_ref()
+            .map(|n| n == &node.header.name)
+            .unwrap_or(true)
+    }
+}
+
+impl AtomPath {
+    /// Parses path query.
+    pub fn new(path: &str) -> Result<Self, Mp4Error> {
+        let invalid = || Mp4Error::InvalidPath(path.to_owned());
+
+        let (mut descendant, rest) = match path.strip_prefix("//") {
+            Some(rest) => (true, rest),
+            None => (false, path.strip_prefix('/').unwrap_or(path)),
+        };
+
+        let mut steps: Vec<PathStep> = Vec::new();
+        for segment in rest.split('/') {
+            // Empty segment, i.e. `//`
+            if segment.is_empty() {
+                if descendant {
+                    return Err(invalid())
+                }
+                descendant = true;
+                continue;
+            }
+
+            let (name, index) = match segment.split_once('[') {
+                Some((name, index)) => {
+                    let index = index.strip_suffix(']').ok_or_else(invalid)?;
+                    match index {
+                        "*" => (name, None),
+                        i => (name, Some(i.parse::<usize>().map_err(|_| invalid())?)),
+                    }
+                },
+                None => (segment, None),
+            };
+
+            let name = match name {
+                "*" => None,
+                n if n.chars().count() == 4 => Some(FourCC::from_str(n)),
+                _ => return Err(invalid()),
+            };
+
+            steps.push(PathStep { name, index, descendant });
+            descendant = false;
+        }
+
+        // Trailing `/`
+        if descendant || steps.is_empty() {
+            return Err(invalid())
+        }
+
+        Ok(Self { steps })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{testing::{mp4, TestTrack}, Mp4};
+
+    use super::*;
+
+    /// Atom tree for an MP4 with a sound track between two other tracks.
+    fn tree() -> (Vec<u8>, AtomTree) {
+        let chunks = vec![vec![vec![0; 4]]];
+        let bytes = mp4(&[
+            TestTrack::new(1, "One", chunks.to_owned()),
+            TestTrack { sub_type: *b"soun", ..TestTrack::new(2, "Two", chunks.to_owned()) },
+            TestTrack::new(3, "Three", chunks),
+        ]);
+        let tree = Mp4::from_slice(&bytes).unwrap().tree().unwrap();
+        (bytes, tree)
+    }
+
+    /// Paths for selected atoms.
+    fn select(tree: &AtomTree, path: &str) -> Vec<String> {
+        tree.select(path).unwrap()
+            .into_iter()
+            .map(|n| tree.path(n))
+            .collect()
+    }
+
+    /// Offsets for selected atoms.
+    fn offsets(tree: &AtomTree, path: &str) -> Vec<u64> {
+        tree.select(path).unwrap()
+            .into_iter()
+            .map(|n| n.header.offset)
+            .collect()
+    }
+
+    #[test]
+    fn parse() {
+        let step = |name: Option<&str>, index: Option<usize>, descendant: bool| PathStep {
+            name: name.map(FourCC::from_str),
+            index,
+            descendant,
+        };
+        assert_eq!(AtomPath::new("//stsd").unwrap().steps, vec![step(Some("stsd"), None, true)]);
+        assert_eq!(AtomPath::new("moov//stsd").unwrap().steps, vec![
+            step(Some("moov"), None, false),
+            step(Some("stsd"), None, true),
+        ]);
+        assert_eq!(AtomPath::new("moov/trak[2]/*").unwrap().steps, vec![
+            step(Some("moov"), None, false),
+            step(Some("trak"), Some(2), false),
+            step(None, None, false),
+        ]);
+        assert_eq!(AtomPath::new("/moov/trak[*]").unwrap(), AtomPath::new("moov/trak").unwrap());
+        // Non-ASCII FourCC
+        assert!(AtomPath::new("moov/udta/\u{a9}xyz").is_ok());
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for path in [
+            "", "/", "//", "a//", "moov//", "moov/", "///x", "moov///stsd",
+            "trak[", "trak[x]", "trak[-1]", "trak[1", "trak[1]x", "trak[1][2]",
+            "tra", "track", "moov/tra[0]", "moov/*x",
+        ] {
+            assert!(
+                matches!(AtomPath::new(path), Err(Mp4Error::InvalidPath(p)) if p == path),
+                "{path}"
+            );
+        }
+    }
+
+    #[test]
+    fn select_paths() {
+        let (_, tree) = tree();
+
+        assert_eq!(select(&tree, "*"), vec!["ftyp", "mdat", "moov"]);
+        assert_eq!(select(&tree, "/moov/mvhd"), vec!["moov/mvhd"]);
+        assert_eq!(select(&tree, "//stsd"), vec!["moov/trak/mdia/minf/stbl/stsd"; 3]);
+        assert_eq!(offsets(&tree, "moov//stsd"), offsets(&tree, "//stsd"));
+        assert_eq!(offsets(&tree, "moov/*/mdia/minf/stbl/stsd"), offsets(&tree, "//stsd"));
+        assert_eq!(offsets(&tree, "moov/trak[*]"), offsets(&tree, "moov/trak"));
+        assert_eq!(offsets(&tree, "moov/trak").len(), 3);
+
+        // Index within each parent
+        let trak = offsets(&tree, "moov/trak");
+        assert_eq!(offsets(&tree, "moov/trak[2]"), vec![trak[2]]);
+        assert!(offsets(&tree, "moov/trak[3]").is_empty());
+        assert_eq!(offsets(&tree, "//trak[0]/tkhd").len(), 1);
+        assert_eq!(offsets(&tree, "moov/trak/tkhd[0]").len(), 3);
+        // Overlapping descendant steps are not repeated
+        assert_eq!(offsets(&tree, "//*//stsd").len(), 3);
+
+        assert!(select(&tree, "moov/stsd").is_empty());
+        assert!(select(&tree, "free").is_empty());
+    }
+
+    #[test]
+    fn filter_by_handler() {
+        let (bytes, tree) = tree();
+        let mut mp4 = Mp4::from_slice(&bytes).unwrap();
+
+        let mut stsd: Vec<&AtomNode> = Vec::new();
+        for trak in tree.select("moov/trak").unwrap() {
+            let hdlr = tree.child(trak, "mdia").and_then(|m| tree.child(m, "hdlr")).unwrap();
+            if mp4.atom_at(hdlr.header()).unwrap().hdlr().unwrap().component_sub_type() == "soun" {
+                stsd.extend(tree.descendants(trak).filter(|n| n.name().to_str() == "stsd"));
+            }
+        }
+        assert_eq!(stsd.len(), 1);
+        assert_eq!(stsd[0].header.offset, offsets(&tree, "moov/trak[1]//stsd")[0]);
+    }
+}
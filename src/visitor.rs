@@ -0,0 +1,239 @@
+//! Visitor for custom atom processing
+//! in a single pass over the MP4.
+//!
+//! Implement `AtomVisitor` and pass it to `Mp4::visit()`.
+//! All callbacks have default implementations that do nothing,
+//! so only the relevant ones need to be implemented.
+//!
+//! ```rs
+//! use mp4iter::{Atom, AtomHeader, AtomVisitor, Mp4, Mp4Error, Visit};
+//! use std::{io::{Read, Seek}, path::Path};
+//!
+//! /// Collects handler names for all tracks.
+//! #[derive(Default)]
+//! struct Handlers(Vec<String>);
+//!
+//! impl AtomVisitor for Handlers {
+//!     fn enter_container(&mut self, _depth: usize, header: &AtomHeader) -> Result<Visit, Mp4Error> {
+//!         match header.name().to_str() {
+//!             "udta" => Ok(Visit::SkipChildren),
+//!             _ => Ok(Visit::Continue),
+//!         }
+//!     }
+//!
+//!     fn visit_leaf<R: Read + Seek>(
+//!         &mut self,
+//!         _depth: usize,
+//!         header: &AtomHeader,
+//!         atom: &mut Atom<'_, R>
+//!     ) -> Result<Visit, Mp4Error> {
+//!         if header.name().to_str() == "hdlr" {
+//!             self.0.push(atom.hdlr()?.component_name().to_owned());
+//!         }
+//!         Ok(Visit::Continue)
+//!     }
+//! }
+//!
+//! fn main() -> Result<(), Mp4Error> {
+//!     let mut mp4 = Mp4::new(Path::new("VIDEO.MP4"))?;
+//!     let mut handlers = Handlers::default();
+//!     mp4.visit(&mut handlers)?;
+//!     println!("{:?}", handlers.0);
+//!     Ok(())
+//! }
+//! ```
+
+use std::io::{Read, Seek};
+
+use crate::{iterator::Mp4Iterator, Atom, AtomHeader, Mp4Error};
+
+/// Returned by `AtomVisitor` callbacks
+/// to control the rest of the traversal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Visit {
+    /// Continue traversal.
+    #[default]
+    Continue,
+    /// Do not visit the child atoms of the container
+    /// just entered. Same as `Continue` for other callbacks.
+    SkipChildren,
+    /// End traversal.
+    Stop,
+}
+
+/// Callbacks for atoms, invoked in file order by `Mp4::visit()`.
+///
+/// Header offsets are absolute. `depth` is 0 for top-level atoms.
+pub trait AtomVisitor {
+    /// Invoked for container atoms (see `CONTAINER` and `AtomRegistry`),
+    /// before any of its child atoms.
+    fn enter_container(&mut self, _depth: usize, _header: &AtomHeader) -> Result<Visit, Mp4Error> {
+        Ok(Visit::Continue)
+    }
+
+    /// Invoked for non-container atoms, with the atom
+    /// positioned at its data load, for parsing
+    /// e.g. via `Atom::stsd()`.
+    fn visit_leaf<R: Read + Seek>(
+        &mut self,
+        _depth: usize,
+        _header: &AtomHeader,
+        _atom: &mut Atom<'_, R>
+    ) -> Result<Visit, Mp4Error> {
+        Ok(Visit::Continue)
+    }
+
+    /// Invoked for container atoms, after all of its child atoms.
+    /// Not invoked if `enter_container()` returned `Visit::SkipChildren`
+    /// or `Visit::Stop`.
+    fn exit_container(&mut self, _depth: usize, _header: &AtomHeader) -> Result<Visit, Mp4Error> {
+        Ok(Visit::Continue)
+    }
+}
+
+/// Drives `visitor` over all atoms in a single walk.
+pub(crate) fn visit<R: Read + Seek, V: AtomVisitor>(
+    walker: &mut Mp4Iterator<'_, R>,
+    visitor: &mut V,
+) -> Result<(), Mp4Error> {
+    // Entered containers with depth
+    let mut open: Vec<(usize, AtomHeader)> = Vec::new();
+
+    while let Some(result) = walker.next() {
+        let (depth, _, header) = result?;
+
+        // Exit containers that do not contain this atom
+        while let Some((d, _)) = open.last() {
+            if *d < depth {
+                break
+            }
+            if let Some((d, hdr)) = open.pop() {
+                if visitor.exit_container(d, &hdr)? == Visit::Stop {
+                    return Ok(())
+                }
+            }
+        }
+
+        let visit = match header.is_container() {
+            true => {
+                let visit = visitor.enter_container(depth, &header)?;
+                match visit {
+                    Visit::Continue => open.push((depth, header)),
+                    _ => walker.skip_children(),
+                }
+                visit
+            },
+            false => {
+                let mut atom = walker.atom(&header)?;
+                visitor.visit_leaf(depth, &header, &mut atom)?
+            },
+        };
+
+        if visit == Visit::Stop {
+            return Ok(())
+        }
+    }
+
+    while let Some((d, hdr)) = open.pop() {
+        if visitor.exit_container(d, &hdr)? == Visit::Stop {
+            break
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{testing::{mp4, TestTrack}, Mp4};
+
+    use super::*;
+
+    /// Records callbacks as `enter NAME`, `leaf NAME`, `exit NAME`,
+    /// and returns `visit` for callback and FourCC
+    /// in `control`.
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+        control: Vec<(&'static str, &'static str, Visit)>,
+    }
+
+    impl Recorder {
+        fn record(&mut self, callback: &'static str, header: &AtomHeader) -> Visit {
+            let name = header.name().to_str();
+            self.events.push(format!("{callback} {name}"));
+            self.control.iter()
+                .find(|(c, n, _)| *c == callback && *n == name)
+                .map(|(.., v)| *v)
+                .unwrap_or_default()
+        }
+    }
+
+    impl AtomVisitor for Recorder {
+        fn enter_container(&mut self, _depth: usize, header: &AtomHeader) -> Result<Visit, Mp4Error> {
+            Ok(self.record("enter", header))
+        }
+
+        fn visit_leaf<R: Read + Seek>(
+            &mut self,
+            _depth: usize,
+            header: &AtomHeader,
+            _atom: &mut Atom<'_, R>
+        ) -> Result<Visit, Mp4Error> {
+            Ok(self.record("leaf", header))
+        }
+
+        fn exit_container(&mut self, _depth: usize, header: &AtomHeader) -> Result<Visit, Mp4Error> {
+            Ok(self.record("exit", header))
+        }
+    }
+
+    /// Visits an MP4 with `tracks` tracks.
+    fn visit(tracks: u32, control: Vec<(&'static str, &'static str, Visit)>) -> Vec<String> {
+        let tracks: Vec<TestTrack> = (1 ..= tracks)
+            .map(|id| TestTrack::new(id, "Track", vec![vec![vec![1; 4]]]))
+            .collect();
+        let bytes = mp4(&tracks);
+        let mut mp4 = Mp4::from_slice(&bytes).unwrap();
+        let mut recorder = Recorder { control, ..Default::default() };
+        mp4.visit(&mut recorder).unwrap();
+        recorder.events
+    }
+
+    #[test]
+    fn nesting() {
+        let events = visit(2, Vec::new());
+        assert_eq!(events[..6], ["leaf ftyp", "leaf mdat", "enter moov", "leaf mvhd", "enter trak", "leaf tkhd"]);
+        assert_eq!(events[events.len() - 7 ..], [
+            "leaf stsz", "leaf stco", "exit stbl", "exit minf", "exit mdia", "exit trak", "exit moov"
+        ]);
+    }
+
+    #[test]
+    fn skip_children() {
+        let events = visit(2, vec![("enter", "mdia", Visit::SkipChildren)]);
+        assert_eq!(events[4..], [
+            "enter trak", "leaf tkhd", "enter mdia", "exit trak",
+            "enter trak", "leaf tkhd", "enter mdia", "exit trak",
+            "exit moov",
+        ]);
+    }
+
+    #[test]
+    fn stop_on_enter() {
+        let events = visit(2, vec![("enter", "trak", Visit::Stop)]);
+        assert_eq!(events[2..], ["enter moov", "leaf mvhd", "enter trak"]);
+    }
+
+    #[test]
+    fn stop_on_exit() {
+        // Containers for first track are exited on reaching the second track
+        let events = visit(2, vec![("exit", "stbl", Visit::Stop)]);
+        assert_eq!(events[events.len() - 2 ..], ["leaf stco", "exit stbl"]);
+        assert_eq!(events.iter().filter(|e| *e == "enter trak").count(), 1);
+
+        // Containers for second track are exited at end of file
+        let events = visit(1, vec![("exit", "mdia", Visit::Stop)]);
+        assert_eq!(events[events.len() - 3 ..], ["exit stbl", "exit minf", "exit mdia"]);
+    }
+}